@@ -0,0 +1,102 @@
+// benches/throughput.rs
+//! Lines per second through the filters alone, and through the whole
+//! per-line path (filters, tags, redaction and rendering).
+//!
+//! Run with `cargo bench`.
+
+use criterion::{BatchSize, Criterion, Throughput, criterion_group, criterion_main};
+use logscout::{Config, Filters, LogLine, StreamFilter};
+use std::hint::black_box;
+use std::io;
+
+/// Lines in the in-memory corpus.
+const CORPUS_LINES: usize = 100_000;
+
+/// Filters like those of a typical web service config.
+const CONFIG: &str = r#"
+follow: false
+include:
+  - 'ERROR|WARN'
+  - '\bHTTP/1\.[01]" 5\d\d '
+  - 'timeout|timed out'
+  - 'user_id=\d+'
+exclude:
+  - 'GET /healthz'
+  - 'DEBUG'
+  - '^\S+ \S+ \S+ \[[^\]]+\] "GET /static/'
+tags:
+  - name: server-error
+    pattern: '" 5\d\d '
+  - name: slow
+    pattern: 'duration_ms=\d{4,}'
+redact:
+  - pattern: 'password=\S+'
+sources:
+  - name: app
+    type: command
+    command: "true"
+"#;
+
+/// A mix of access-log and application lines, some of which every
+/// pattern above matches.
+fn corpus() -> Vec<String> {
+    let paths = ["/api/orders", "/healthz", "/static/app.js", "/api/users/42"];
+    let statuses = [200, 200, 200, 404, 500, 503];
+    let levels = ["INFO", "DEBUG", "WARN", "ERROR", "INFO"];
+    (0..CORPUS_LINES)
+        .map(|i| {
+            if i % 3 == 0 {
+                format!(
+                    "10.0.{}.{} - - [10/Oct/2024:13:55:{:02} +0000] \"GET {} HTTP/1.1\" {} {}",
+                    i % 256,
+                    i % 97,
+                    i % 60,
+                    paths[i % paths.len()],
+                    statuses[i % statuses.len()],
+                    i * 7 % 10_000
+                )
+            } else {
+                format!(
+                    "2024-10-10T13:55:{:02}.{:03}Z {} worker-{} handled request user_id={} \
+                     duration_ms={} password=hunter{} status=ok",
+                    i % 60,
+                    i % 1000,
+                    levels[i % levels.len()],
+                    i % 16,
+                    i % 5000,
+                    i * 13 % 3000,
+                    i % 10
+                )
+            }
+        })
+        .collect()
+}
+
+fn throughput(c: &mut Criterion) {
+    let cfg: Config = serde_yaml::from_str(CONFIG).expect("benchmark config");
+    let corpus = corpus();
+    let lines: Vec<LogLine> = corpus.iter().map(|l| LogLine::new("app", l)).collect();
+
+    let mut group = c.benchmark_group("throughput");
+    group.throughput(Throughput::Elements(CORPUS_LINES as u64));
+
+    let filters = Filters::from_config(&cfg).expect("filters");
+    let refs: Vec<&str> = corpus.iter().map(String::as_str).collect();
+    group.bench_function("classify", |b| {
+        b.iter(|| black_box(filters.classify_all(black_box(&refs))))
+    });
+
+    let mut stream = StreamFilter::new(&cfg, io::sink()).expect("stream filter");
+    group.bench_function("stream", |b| {
+        b.iter_batched(
+            || lines.clone(),
+            |batch| stream.run(batch).expect("write to sink"),
+            BatchSize::LargeInput,
+        )
+    });
+
+    group.finish();
+}
+
+criterion_group!(benches, throughput);
+criterion_main!(benches);
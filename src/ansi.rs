@@ -0,0 +1,87 @@
+// src/ansi.rs
+//! Removing ANSI escape sequences (colors, cursor movement, terminal
+//! titles) from lines read from colorizing programs (`strip_ansi`).
+
+const ESC: u8 = 0x1b;
+const BEL: u8 = 0x07;
+
+/// Remove every escape sequence from `line`, in place.
+///
+/// Handles CSI sequences (`ESC [ ... m` and the like), OSC sequences
+/// (`ESC ] ... BEL` or `ESC ] ... ESC \`), and two-byte escapes such as
+/// `ESC (B`. A sequence cut off by the end of the line is dropped too.
+pub fn strip(line: &mut Vec<u8>) {
+    // Most lines have no escapes at all
+    let Some(first) = line.iter().position(|&b| b == ESC) else {
+        return;
+    };
+
+    let mut out = first;
+    let mut i = first;
+    while i < line.len() {
+        if line[i] != ESC {
+            line[out] = line[i];
+            out += 1;
+            i += 1;
+            continue;
+        }
+        i = skip_sequence(line, i + 1);
+    }
+    line.truncate(out);
+}
+
+/// Index just past the escape sequence whose `ESC` is right before `i`.
+fn skip_sequence(line: &[u8], i: usize) -> usize {
+    match line.get(i) {
+        // CSI: parameter and intermediate bytes, then one final byte
+        Some(b'[') => line[i + 1..]
+            .iter()
+            .position(|b| (0x40..=0x7e).contains(b))
+            .map_or(line.len(), |p| i + 1 + p + 1),
+        // OSC, DCS and friends: up to BEL or the string terminator `ESC \`
+        Some(b']' | b'P' | b'X' | b'^' | b'_') => {
+            let mut j = i + 1;
+            while j < line.len() {
+                match line[j] {
+                    BEL => return j + 1,
+                    ESC if line.get(j + 1) == Some(&b'\\') => return j + 2,
+                    _ => j += 1,
+                }
+            }
+            line.len()
+        }
+        // Character set selection takes one more byte, e.g. `ESC ( B`
+        Some(b'(' | b')' | b'*' | b'+') => (i + 2).min(line.len()),
+        // Any other two-byte escape, e.g. `ESC =` or `ESC 7`
+        Some(_) => i + 1,
+        None => i,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn stripped(line: &[u8]) -> Vec<u8> {
+        let mut line = line.to_vec();
+        strip(&mut line);
+        line
+    }
+
+    #[test]
+    fn escapes_are_removed() {
+        // CSI colors
+        assert_eq!(
+            stripped(b"\x1b[1;31mERROR\x1b[0m disk full"),
+            b"ERROR disk full"
+        );
+        // OSC window titles, ended by BEL or `ESC \`
+        assert_eq!(stripped(b"\x1b]0;build\x07done"), b"done");
+        assert_eq!(stripped(b"\x1b]8;;http://x\x1b\\link"), b"link");
+        // A bare ESC, or a sequence cut off, at the end of the line
+        assert_eq!(stripped(b"progress 50%\x1b"), b"progress 50%");
+        assert_eq!(stripped(b"tail \x1b[3"), b"tail ");
+        // Nothing to do
+        assert_eq!(stripped(b"plain [1m text"), b"plain [1m text");
+    }
+}
@@ -0,0 +1,249 @@
+// src/cli.rs
+use std::path::PathBuf;
+use thiserror::Error;
+
+pub const USAGE: &str = "\
+Usage: logscout [OPTIONS] [CONFIG]...
+
+Arguments:
+  [CONFIG]...              Config files (YAML, or TOML/JSON by extension), merged in
+                           order (default: config.yaml); `-` reads YAML from stdin
+
+Options:
+  -c, --config <PATH>      Config file (repeatable, same as a positional path)
+  -q, --quiet              Print no lines, only the summary (same as `quiet: true`)
+      --only <SOURCE>      Read only this source (repeatable)
+      --mute <SOURCE>      Don't read this source (repeatable)
+      --duration <TIME>    Stop after this long, e.g. 90s, 5m or 2h (same as `duration_secs`)
+      --max-lines <N>      Stop after this many lines from all sources (same as `max_lines`)
+      --stats-json         Print the final summary as a JSON object
+      --print-config[=FMT] Print the resolved config as yaml (default) or json, then exit
+      --no-unicode         Use ASCII level icons such as [E] (same as `output.no_unicode`)
+      --ordered            Read sources one at a time, in config order (same as `ordered: true`)
+  -h, --help               Print this help and exit
+  -V, --version            Print version and exit
+";
+
+/// Options for a normal run.
+#[derive(Debug, Clone)]
+pub struct Cli {
+    /// Configuration files, merged in order.
+    pub configs: Vec<PathBuf>,
+
+    /// Print the final summary as JSON instead of text.
+    pub stats_json: bool,
+
+    /// Print no lines, whatever the config says.
+    pub quiet: bool,
+
+    /// Read only these sources (all if empty).
+    pub only: Vec<String>,
+
+    /// Skip these sources.
+    pub mute: Vec<String>,
+
+    /// ASCII level icons, whatever the config says.
+    pub no_unicode: bool,
+
+    /// Read sources one after another, whatever the config says.
+    pub ordered: bool,
+
+    /// Print the resolved config in this format instead of running.
+    pub print_config: Option<PrintFormat>,
+
+    /// Stop after this many seconds, whatever the config says.
+    pub duration_secs: Option<u64>,
+
+    /// Stop after this many lines, whatever the config says.
+    pub max_lines: Option<u64>,
+}
+
+/// Format of `--print-config`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PrintFormat {
+    Yaml,
+    Json,
+}
+
+/// What the user asked us to do.
+#[derive(Debug)]
+pub enum Command {
+    Run(Cli),
+    Help,
+    Version,
+}
+
+#[derive(Debug, Error)]
+pub enum CliError {
+    #[error("unknown option `{0}`")]
+    UnknownFlag(String),
+
+    #[error("option `{0}` requires a value")]
+    MissingValue(String),
+
+    #[error("invalid value `{value}` for `{flag}`: {reason}")]
+    InvalidValue {
+        flag: String,
+        value: String,
+        reason: &'static str,
+    },
+}
+
+impl Cli {
+    /// Parse command-line arguments (without the program name).
+    ///
+    /// Config paths may be given positionally or with `-c`, in any mix;
+    /// they are merged in the order given.
+    pub fn parse<I>(args: I) -> Result<Command, CliError>
+    where
+        I: IntoIterator<Item = String>,
+    {
+        let mut args = args.into_iter();
+        let mut configs: Vec<PathBuf> = Vec::new();
+        let mut stats_json = false;
+        let mut quiet = false;
+        let mut no_unicode = false;
+        let mut ordered = false;
+        let mut print_config = None;
+        let mut only = Vec::new();
+        let mut mute = Vec::new();
+        let mut duration_secs = None;
+        let mut max_lines = None;
+
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "-h" | "--help" => return Ok(Command::Help),
+                "-V" | "--version" => return Ok(Command::Version),
+                "--stats-json" => stats_json = true,
+                "-q" | "--quiet" => quiet = true,
+                "--no-unicode" => no_unicode = true,
+                "--ordered" => ordered = true,
+                "--print-config" => print_config = Some(PrintFormat::Yaml),
+                _ if arg.starts_with("--print-config=") => {
+                    let value = &arg["--print-config=".len()..];
+                    print_config = Some(parse_print_format(value)?);
+                }
+                "-c" | "--config" => {
+                    let value = args
+                        .next()
+                        .ok_or_else(|| CliError::MissingValue(arg.clone()))?;
+                    configs.push(PathBuf::from(value));
+                }
+                _ if arg.starts_with("--config=") => {
+                    configs.push(PathBuf::from(&arg["--config=".len()..]));
+                }
+                "--only" | "--mute" => {
+                    let value = args
+                        .next()
+                        .ok_or_else(|| CliError::MissingValue(arg.clone()))?;
+                    if arg == "--only" {
+                        only.push(value);
+                    } else {
+                        mute.push(value);
+                    }
+                }
+                _ if arg.starts_with("--only=") => only.push(arg["--only=".len()..].to_string()),
+                _ if arg.starts_with("--mute=") => mute.push(arg["--mute=".len()..].to_string()),
+                "--duration" => {
+                    let value = args
+                        .next()
+                        .ok_or_else(|| CliError::MissingValue(arg.clone()))?;
+                    duration_secs = Some(parse_duration_secs(&arg, &value)?);
+                }
+                _ if arg.starts_with("--duration=") => {
+                    let value = &arg["--duration=".len()..];
+                    duration_secs = Some(parse_duration_secs("--duration", value)?);
+                }
+                "--max-lines" => {
+                    let value = args
+                        .next()
+                        .ok_or_else(|| CliError::MissingValue(arg.clone()))?;
+                    max_lines = Some(parse_count(&arg, &value)?);
+                }
+                _ if arg.starts_with("--max-lines=") => {
+                    let value = &arg["--max-lines=".len()..];
+                    max_lines = Some(parse_count("--max-lines", value)?);
+                }
+                _ if arg.starts_with('-') && arg.len() > 1 => {
+                    return Err(CliError::UnknownFlag(arg));
+                }
+                _ => configs.push(PathBuf::from(arg)),
+            }
+        }
+
+        if configs.is_empty() {
+            configs.push(PathBuf::from("config.yaml"));
+        }
+
+        Ok(Command::Run(Cli {
+            configs,
+            stats_json,
+            quiet,
+            only,
+            mute,
+            no_unicode,
+            ordered,
+            print_config,
+            duration_secs,
+            max_lines,
+        }))
+    }
+}
+
+/// Parse a duration such as `90`, `90s`, `5m` or `2h` into seconds.
+fn parse_duration_secs(flag: &str, value: &str) -> Result<u64, CliError> {
+    let invalid = |reason| CliError::InvalidValue {
+        flag: flag.to_string(),
+        value: value.to_string(),
+        reason,
+    };
+
+    let (number, unit) = match value.find(|c: char| !c.is_ascii_digit()) {
+        Some(i) => value.split_at(i),
+        None => (value, "s"),
+    };
+    let scale = match unit {
+        "s" => 1,
+        "m" => 60,
+        "h" => 60 * 60,
+        _ => {
+            return Err(invalid(
+                "expected a number with an optional s, m or h suffix",
+            ));
+        }
+    };
+    let secs = number
+        .parse::<u64>()
+        .ok()
+        .and_then(|n| n.checked_mul(scale))
+        .ok_or_else(|| invalid("expected a number with an optional s, m or h suffix"))?;
+    if secs == 0 {
+        return Err(invalid("must be greater than zero"));
+    }
+    Ok(secs)
+}
+
+/// Parse the format of `--print-config=<FORMAT>`: `yaml` or `json`.
+fn parse_print_format(value: &str) -> Result<PrintFormat, CliError> {
+    match value {
+        "yaml" => Ok(PrintFormat::Yaml),
+        "json" => Ok(PrintFormat::Json),
+        _ => Err(CliError::InvalidValue {
+            flag: "--print-config".to_string(),
+            value: value.to_string(),
+            reason: "expected `yaml` or `json`",
+        }),
+    }
+}
+
+/// Parse a positive count such as `1000`.
+fn parse_count(flag: &str, value: &str) -> Result<u64, CliError> {
+    match value.parse::<u64>() {
+        Ok(n) if n > 0 => Ok(n),
+        _ => Err(CliError::InvalidValue {
+            flag: flag.to_string(),
+            value: value.to_string(),
+            reason: "expected a positive whole number",
+        }),
+    }
+}
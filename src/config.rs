@@ -1,14 +1,33 @@
 // src/config.rs
-use regex::Error;
-use serde::Deserialize;
+use crate::context::ContextConfig;
+use crate::fields::{FieldFilterConfig, JsonFilterConfig, ParseFormat};
+use crate::filters;
+use crate::follow;
+use crate::health::HealthConfig;
+use crate::level::Level;
+use crate::multiline::MultilineConfig;
+use crate::output::{Color, Destination, OutputConfig, OutputFormat};
+use crate::raw_output::RawOutputConfig;
+use crate::redact::RedactConfig;
+use crate::rollup::RollupConfig;
+use crate::sample::SampleConfig;
+use crate::syslog::Severity;
+use crate::tags::TagRule;
+use crate::tcp::TlsConfig;
+use crate::timeutil::{self, TimeRange};
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
 use std::{
+    collections::HashMap,
     fs, io,
+    ops::RangeInclusive,
     path::{Path, PathBuf},
 };
 use thiserror::Error;
 
 #[allow(dead_code)]
-#[derive(Debug, Deserialize, Clone)]
+#[derive(Debug, Deserialize, Serialize, Clone)]
+#[serde(deny_unknown_fields)]
 pub struct Config {
     /// Whether to follow files like `tail -F`.
     pub follow: bool,
@@ -17,35 +36,587 @@ pub struct Config {
     #[serde(default)]
     pub include: Vec<String>,
 
+    /// Lines must also match every one of these (if not empty).
+    #[serde(default)]
+    pub include_all: Vec<String>,
+
     /// Lines must NOT match any of these.
     #[serde(default)]
     pub exclude: Vec<String>,
 
+    /// Whether patterns may match anywhere in a line or must match all of it.
+    #[serde(default)]
+    pub match_mode: MatchMode,
+
+    /// Whether excludes or includes win when a line matches both.
+    #[serde(default)]
+    pub precedence: Precedence,
+
+    /// Whether filters see lines before or after transforms (redaction).
+    #[serde(default)]
+    pub filter_stage: FilterStage,
+
+    /// Conditions on parsed fields; all must hold for a parsed line to pass.
+    #[serde(default)]
+    pub field_filters: Vec<FieldFilterConfig>,
+
+    /// Drop lines below this severity (if set).
+    #[serde(default)]
+    pub min_level: Option<Level>,
+
+    /// Locates the level token for `min_level` (first capture group).
+    #[serde(default)]
+    pub level_pattern: Option<String>,
+
+    /// Keep lines whose level cannot be recognized when `min_level` is set.
+    #[serde(default = "default_keep_unleveled")]
+    pub keep_unleveled: bool,
+
+    /// Drop syslog lines whose `<PRI>` severity is below this (if set).
+    #[serde(default)]
+    pub min_severity: Option<Severity>,
+
+    /// Treat patterns as literal substrings (`grep -F`), for both lists
+    /// (`true`) or only the named ones (`[include]`, `[exclude]`).
+    #[serde(default)]
+    pub literal: Literal,
+
+    /// Invert the include list: show lines matching none of its patterns.
+    #[serde(default)]
+    pub invert: bool,
+
     /// Log sources to read.
     pub sources: Vec<SourceConfig>,
+
+    /// Skip the sources whose names match any of these regexes.
+    #[serde(default)]
+    pub exclude_sources: Vec<String>,
+
+    /// Settings applied to every source that doesn't set them itself.
+    #[serde(default)]
+    pub defaults: SourceDefaults,
+
+    /// Upper bound (bytes) on the compiled size of each user-supplied regex.
+    #[serde(default = "default_regex_size_limit")]
+    pub regex_size_limit: usize,
+
+    /// Lines that may wait for the consumer before readers block.
+    #[serde(default = "default_queue_capacity")]
+    pub queue_capacity: usize,
+
+    /// Size of each reader's input buffer (file, pipe, command and TCP
+    /// sources).
+    #[serde(default = "default_read_buffer_bytes")]
+    pub read_buffer_bytes: usize,
+
+    /// Longer lines are cut to this many bytes as they are read.
+    #[serde(default = "default_max_line_bytes")]
+    pub max_line_bytes: usize,
+
+    /// Stop once this many bytes of output have been emitted (if set).
+    #[serde(default)]
+    pub max_output_bytes: Option<u64>,
+
+    /// Also show unmatched lines around each emitted line (like `grep -C`).
+    #[serde(default)]
+    pub context: Option<ContextConfig>,
+
+    /// Reproduce the gaps between the lines' own timestamps when emitting.
+    #[serde(default)]
+    pub replay: bool,
+
+    /// Replay this many times faster than real time.
+    #[serde(default = "default_replay_speed")]
+    pub replay_speed: f64,
+
+    /// Hold lines back this many milliseconds and emit them sorted by
+    /// timestamp, so lines from different sources interleave in order.
+    #[serde(default)]
+    pub reorder_window_ms: Option<u64>,
+
+    /// Drop lines whose own timestamp is before this RFC 3339 time.
+    #[serde(default)]
+    pub since: Option<String>,
+
+    /// Drop lines whose own timestamp is at or after this RFC 3339 time.
+    #[serde(default)]
+    pub until: Option<String>,
+
+    /// Keep lines without a recognizable timestamp when `since` or `until`
+    /// is set.
+    #[serde(default = "default_keep_untimed")]
+    pub keep_untimed: bool,
+
+    /// Emit only every Nth line that passes the filters (if set).
+    #[serde(default)]
+    pub sample: Option<SampleConfig>,
+
+    /// Drop lines identical to one emitted within this many seconds.
+    #[serde(default)]
+    pub dedup_window_secs: Option<u64>,
+
+    /// Most distinct lines remembered for `dedup_window_secs`.
+    #[serde(default = "default_dedup_capacity")]
+    pub dedup_capacity: usize,
+
+    /// Match patterns against the raw bytes of each line instead of
+    /// a (lossily decoded) UTF-8 string.
+    #[serde(default)]
+    pub binary_match: bool,
+
+    /// Stop a source at the first line that is not valid UTF-8 instead of
+    /// replacing the invalid sequences.
+    #[serde(default)]
+    pub strict_utf8: bool,
+
+    /// Remove ANSI escape sequences (e.g. colors) from lines as they are read.
+    #[serde(default)]
+    pub strip_ansi: bool,
+
+    /// Remove every carriage return (`\r`) from lines as they are read.
+    /// A `\r\n` ending is stripped anyway; this also drops a `\r` before
+    /// a missing last newline, doubled `\r\r\n` endings and `\r` inside
+    /// a line.
+    #[serde(default)]
+    pub normalize_crlf: bool,
+
+    /// Remove leading and trailing whitespace from each line (or
+    /// multiline record).
+    #[serde(default)]
+    pub trim_whitespace: bool,
+
+    /// Drop empty and whitespace-only lines before filtering.
+    #[serde(default)]
+    pub skip_empty_lines: bool,
+
+    /// Count lines without writing them to the main output; the other
+    /// outputs, dedup and sampling work as usual.
+    #[serde(default)]
+    pub quiet: bool,
+
+    /// Like `quiet`, and count every include pattern a line matches rather
+    /// than only the first, to report how many lines matched each.
+    #[serde(default)]
+    pub count_only: bool,
+
+    /// Read the sources one at a time, in config order, each to its end
+    /// before the next starts, so finite inputs give the same output on
+    /// every run.
+    #[serde(default)]
+    pub ordered: bool,
+
+    /// Threads matching lines against the filters. Output is still written
+    /// by one thread, in order.
+    #[serde(default = "default_workers")]
+    pub workers: usize,
+
+    /// Whether a source that can't be opened ends the whole run.
+    #[serde(default)]
+    pub on_source_error: OnSourceError,
+
+    /// Write a `<EOF>` marker line when a source ends (command exited, file
+    /// read to the end, ...).
+    #[serde(default)]
+    pub emit_lifecycle_events: bool,
+
+    /// Stop reading and print the summary after this many seconds (if set).
+    #[serde(default)]
+    pub duration_secs: Option<u64>,
+
+    /// Stop and print the summary after this many lines from all sources
+    /// (if set).
+    #[serde(default)]
+    pub max_lines: Option<u64>,
+
+    /// How long readers get to stop at the end of a run before they are
+    /// left behind (0: don't wait).
+    #[serde(default = "default_shutdown_timeout_secs")]
+    pub shutdown_timeout_secs: u64,
+
+    /// Print a rolling stats snapshot to stderr this often (if set).
+    #[serde(default)]
+    pub stats_interval_secs: Option<u64>,
+
+    /// Show this many of the first and of the last included lines of each
+    /// source in the summary.
+    #[serde(default)]
+    pub summary_samples: usize,
+
+    /// Serve a `/healthz` readiness endpoint (if set).
+    #[serde(default)]
+    pub health: Option<HealthConfig>,
+
+    /// How matched lines are rendered.
+    #[serde(default)]
+    pub output: OutputConfig,
+
+    /// Tag each line with the first of these rules it matches.
+    #[serde(default)]
+    pub tags: Vec<TagRule>,
+
+    /// Mask secrets in lines before they are written anywhere.
+    #[serde(default, deserialize_with = "crate::redact::deserialize_redact")]
+    pub redact: RedactConfig,
+
+    /// Tee every line read (before filtering) into a file.
+    #[serde(default)]
+    pub raw_output: Option<RawOutputConfig>,
+
+    /// Periodically summarize the most frequent error lines into a file.
+    #[serde(default)]
+    pub rollup: Option<RollupConfig>,
+}
+
+pub(crate) fn default_regex_size_limit() -> usize {
+    2 * 1024 * 1024
+}
+
+fn default_dedup_capacity() -> usize {
+    100_000
+}
+
+fn default_workers() -> usize {
+    1
+}
+
+fn default_shutdown_timeout_secs() -> u64 {
+    2
+}
+
+fn default_replay_speed() -> f64 {
+    1.0
+}
+
+fn default_keep_unleveled() -> bool {
+    true
+}
+
+fn default_keep_untimed() -> bool {
+    true
+}
+
+fn default_queue_capacity() -> usize {
+    10_000
+}
+
+/// The same as `BufReader::new`.
+fn default_read_buffer_bytes() -> usize {
+    8 * 1024
+}
+
+fn default_max_line_bytes() -> usize {
+    1024 * 1024
+}
+
+/// Bounds of `read_buffer_bytes`.
+const READ_BUFFER_BYTES: RangeInclusive<usize> = 512..=64 * 1024 * 1024;
+
+#[derive(Debug, Deserialize, Serialize, Clone, Copy, Default, PartialEq, Eq)]
+pub enum MatchMode {
+    /// Pattern may match anywhere in the line (like `grep`).
+    #[default]
+    #[serde(rename = "substring")]
+    Substring,
+
+    /// Pattern must match the entire line (like `grep -x`).
+    #[serde(rename = "whole_line")]
+    WholeLine,
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Precedence {
+    /// A line matching any exclude pattern is dropped.
+    #[default]
+    #[serde(rename = "exclude_first")]
+    ExcludeFirst,
+
+    /// A line matching an include pattern is kept even if it matches an
+    /// exclude pattern.
+    #[serde(rename = "include_first")]
+    IncludeFirst,
 }
 
-#[derive(Debug, Deserialize, Clone)]
+#[derive(Debug, Deserialize, Serialize, Clone, Copy, Default, PartialEq, Eq)]
+pub enum FilterStage {
+    /// Filters match the line as read; transforms apply to emitted lines.
+    #[default]
+    #[serde(rename = "pre_transform")]
+    PreTransform,
+
+    /// Transforms apply first and filters match the transformed line.
+    #[serde(rename = "post_transform")]
+    PostTransform,
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone, PartialEq, Eq)]
+#[serde(untagged)]
+pub enum Literal {
+    /// Applies to every pattern list (or none).
+    All(bool),
+
+    /// Applies only to the listed pattern lists.
+    Lists(Vec<PatternList>),
+}
+
+impl Default for Literal {
+    fn default() -> Self {
+        Literal::All(false)
+    }
+}
+
+impl Literal {
+    /// Whether patterns in `list` should be matched literally.
+    pub fn applies_to(&self, list: PatternList) -> bool {
+        match self {
+            Literal::All(on) => *on,
+            Literal::Lists(lists) => lists.contains(&list),
+        }
+    }
+}
+
+/// What to do when a source can't be opened once the run has started
+/// (e.g. a file removed after validation, or a port already in use).
+#[derive(Debug, Deserialize, Serialize, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum OnSourceError {
+    /// Report it and keep reading the other sources.
+    #[default]
+    Ignore,
+    /// Report it, stop every source and exit with an error.
+    Fail,
+}
+
+/// The pattern lists that options like `literal` can target.
+#[derive(Debug, Deserialize, Serialize, Clone, Copy, PartialEq, Eq)]
+pub enum PatternList {
+    #[serde(rename = "include")]
+    Include,
+
+    #[serde(rename = "exclude")]
+    Exclude,
+}
+
+/// Per-source settings given once for all sources (`defaults`).
+#[derive(Debug, Deserialize, Serialize, Clone, Default)]
+#[serde(deny_unknown_fields)]
+pub struct SourceDefaults {
+    #[serde(default)]
+    pub multiline: Option<MultilineConfig>,
+
+    #[serde(default)]
+    pub parse: Option<ParseFormat>,
+
+    /// Only applied to file, dir, journald and windows_event sources.
+    #[serde(default)]
+    pub follow: Option<bool>,
+
+    #[serde(default)]
+    pub json_filter: Option<JsonFilterConfig>,
+
+    #[serde(default)]
+    pub max_lines: Option<u64>,
+
+    #[serde(default)]
+    pub color: Option<Color>,
+}
+
+impl SourceDefaults {
+    /// Fill in whatever `src` leaves unset.
+    fn apply(&self, src: &mut SourceConfig) {
+        if src.multiline.is_none() {
+            src.multiline = self.multiline.clone();
+        }
+        if src.parse.is_none() {
+            src.parse = self.parse;
+        }
+        if src.follow.is_none()
+            && matches!(
+                src.kind,
+                SourceKind::File { .. }
+                    | SourceKind::Dir { .. }
+                    | SourceKind::Journald { .. }
+                    | SourceKind::WindowsEvent { .. }
+            )
+        {
+            src.follow = self.follow;
+        }
+        if src.json_filter.is_none() {
+            src.json_filter = self.json_filter.clone();
+        }
+        src.max_lines = src.max_lines.or(self.max_lines);
+        src.color = src.color.or(self.color);
+    }
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone, PartialEq)]
 pub struct SourceConfig {
     /// Human-friendly name, printed in output.
     pub name: String,
 
+    /// Join continuation lines into a single record.
+    #[serde(default)]
+    pub multiline: Option<MultilineConfig>,
+
+    /// Parse each record into fields (for `field_filters` and JSON output).
+    #[serde(default)]
+    pub parse: Option<ParseFormat>,
+
+    /// Overrides the global `follow` for this source (file sources only).
+    #[serde(default)]
+    pub follow: Option<bool>,
+
+    /// Filter this source's JSON lines on a field instead of the regexes.
+    #[serde(default)]
+    pub json_filter: Option<JsonFilterConfig>,
+
+    /// Stop reading this source after this many lines (records).
+    #[serde(default)]
+    pub max_lines: Option<u64>,
+
+    /// Short name shown instead of `name` in text output, e.g. `NGX`.
+    #[serde(default)]
+    pub label: Option<String>,
+
+    /// Terminal color of the source name in text output, e.g. `red`.
+    #[serde(default)]
+    pub color: Option<Color>,
+
+    // serde cannot deny unknown fields across `flatten`; `SourceKind`
+    // rejects whatever keys neither struct knows.
     #[serde(flatten)]
     pub kind: SourceKind,
 }
 
-#[derive(Debug, Deserialize, Clone)]
-#[serde(tag = "type")] // "file" or "command"
+impl SourceConfig {
+    /// How lines are parsed into fields; `json_filter` implies JSON.
+    pub fn parse_format(&self) -> Option<ParseFormat> {
+        self.parse
+            .or(self.json_filter.as_ref().map(|_| ParseFormat::Json))
+    }
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone, PartialEq)]
+#[serde(tag = "type", deny_unknown_fields)] // "file", "command", "tcp", "journald" or "windows_event"
 pub enum SourceKind {
     #[serde(rename = "file")]
-    File { path: PathBuf },
+    File {
+        /// The file to read.
+        #[serde(default)]
+        path: Option<PathBuf>,
+        /// Files to read in order instead of `path`, e.g. rotated archives
+        /// followed by the live file.
+        #[serde(default)]
+        paths: Vec<PathBuf>,
+        /// Compression of the files; detected from the extension if unset.
+        #[serde(default)]
+        compression: Option<Compression>,
+        /// Where to start reading an existing file (the last one of `paths`).
+        #[serde(default)]
+        start_at: StartAt,
+        /// Start with the last this many lines of the existing file
+        /// (the last one of `paths`), like `tail -n`.
+        #[serde(default)]
+        tail: Option<u64>,
+    },
+
+    /// Every file in a directory whose name matches `pattern`, including
+    /// files created while logscout runs (with `follow`).
+    #[serde(rename = "dir")]
+    Dir {
+        path: PathBuf,
+        /// Regex matched against file names; all files if unset.
+        #[serde(default)]
+        pattern: Option<String>,
+    },
 
     #[serde(rename = "command")]
     Command {
         command: String,
         #[serde(default)]
         args: Vec<String>,
+        /// Also read the command's stderr, marked with a `stream: stderr` field.
+        #[serde(default)]
+        capture_stderr: bool,
     },
+
+    /// Lines sent over TCP, e.g. by a log forwarder.
+    #[serde(rename = "tcp")]
+    Tcp {
+        /// Address to listen on, e.g. `0.0.0.0:5140`.
+        bind: String,
+        /// Serve TLS (needs the `tls` feature).
+        #[serde(default)]
+        tls: Option<TlsConfig>,
+    },
+
+    /// The systemd journal (needs the `journald` feature, Linux only).
+    #[serde(rename = "journald")]
+    Journald {
+        /// Only entries of this unit, e.g. `nginx.service`.
+        #[serde(default)]
+        unit: Option<String>,
+        /// Read the existing journal, or only new entries.
+        #[serde(default)]
+        start_at: StartAt,
+    },
+
+    /// A Windows Event Log channel (needs the `windows_event` feature,
+    /// Windows only).
+    #[serde(rename = "windows_event")]
+    WindowsEvent {
+        /// The channel to read, e.g. `Application` or `System`.
+        channel: String,
+        /// Read the existing events, or only new ones.
+        #[serde(default)]
+        start_at: StartAt,
+    },
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone, Copy, Default, PartialEq, Eq)]
+pub enum StartAt {
+    /// Read the whole file (`tail -n +1`).
+    #[default]
+    #[serde(rename = "beginning")]
+    Beginning,
+
+    /// Skip what is already there and read only appended lines (`tail -n 0`).
+    #[serde(rename = "end")]
+    End,
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone, Copy, PartialEq, Eq)]
+pub enum Compression {
+    #[serde(rename = "none")]
+    None,
+
+    #[serde(rename = "gzip")]
+    Gzip,
+}
+
+impl Compression {
+    /// Resolve the effective compression for a file source.
+    /// An explicit setting wins; otherwise `.gz` files are treated as gzip.
+    pub fn resolve(explicit: Option<Compression>, path: &Path) -> Compression {
+        match explicit {
+            Some(c) => c,
+            None if path.extension().is_some_and(|ext| ext == "gz") => Compression::Gzip,
+            None => Compression::None,
+        }
+    }
+}
+
+/// Why a config file could not be deserialized, by format.
+#[derive(Debug, Error)]
+pub enum ParseError {
+    #[error(transparent)]
+    Yaml(#[from] serde_yaml::Error),
+
+    /// Boxed, as it is much larger than the others.
+    #[error(transparent)]
+    Toml(Box<toml::de::Error>),
+
+    #[error(transparent)]
+    Json(#[from] serde_json::Error),
 }
 
 #[derive(Debug, Error)]
@@ -57,10 +628,12 @@ pub enum ConfigError {
         path: String,
     },
 
-    #[error("Failed to parse YAML in `{path}`: {source}")]
+    #[error("Failed to parse {format} in `{path}`: {source}")]
     Parse {
         #[source]
-        source: serde_yaml::Error,
+        source: ParseError,
+        /// `YAML`, `TOML` or `JSON`; `config` for several merged files.
+        format: &'static str,
         path: String,
     },
 
@@ -84,7 +657,9 @@ pub enum ConfigError {
         source: io::Error,
     },
 
-    #[allow(dead_code)]
+    #[error("Source `{name}`: {message}")]
+    SourceFailed { name: String, message: String },
+
     #[error("Source `{name}`: failed to spawn command `{command}`: {source}")]
     SourceSpawn {
         name: String,
@@ -93,6 +668,20 @@ pub enum ConfigError {
         source: io::Error,
     },
 
+    #[error("Failed to open output file `{path}`: {source}")]
+    OutputIo {
+        path: String,
+        #[source]
+        source: io::Error,
+    },
+
+    #[error("Failed to listen on `{addr}`: {source}")]
+    OutputBind {
+        addr: String,
+        #[source]
+        source: io::Error,
+    },
+
     #[error("Invalid {kind} regex `{pattern}`: {source}")]
     InvalidRegex {
         kind: &'static str, // "include" or "exclude"
@@ -104,29 +693,345 @@ pub enum ConfigError {
 
 impl Config {
     /// Load and validate configuration from a YAML file.
+    ///
+    /// If `path` is a directory, every `*.yaml`/`*.yml` file in it is loaded
+    /// in name order and merged (see `from_dir`).
     pub fn from_file(path: &Path) -> Result<Self, ConfigError> {
-        let path_str = path.display().to_string();
+        if path.is_dir() {
+            return Self::from_dir(path);
+        }
 
-        let contents = fs::read_to_string(path).map_err(|e| ConfigError::Io {
-            source: e,
-            path: path_str.clone(),
+        let (path_str, contents) = read_config(path)?;
+        let mut cfg: Config = ConfigFormat::of(path).parse(&contents, path_str)?;
+
+        cfg.validate()?;
+        Ok(cfg)
+    }
+
+    /// Load several config files (or directories) and merge them in order.
+    ///
+    /// Merging works like a config directory (see `from_dir`), except that a
+    /// source defined in more than one file is kept once, from its first file.
+    pub fn from_files(paths: &[PathBuf]) -> Result<Self, ConfigError> {
+        if paths.iter().filter(|p| is_stdin(p)).count() > 1 {
+            return Err(ConfigError::Invalid(
+                "The config can be read from stdin (`-`) only once.".into(),
+            ));
+        }
+
+        let path = match paths {
+            [] => return Err(ConfigError::Invalid("No config file given.".into())),
+            [path] => return Self::from_file(path),
+            _ => paths
+                .iter()
+                .map(|p| config_label(p))
+                .collect::<Vec<_>>()
+                .join(", "),
+        };
+
+        let mut merged = serde_yaml::Value::Null;
+        for p in paths {
+            let value = if p.is_dir() {
+                Self::dir_value(p)?
+            } else {
+                let (p_str, contents) = read_config(p)?;
+                ConfigFormat::of(p).parse(&contents, p_str)?
+            };
+            merge_yaml(&mut merged, value);
+        }
+
+        // Sources repeated across files are dropped by `validate`
+        let mut cfg: Config = serde_yaml::from_value(merged).map_err(|e| ConfigError::Parse {
+            source: e.into(),
+            format: "config",
+            path,
         })?;
 
-        let mut cfg: Config = serde_yaml::from_str(&contents).map_err(|e| ConfigError::Parse {
-            source: e,
-            path: path_str.clone(),
+        cfg.validate()?;
+        Ok(cfg)
+    }
+
+    /// Load a drop-in config directory (like `conf.d/`).
+    ///
+    /// Fragments are merged in file name order: lists (`include`, `exclude`,
+    /// `sources`, ...) are concatenated, nested maps are merged, and other
+    /// values from later files override earlier ones. A source name defined
+    /// in more than one file is an error.
+    fn from_dir(dir: &Path) -> Result<Self, ConfigError> {
+        let merged = Self::dir_value(dir)?;
+
+        let mut cfg: Config = serde_yaml::from_value(merged).map_err(|e| ConfigError::Parse {
+            source: e.into(),
+            format: "config",
+            path: dir.display().to_string(),
         })?;
 
         cfg.validate()?;
         Ok(cfg)
     }
 
+    /// The merged YAML of every fragment in a config directory.
+    fn dir_value(dir: &Path) -> Result<serde_yaml::Value, ConfigError> {
+        let dir_str = dir.display().to_string();
+        let io_err = |e| ConfigError::Io {
+            source: e,
+            path: dir_str.clone(),
+        };
+
+        let mut files = Vec::new();
+        for entry in fs::read_dir(dir).map_err(io_err)? {
+            let path = entry.map_err(io_err)?.path();
+            let is_config = path
+                .extension()
+                .is_some_and(|ext| ["yaml", "yml", "toml", "json"].iter().any(|e| ext == *e));
+            if is_config && path.is_file() {
+                files.push(path);
+            }
+        }
+        files.sort();
+
+        if files.is_empty() {
+            return Err(ConfigError::Invalid(format!(
+                "No *.yaml, *.toml or *.json files found in config directory `{dir_str}`."
+            )));
+        }
+
+        let mut merged = serde_yaml::Value::Null;
+        let mut source_owners: HashMap<String, String> = HashMap::new();
+
+        for file in &files {
+            let file_str = file.display().to_string();
+            let contents = fs::read_to_string(file).map_err(|e| ConfigError::Io {
+                source: e,
+                path: file_str.clone(),
+            })?;
+            let value: serde_yaml::Value =
+                ConfigFormat::of(file).parse(&contents, file_str.clone())?;
+
+            // Reject source names that are already defined by another fragment
+            for name in source_names(&value) {
+                if let Some(owner) = source_owners.insert(name.clone(), file_str.clone()) {
+                    return Err(ConfigError::Invalid(format!(
+                        "Source `{name}` is defined in both `{owner}` and `{file_str}`."
+                    )));
+                }
+            }
+
+            merge_yaml(&mut merged, value);
+        }
+
+        Ok(merged)
+    }
+
+    /// Keep only the sources named in `only` (all of them if it is empty),
+    /// minus those in `mute`. Every name must be a configured source.
+    pub fn select_sources(&mut self, only: &[String], mute: &[String]) -> Result<(), ConfigError> {
+        if let Some(unknown) = only
+            .iter()
+            .chain(mute)
+            .find(|name| !self.sources.iter().any(|s| &s.name == *name))
+        {
+            return Err(ConfigError::Invalid(format!(
+                "No source named `{unknown}` in the config."
+            )));
+        }
+
+        self.sources
+            .retain(|s| (only.is_empty() || only.contains(&s.name)) && !mute.contains(&s.name));
+        if self.sources.is_empty() {
+            return Err(ConfigError::Invalid(
+                "No sources left to read after --only/--mute.".into(),
+            ));
+        }
+        Ok(())
+    }
+
     fn validate(&mut self) -> Result<(), ConfigError> {
+        for s in &mut self.sources {
+            self.defaults.apply(s);
+        }
+        self.quiet |= self.count_only;
+
         if self.sources.is_empty() {
             return Err(ConfigError::Invalid(
                 "At least one log source must be specified.".into(),
             ));
         }
+        self.exclude_sources_by_name()?;
+
+        if self.count_only && self.include.is_empty() && self.include_all.is_empty() {
+            return Err(ConfigError::Invalid(
+                "count_only needs at least one `include` or `include_all` pattern.".into(),
+            ));
+        }
+
+        if self.regex_size_limit == 0 {
+            return Err(ConfigError::Invalid(
+                "regex_size_limit must be greater than zero.".into(),
+            ));
+        }
+
+        if self.queue_capacity == 0 {
+            return Err(ConfigError::Invalid(
+                "queue_capacity must be greater than zero.".into(),
+            ));
+        }
+
+        if !READ_BUFFER_BYTES.contains(&self.read_buffer_bytes) {
+            return Err(ConfigError::Invalid(format!(
+                "read_buffer_bytes must be between {} and {}.",
+                READ_BUFFER_BYTES.start(),
+                READ_BUFFER_BYTES.end()
+            )));
+        }
+
+        if self.max_line_bytes == 0 {
+            return Err(ConfigError::Invalid(
+                "max_line_bytes must be greater than zero.".into(),
+            ));
+        }
+
+        if self.max_output_bytes == Some(0) {
+            return Err(ConfigError::Invalid(
+                "max_output_bytes must be greater than zero.".into(),
+            ));
+        }
+
+        if self.sample.as_ref().is_some_and(|s| s.rate == 0) {
+            return Err(ConfigError::Invalid(
+                "sample.rate must be greater than zero.".into(),
+            ));
+        }
+
+        if let Some(r) = &self.rollup
+            && (r.interval_secs == 0 || r.top == 0)
+        {
+            return Err(ConfigError::Invalid(
+                "rollup.interval_secs and rollup.top must be greater than zero.".into(),
+            ));
+        }
+
+        if let Some(s) = self.sources.iter().find(|s| s.max_lines == Some(0)) {
+            return Err(ConfigError::Invalid(format!(
+                "source `{}`: max_lines must be greater than zero.",
+                s.name
+            )));
+        }
+
+        if let Some(s) = self
+            .sources
+            .iter()
+            .find(|s| s.label.as_ref().is_some_and(|l| l.trim().is_empty()))
+        {
+            return Err(ConfigError::Invalid(format!(
+                "source `{}`: label must not be empty.",
+                s.name
+            )));
+        }
+
+        if self.dedup_window_secs == Some(0) || self.dedup_capacity == 0 {
+            return Err(ConfigError::Invalid(
+                "dedup_window_secs and dedup_capacity must be greater than zero.".into(),
+            ));
+        }
+
+        if !(self.replay_speed.is_finite() && self.replay_speed > 0.0) {
+            return Err(ConfigError::Invalid(
+                "replay_speed must be a positive number.".into(),
+            ));
+        }
+
+        if self.reorder_window_ms == Some(0) {
+            return Err(ConfigError::Invalid(
+                "reorder_window_ms must be greater than zero.".into(),
+            ));
+        }
+
+        let range = self.time_range()?;
+        if let Some(TimeRange {
+            since: Some(since),
+            until: Some(until),
+            ..
+        }) = range
+            && since >= until
+        {
+            return Err(ConfigError::Invalid("since must be before until.".into()));
+        }
+
+        if self.duration_secs == Some(0) {
+            return Err(ConfigError::Invalid(
+                "duration_secs must be greater than zero.".into(),
+            ));
+        }
+
+        if self.workers == 0 {
+            return Err(ConfigError::Invalid(
+                "workers must be greater than zero.".into(),
+            ));
+        }
+
+        if self.max_lines == Some(0) {
+            return Err(ConfigError::Invalid(
+                "max_lines must be greater than zero.".into(),
+            ));
+        }
+
+        if self.stats_interval_secs == Some(0) {
+            return Err(ConfigError::Invalid(
+                "stats_interval_secs must be greater than zero.".into(),
+            ));
+        }
+
+        if self.output.flush_interval_ms == 0 {
+            return Err(ConfigError::Invalid(
+                "output.flush_interval_ms must be greater than zero.".into(),
+            ));
+        }
+
+        // Report template mistakes before any output starts
+        self.output.text_template()?;
+
+        if let Some(dir) = &self.output.dir {
+            dir.validate()?;
+        }
+
+        if let Some(hook) = &self.output.webhook {
+            hook.validate()?;
+        }
+
+        if cfg!(not(feature = "websocket")) && self.output.websocket.is_some() {
+            return Err(ConfigError::Invalid(
+                "output.websocket requires logscout to be built with the `websocket` feature."
+                    .into(),
+            ));
+        }
+
+        if self.output.level_icons && self.output.format != OutputFormat::Text {
+            return Err(ConfigError::Invalid(
+                "output.level_icons only applies to the text format.".into(),
+            ));
+        }
+        if self.output.no_unicode && !self.output.level_icons {
+            return Err(ConfigError::Invalid(
+                "output.no_unicode has no effect without `level_icons`.".into(),
+            ));
+        }
+
+        let syslog = self.output.destination == Destination::Syslog;
+        if syslog && cfg!(not(unix)) {
+            return Err(ConfigError::Invalid(
+                "output.destination `syslog` is only supported on Unix.".into(),
+            ));
+        }
+        if !syslog
+            && (self.output.facility.is_some()
+                || self.output.severity.is_some()
+                || self.output.syslog_socket.is_some())
+        {
+            return Err(ConfigError::Invalid(
+                "output.facility, severity and syslog_socket require `destination: syslog`.".into(),
+            ));
+        }
 
         // basic sanity checks
         for s in &self.sources {
@@ -134,11 +1039,139 @@ impl Config {
             if s.name.trim().is_empty() {
                 return Err(ConfigError::Invalid("Source name cannot be empty.".into()));
             }
+
+            if let Some(ml) = &s.multiline {
+                ml.validate(&s.name, self.regex_size_limit)?;
+            }
+
+            if s.json_filter.is_some() && s.parse.is_some_and(|p| p != ParseFormat::Json) {
+                return Err(ConfigError::Invalid(format!(
+                    "source `{}`: `json_filter` requires `parse: json` (or no `parse`).",
+                    s.name
+                )));
+            }
+
+            if s.follow.is_some()
+                && matches!(s.kind, SourceKind::Command { .. } | SourceKind::Tcp { .. })
+            {
+                eprintln!(
+                    "[logscout] source `{}`: `follow` only applies to file, dir, journald and windows_event sources, ignoring it",
+                    s.name
+                );
+            }
         }
 
         self.dedup_sources_by_name();
         self.validate_sources()?;
+        if self.ordered {
+            self.check_ordered()?;
+        }
+
+        Ok(())
+    }
+
+    /// The settings a reload doesn't apply that differ in `new`: outputs,
+    /// and limits and machinery set up when the run starts.
+    pub fn restart_only_changes(&self, new: &Config) -> Vec<&'static str> {
+        const RESTART_ONLY: &[&str] = &[
+            "output",
+            "raw_output",
+            "rollup",
+            "health",
+            "max_lines",
+            "max_output_bytes",
+            "duration_secs",
+            "stats_interval_secs",
+            "shutdown_timeout_secs",
+            "queue_capacity",
+            "reorder_window_ms",
+            "replay",
+            "replay_speed",
+            "workers",
+            "on_source_error",
+        ];
+        let (Ok(old), Ok(new)) = (serde_json::to_value(self), serde_json::to_value(new)) else {
+            return Vec::new();
+        };
+        RESTART_ONLY
+            .iter()
+            .copied()
+            .filter(|key| old.get(key) != new.get(key))
+            .collect()
+    }
 
+    /// Check that every source ends, as `ordered` reads them one after
+    /// another: no `tcp` sources, and none that are followed.
+    pub fn check_ordered(&self) -> Result<(), ConfigError> {
+        for s in &self.sources {
+            let endless = match s.kind {
+                SourceKind::Tcp { .. } => Some("tcp sources never end"),
+                SourceKind::Command { .. } => None,
+                _ if s.follow.unwrap_or(self.follow) => Some("followed sources never end"),
+                _ => None,
+            };
+            if let Some(reason) = endless {
+                return Err(ConfigError::Invalid(format!(
+                    "source `{}`: `ordered` reads sources one after another, but {}.",
+                    s.name, reason
+                )));
+            }
+        }
+        Ok(())
+    }
+
+    /// The `since`/`until` range, if either is set.
+    pub fn time_range(&self) -> Result<Option<TimeRange>, ConfigError> {
+        let parse = |key: &str, value: &Option<String>| {
+            value
+                .as_deref()
+                .map(|v| {
+                    timeutil::parse_iso8601(v).ok_or_else(|| {
+                        ConfigError::Invalid(format!(
+                            "{key}: `{v}` is not an RFC 3339 time such as `2024-01-01T00:00:00Z`."
+                        ))
+                    })
+                })
+                .transpose()
+        };
+        let since = parse("since", &self.since)?;
+        let until = parse("until", &self.until)?;
+        Ok((since.is_some() || until.is_some()).then_some(TimeRange {
+            since,
+            until,
+            keep_untimed: self.keep_untimed,
+        }))
+    }
+
+    /// Drop the sources whose names match an `exclude_sources` pattern,
+    /// and say which ones were skipped.
+    fn exclude_sources_by_name(&mut self) -> Result<(), ConfigError> {
+        if self.exclude_sources.is_empty() {
+            return Ok(());
+        }
+        let patterns = self
+            .exclude_sources
+            .iter()
+            .map(|p| filters::build_regex(p, "exclude_sources", self.regex_size_limit))
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let (skipped, kept): (Vec<_>, Vec<_>) = self
+            .sources
+            .drain(..)
+            .partition(|s| patterns.iter().any(|re| re.is_match(&s.name)));
+        self.sources = kept;
+        if !skipped.is_empty() {
+            let names: Vec<&str> = skipped.iter().map(|s| s.name.as_str()).collect();
+            eprintln!(
+                "[logscout] Skipping sources matching exclude_sources: {}",
+                names.join(", ")
+            );
+        }
+        if self.sources.is_empty() {
+            return Err(ConfigError::Invalid(
+                "exclude_sources excludes every source.".into(),
+            ));
+        }
         Ok(())
     }
 
@@ -151,55 +1184,336 @@ impl Config {
 
     /// Validate that sources are accessible and valid.
     fn validate_sources(&self) -> Result<(), ConfigError> {
-        use std::io::ErrorKind;
-
         for s in &self.sources {
             match &s.kind {
-                // Check that the given log file exists and is a regular file.
-                SourceKind::File { path } => {
-                    let name = s.name.clone();
-                    let path_str = path.display().to_string();
-
-                    let meta = match fs::metadata(path) {
-                        Ok(m) => m,
-                        Err(e) => {
-                            return match e.kind() {
-                                ErrorKind::NotFound => Err(ConfigError::SourceFileNotFound {
-                                    name,
-                                    path: path_str,
-                                }),
-                                ErrorKind::PermissionDenied => Err(ConfigError::SourceIo {
-                                    name,
-                                    path: path_str,
-                                    source: e,
-                                }),
-                                _ => Err(ConfigError::SourceIo {
-                                    name,
-                                    path: path_str,
-                                    source: e,
-                                }),
-                            }?;
+                // Check that the given log files exist and are regular files.
+                SourceKind::File {
+                    path,
+                    paths,
+                    compression,
+                    start_at,
+                    tail,
+                } => {
+                    if path.is_some() != paths.is_empty() {
+                        return Err(ConfigError::Invalid(format!(
+                            "source `{}`: set either `path` or a non-empty `paths` list.",
+                            s.name
+                        )));
+                    }
+                    if tail.is_some() {
+                        let last = paths.last().or(path.as_ref()).expect("checked above");
+                        if *start_at == StartAt::End {
+                            return Err(ConfigError::Invalid(format!(
+                                "source `{}`: set either `tail` or `start_at: end`, not both.",
+                                s.name
+                            )));
+                        }
+                        if Compression::resolve(*compression, last) != Compression::None {
+                            return Err(ConfigError::Invalid(format!(
+                                "source `{}`: `tail` is not supported for compressed files.",
+                                s.name
+                            )));
                         }
-                    };
-                    if !meta.is_file() {
-                        return Err(ConfigError::SourceNotAFile {
-                            name,
-                            path: path_str,
-                        });
+                    }
+                    for path in path.iter().chain(paths) {
+                        Self::validate_file(&s.name, path)?;
                     }
                 }
 
-                // Check that the command is not empty. (Later we try to spawn it to verify.)
+                SourceKind::Dir { path, pattern } => {
+                    if !path.is_dir() {
+                        return Err(ConfigError::Invalid(format!(
+                            "source `{}`: `{}` is not a directory.",
+                            s.name,
+                            path.display()
+                        )));
+                    }
+                    if let Some(p) = pattern {
+                        filters::build_regex(p, "dir pattern", self.regex_size_limit)?;
+                    }
+                }
+
+                // Check that the command is not empty and can be found, so a
+                // typo fails at startup rather than in the reader thread.
                 SourceKind::Command { command, .. } => {
                     if command.trim().is_empty() {
                         return Err(ConfigError::SourceCommandEmpty {
                             name: s.name.clone(),
                         });
                     }
+                    find_program(command).map_err(|source| ConfigError::SourceSpawn {
+                        name: s.name.clone(),
+                        command: command.clone(),
+                        source,
+                    })?;
+                }
+
+                SourceKind::Tcp { tls, .. } => {
+                    if let Some(tls) = tls {
+                        Self::validate_tls(&s.name, tls)?;
+                    }
+                }
+
+                SourceKind::Journald { .. } => {
+                    if cfg!(not(all(feature = "journald", target_os = "linux"))) {
+                        return Err(ConfigError::Invalid(format!(
+                            "source `{}`: journald sources require logscout to be built with the `journald` feature (Linux only).",
+                            s.name
+                        )));
+                    }
+                }
+
+                SourceKind::WindowsEvent { channel, .. } => {
+                    if cfg!(not(all(feature = "windows_event", windows))) {
+                        return Err(ConfigError::Invalid(format!(
+                            "source `{}`: windows_event sources require logscout to be built with the `windows_event` feature (Windows only).",
+                            s.name
+                        )));
+                    }
+                    if channel.trim().is_empty() {
+                        return Err(ConfigError::Invalid(format!(
+                            "source `{}`: channel must not be empty.",
+                            s.name
+                        )));
+                    }
                 }
             }
         }
 
         Ok(())
     }
+
+    /// Check that a TCP source's certificate and key can be loaded.
+    fn validate_tls(name: &str, tls: &TlsConfig) -> Result<(), ConfigError> {
+        #[cfg(feature = "tls")]
+        return tls.load().map(drop).map_err(|e| {
+            ConfigError::Invalid(format!("source `{name}`: failed to load TLS config: {e}"))
+        });
+
+        #[cfg(not(feature = "tls"))]
+        {
+            let _ = tls;
+            Err(ConfigError::Invalid(format!(
+                "source `{name}`: tls requires logscout to be built with the `tls` feature."
+            )))
+        }
+    }
+
+    /// Check that a file source's file exists and is a regular file (or pipe).
+    fn validate_file(name: &str, path: &Path) -> Result<(), ConfigError> {
+        use std::io::ErrorKind;
+
+        let name = name.to_string();
+        let path_str = path.display().to_string();
+
+        let meta = match fs::metadata(path) {
+            Ok(m) => m,
+            Err(e) => {
+                return match e.kind() {
+                    ErrorKind::NotFound => Err(ConfigError::SourceFileNotFound {
+                        name,
+                        path: path_str,
+                    }),
+                    ErrorKind::PermissionDenied => Err(ConfigError::SourceIo {
+                        name,
+                        path: path_str,
+                        source: e,
+                    }),
+                    _ => Err(ConfigError::SourceIo {
+                        name,
+                        path: path_str,
+                        source: e,
+                    }),
+                }?;
+            }
+        };
+        if !meta.is_file() && !follow::is_fifo(&meta) {
+            return Err(ConfigError::SourceNotAFile {
+                name,
+                path: path_str,
+            });
+        }
+        Ok(())
+    }
+}
+
+/// Check that `command` names an executable file, either as a path or by
+/// looking it up on `PATH` the way spawning it would.
+#[cfg(unix)]
+fn find_program(command: &str) -> io::Result<()> {
+    use std::os::unix::fs::PermissionsExt;
+
+    let is_executable = |path: &Path| -> io::Result<()> {
+        let meta = fs::metadata(path)?;
+        if !meta.is_file() {
+            return Err(io::Error::other("not a regular file"));
+        }
+        if meta.permissions().mode() & 0o111 == 0 {
+            return Err(io::Error::from(io::ErrorKind::PermissionDenied));
+        }
+        Ok(())
+    };
+
+    if command.contains('/') {
+        return is_executable(Path::new(command));
+    }
+    let dirs = std::env::var_os("PATH").unwrap_or_default();
+    if std::env::split_paths(&dirs).any(|dir| is_executable(&dir.join(command)).is_ok()) {
+        Ok(())
+    } else {
+        Err(io::Error::new(io::ErrorKind::NotFound, "not found on PATH"))
+    }
+}
+
+/// Program lookup rules differ elsewhere (e.g. `PATHEXT` on Windows), so
+/// spawn errors are left to the reader there.
+#[cfg(not(unix))]
+fn find_program(_command: &str) -> io::Result<()> {
+    Ok(())
+}
+
+/// Config path that means "read from stdin".
+const STDIN_PATH: &str = "-";
+
+fn is_stdin(path: &Path) -> bool {
+    path.as_os_str() == STDIN_PATH
+}
+
+/// How a config path is named in messages (`<stdin>` for `-`).
+fn config_label(path: &Path) -> String {
+    if is_stdin(path) {
+        "<stdin>".to_string()
+    } else {
+        path.display().to_string()
+    }
+}
+
+/// The language of a config file, from its extension.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ConfigFormat {
+    Yaml,
+    Toml,
+    Json,
+}
+
+impl ConfigFormat {
+    /// `.toml` and `.json` files; YAML for anything else, including stdin.
+    fn of(path: &Path) -> Self {
+        match path.extension().and_then(|ext| ext.to_str()) {
+            Some("toml") => ConfigFormat::Toml,
+            Some("json") => ConfigFormat::Json,
+            _ => ConfigFormat::Yaml,
+        }
+    }
+
+    fn name(self) -> &'static str {
+        match self {
+            ConfigFormat::Yaml => "YAML",
+            ConfigFormat::Toml => "TOML",
+            ConfigFormat::Json => "JSON",
+        }
+    }
+
+    /// Deserialize `contents`, read from `path`.
+    fn parse<T: DeserializeOwned>(self, contents: &str, path: String) -> Result<T, ConfigError> {
+        let parsed = match self {
+            ConfigFormat::Yaml => serde_yaml::from_str(contents).map_err(ParseError::from),
+            ConfigFormat::Toml => {
+                toml::from_str(contents).map_err(|e| ParseError::Toml(Box::new(e)))
+            }
+            ConfigFormat::Json => serde_json::from_str(contents).map_err(ParseError::from),
+        };
+        parsed.map_err(|source| ConfigError::Parse {
+            source,
+            format: self.name(),
+            path,
+        })
+    }
+}
+
+/// Read a config file (or stdin for `-`), returning its label and contents.
+fn read_config(path: &Path) -> Result<(String, String), ConfigError> {
+    let label = config_label(path);
+    let contents = if is_stdin(path) {
+        io::read_to_string(io::stdin())
+    } else {
+        fs::read_to_string(path)
+    };
+    match contents {
+        Ok(contents) => Ok((label, contents)),
+        Err(e) => Err(ConfigError::Io {
+            source: e,
+            path: label,
+        }),
+    }
+}
+
+/// Merge `overlay` into `base`: sequences are concatenated, mappings are
+/// merged key by key, and anything else in `overlay` replaces `base`.
+fn merge_yaml(base: &mut serde_yaml::Value, overlay: serde_yaml::Value) {
+    use serde_yaml::Value;
+
+    match (base, overlay) {
+        (_, Value::Null) => {}
+        (Value::Sequence(b), Value::Sequence(o)) => b.extend(o),
+        (Value::Mapping(b), Value::Mapping(o)) => {
+            for (k, v) in o {
+                match b.get_mut(&k) {
+                    Some(existing) => merge_yaml(existing, v),
+                    None => {
+                        b.insert(k, v);
+                    }
+                }
+            }
+        }
+        (b, o) => *b = o,
+    }
+}
+
+/// Names of the sources listed in a (not yet deserialized) config document.
+fn source_names(value: &serde_yaml::Value) -> Vec<String> {
+    value
+        .get("sources")
+        .and_then(|s| s.as_sequence())
+        .map(|seq| {
+            seq.iter()
+                .filter_map(|s| s.get("name")?.as_str().map(str::to_string))
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(yaml: &str) -> Result<Config, String> {
+        serde_yaml::from_str(yaml).map_err(|e| e.to_string())
+    }
+
+    const SOURCE: &str = "  - name: app\n    type: file\n    path: /var/log/app.log\n";
+
+    #[test]
+    fn unknown_keys_are_rejected() {
+        assert!(parse(&format!("follow: false\nsources:\n{SOURCE}")).is_ok());
+
+        let err = parse(&format!(
+            "follow: false\ninlcude: [ERROR]\nsources:\n{SOURCE}"
+        ))
+        .unwrap_err();
+        assert!(err.contains("unknown field `inlcude`"), "{err}");
+
+        // Keys of `SourceConfig` and of its flattened `SourceKind` share a
+        // map; a typo in either is caught
+        let err = parse(&format!(
+            "follow: false\nsources:\n{SOURCE}    lable: APP\n"
+        ))
+        .unwrap_err();
+        assert!(err.contains("unknown field `lable`"), "{err}");
+        let err = parse(&format!(
+            "follow: false\nsources:\n{SOURCE}    start_att: end\n"
+        ))
+        .unwrap_err();
+        assert!(err.contains("unknown field `start_att`"), "{err}");
+    }
 }
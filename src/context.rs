@@ -0,0 +1,73 @@
+// src/context.rs
+use crate::logline::LogLine;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, VecDeque};
+
+#[derive(Debug, Deserialize, Serialize, Clone, Default)]
+#[serde(deny_unknown_fields)]
+pub struct ContextConfig {
+    /// Lines to show before each emitted line (like `grep -B`).
+    #[serde(default)]
+    pub before: usize,
+
+    /// Lines to show after each emitted line (like `grep -A`).
+    #[serde(default)]
+    pub after: usize,
+}
+
+/// Context state of one source.
+#[derive(Debug, Default)]
+struct SourceContext {
+    /// The most recent unshown lines, oldest first.
+    recent: VecDeque<LogLine>,
+    /// Lines still to show after the last emitted line.
+    after_left: usize,
+}
+
+/// Tracks, per source, which unmatched lines to show around emitted ones.
+///
+/// Every line is shown at most once: before-context is taken out of the
+/// buffer when printed, and a match inside another match's after-context
+/// restarts the count instead of repeating lines.
+#[derive(Debug)]
+pub struct Context {
+    before: usize,
+    after: usize,
+    sources: HashMap<String, SourceContext>,
+}
+
+impl Context {
+    pub fn new(cfg: &ContextConfig) -> Self {
+        Self {
+            before: cfg.before,
+            after: cfg.after,
+            sources: HashMap::new(),
+        }
+    }
+
+    /// A line from `source` is about to be emitted. Returns the buffered
+    /// lines to show before it and starts its after-context.
+    pub fn on_emit(&mut self, source: &str) -> Vec<LogLine> {
+        let ctx = self.sources.entry(source.to_string()).or_default();
+        ctx.after_left = self.after;
+        ctx.recent.drain(..).collect()
+    }
+
+    /// A line that matched no include pattern. Returns it if it should be
+    /// shown as after-context; otherwise keeps it as possible before-context.
+    pub fn on_unmatched(&mut self, msg: LogLine) -> Option<LogLine> {
+        let ctx = self.sources.entry(msg.source.clone()).or_default();
+        if ctx.after_left > 0 {
+            ctx.after_left -= 1;
+            return Some(msg);
+        }
+
+        if self.before > 0 {
+            if ctx.recent.len() == self.before {
+                ctx.recent.pop_front();
+            }
+            ctx.recent.push_back(msg);
+        }
+        None
+    }
+}
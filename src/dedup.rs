@@ -0,0 +1,61 @@
+// src/dedup.rs
+use std::collections::hash_map::DefaultHasher;
+use std::collections::{HashMap, VecDeque};
+use std::hash::{Hash, Hasher};
+use std::time::{Duration, Instant};
+
+/// Drops lines already seen within a time window, from any source.
+///
+/// Lines are tracked by hash, each for `window` after it was first seen;
+/// repeats do not extend that. At most `capacity` hashes are kept: beyond
+/// that the oldest are forgotten early, so a repeat may slip through.
+#[derive(Debug)]
+pub struct Deduper {
+    window: Duration,
+    capacity: usize,
+    /// When each tracked hash was first seen.
+    seen: HashMap<u64, Instant>,
+    /// Hashes in the order they were first seen, for expiry.
+    order: VecDeque<(u64, Instant)>,
+}
+
+impl Deduper {
+    pub fn new(window: Duration, capacity: usize) -> Self {
+        Self {
+            window,
+            capacity,
+            seen: HashMap::new(),
+            order: VecDeque::new(),
+        }
+    }
+
+    /// Whether `line` repeats one seen within the window; otherwise it is
+    /// remembered from `now` on.
+    pub fn is_repeat(&mut self, line: &str, now: Instant) -> bool {
+        while let Some(&(_, at)) = self.order.front()
+            && now.duration_since(at) >= self.window
+        {
+            self.forget_oldest();
+        }
+
+        let mut hasher = DefaultHasher::new();
+        line.hash(&mut hasher);
+        let hash = hasher.finish();
+        if self.seen.contains_key(&hash) {
+            return true;
+        }
+
+        while self.seen.len() >= self.capacity {
+            self.forget_oldest();
+        }
+        self.seen.insert(hash, now);
+        self.order.push_back((hash, now));
+        false
+    }
+
+    fn forget_oldest(&mut self) {
+        if let Some((hash, _)) = self.order.pop_front() {
+            self.seen.remove(&hash);
+        }
+    }
+}
@@ -0,0 +1,223 @@
+// src/dir_output.rs
+use crate::config::ConfigError;
+use crate::logline::LogLine;
+use crate::sink_policy::OnError;
+use crate::timeutil::{self, Date};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, BufWriter, Write};
+use std::path::PathBuf;
+
+#[derive(Debug, Deserialize, Serialize, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct DirOutputConfig {
+    /// Root directory for archived output.
+    pub path: PathBuf,
+
+    /// Relative file path under `path`. Supports `{year}`, `{month}`,
+    /// `{day}` (UTC, zero-padded) and `{source}`.
+    #[serde(default = "default_template")]
+    pub template: String,
+
+    /// What to do when writing an archive file fails.
+    #[serde(default)]
+    pub on_error: OnError,
+}
+
+fn default_template() -> String {
+    "{year}/{month}/{day}/{source}.log".to_string()
+}
+
+const PLACEHOLDERS: &[&str] = &["year", "month", "day", "source"];
+
+impl DirOutputConfig {
+    /// Check that the template only uses known placeholders.
+    pub fn validate(&self) -> Result<(), ConfigError> {
+        let mut rest = self.template.as_str();
+        while let Some(start) = rest.find('{') {
+            let Some(len) = rest[start..].find('}') else {
+                return Err(ConfigError::Invalid(format!(
+                    "output.dir.template: unclosed `{{` in `{}`",
+                    self.template
+                )));
+            };
+            let name = &rest[start + 1..start + len];
+            if !PLACEHOLDERS.contains(&name) {
+                return Err(ConfigError::Invalid(format!(
+                    "output.dir.template: unknown placeholder `{{{name}}}`"
+                )));
+            }
+            rest = &rest[start + len + 1..];
+        }
+
+        if !self.template.contains("{source}") {
+            return Err(ConfigError::Invalid(
+                "output.dir.template must contain `{source}`.".into(),
+            ));
+        }
+        Ok(())
+    }
+}
+
+/// An open output file and the date it belongs to.
+struct OpenFile {
+    date: Date,
+    writer: BufWriter<File>,
+}
+
+/// Writes each source's lines into dated files under a root directory,
+/// switching to a new file when the (UTC) day changes.
+pub struct DirWriter {
+    cfg: DirOutputConfig,
+    /// First line of every new file (the CSV header).
+    header: Option<&'static str>,
+    files: HashMap<String, OpenFile>,
+}
+
+impl DirWriter {
+    pub fn new(cfg: DirOutputConfig, header: Option<&'static str>) -> Self {
+        Self {
+            cfg,
+            header,
+            files: HashMap::new(),
+        }
+    }
+
+    /// Append an already rendered line for `msg` to its source's file.
+    pub fn write_line(&mut self, msg: &LogLine, rendered: &str) -> io::Result<()> {
+        let date = timeutil::utc_date(msg.timestamp);
+
+        let needs_open = match self.files.get(&msg.source) {
+            Some(f) => f.date != date, // crossed a day boundary
+            None => true,
+        };
+        if needs_open {
+            // Finish the previous day's file first: if that fails, it stays
+            // open with its buffered lines for the next attempt
+            if let Some(old) = self.files.get_mut(&msg.source) {
+                old.writer.flush()?;
+            }
+            let writer = self.open(&msg.source, date)?;
+            self.files
+                .insert(msg.source.clone(), OpenFile { date, writer });
+        }
+
+        let file = self.files.get_mut(&msg.source).expect("file opened above");
+        file.writer.write_all(rendered.as_bytes())?;
+        file.writer.write_all(b"\n")
+    }
+
+    pub fn flush(&mut self) -> io::Result<()> {
+        for f in self.files.values_mut() {
+            f.writer.flush()?;
+        }
+        Ok(())
+    }
+
+    fn open(&self, source: &str, date: Date) -> io::Result<BufWriter<File>> {
+        let path = self.cfg.path.join(self.expand(source, date));
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        let file = OpenOptions::new().create(true).append(true).open(&path)?;
+        // A file already written to (e.g. by an earlier run that day) has it
+        let empty = file.metadata()?.len() == 0;
+        let mut writer = BufWriter::new(file);
+        if let Some(header) = self.header.filter(|_| empty) {
+            writer.write_all(header.as_bytes())?;
+            writer.write_all(b"\n")?;
+        }
+        Ok(writer)
+    }
+
+    fn expand(&self, source: &str, date: Date) -> String {
+        // Keep source names from escaping into other directories
+        let source = source.replace(['/', '\\'], "_");
+        self.cfg
+            .template
+            .replace("{year}", &format!("{:04}", date.year))
+            .replace("{month}", &format!("{:02}", date.month))
+            .replace("{day}", &format!("{:02}", date.day))
+            .replace("{source}", &source)
+    }
+}
+
+impl Drop for DirWriter {
+    fn drop(&mut self) {
+        let _ = self.flush();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+    #[test]
+    fn lines_go_to_the_file_of_their_day() {
+        let root = std::env::temp_dir().join(format!("logscout-dir-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&root);
+        let mut dir = DirWriter::new(
+            DirOutputConfig {
+                path: root.clone(),
+                template: default_template(),
+                on_error: OnError::Stop,
+            },
+            None,
+        );
+
+        // 2024-03-05 23:59:59 UTC, then a second later
+        let day = UNIX_EPOCH + Duration::from_secs(1_709_683_199);
+        for (source, at, line) in [
+            ("app", day, "late"),
+            ("web/front", day, "slash"),
+            ("app", day + Duration::from_secs(1), "early"),
+        ] {
+            let msg = LogLine {
+                timestamp: at,
+                ..LogLine::new(source, line)
+            };
+            dir.write_line(&msg, line).unwrap();
+        }
+        drop(dir);
+
+        let read = |path: &str| fs::read_to_string(root.join(path)).unwrap();
+        assert_eq!(read("2024/03/05/app.log"), "late\n");
+        assert_eq!(read("2024/03/05/web_front.log"), "slash\n");
+        assert_eq!(read("2024/03/06/app.log"), "early\n");
+        let _ = fs::remove_dir_all(&root);
+    }
+
+    #[test]
+    fn every_new_file_starts_with_the_header() {
+        let root = std::env::temp_dir().join(format!("logscout-dir-csv-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&root);
+        let cfg = DirOutputConfig {
+            path: root.clone(),
+            template: "{day}/{source}.csv".to_string(),
+            on_error: OnError::Stop,
+        };
+
+        // 2024-03-05 23:59:59 UTC, then a second later
+        let day = UNIX_EPOCH + Duration::from_secs(1_709_683_199);
+        let write = |lines: &[(SystemTime, &str)]| {
+            let mut dir = DirWriter::new(cfg.clone(), Some("h"));
+            for &(at, line) in lines {
+                let msg = LogLine {
+                    timestamp: at,
+                    ..LogLine::new("app", line)
+                };
+                dir.write_line(&msg, line).unwrap();
+            }
+        };
+        write(&[(day, "a"), (day + Duration::from_secs(1), "b")]);
+        // A later run appends to the day's file without a second header
+        write(&[(day + Duration::from_secs(2), "c")]);
+
+        let read = |path: &str| fs::read_to_string(root.join(path)).unwrap();
+        assert_eq!(read("05/app.csv"), "h\na\n");
+        assert_eq!(read("06/app.csv"), "h\nb\nc\n");
+        let _ = fs::remove_dir_all(&root);
+    }
+}
@@ -0,0 +1,368 @@
+// src/fields.rs
+use crate::config::ConfigError;
+use crate::filters;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::borrow::Cow;
+use std::collections::BTreeMap;
+use std::sync::LazyLock;
+
+/// Structured formats a source's lines can be parsed into fields from.
+#[derive(Debug, Deserialize, Serialize, Clone, Copy, PartialEq, Eq)]
+pub enum ParseFormat {
+    /// Common Log Format: `host ident user [time] "request" status bytes`
+    #[serde(rename = "clf")]
+    Clf,
+
+    /// Combined Log Format: CLF plus `"referer" "user-agent"`
+    #[serde(rename = "combined")]
+    Combined,
+
+    /// One JSON object per line; nested keys are joined with `.`
+    #[serde(rename = "json")]
+    Json,
+}
+
+static ACCESS_LOG: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(
+        r#"^(\S+) (\S+) (\S+) \[([^\]]+)\] "([^"]*)" (\d{3}) (\S+)(?: "((?:[^"\\]|\\.)*)" "((?:[^"\\]|\\.)*)")?"#,
+    )
+    .expect("access log regex is valid")
+});
+
+/// Parse `line` into named fields. Returns `None` for malformed lines,
+/// which are then passed along unparsed.
+pub fn parse(format: ParseFormat, line: &str) -> Option<BTreeMap<String, String>> {
+    if format == ParseFormat::Json {
+        return parse_json(line);
+    }
+
+    let caps = ACCESS_LOG.captures(line)?;
+    if format == ParseFormat::Combined && caps.get(8).is_none() {
+        return None;
+    }
+
+    let mut fields = BTreeMap::new();
+    let mut put = |k: &str, v: &str| {
+        fields.insert(k.to_string(), v.to_string());
+    };
+
+    put("client_ip", &caps[1]);
+    put("ident", &caps[2]);
+    put("user", &caps[3]);
+    put("time", &caps[4]);
+
+    // "GET /path HTTP/1.1"; anything else is kept whole as `request`
+    let request = &caps[5];
+    put("request", request);
+    let mut parts = request.split(' ');
+    if let (Some(method), Some(path)) = (parts.next(), parts.next()) {
+        put("method", method);
+        put("path", path);
+        if let Some(protocol) = parts.next() {
+            put("protocol", protocol);
+        }
+    }
+
+    put("status", &caps[6]);
+    put("bytes", &caps[7]);
+
+    if format == ParseFormat::Combined {
+        put("referer", &caps[8]);
+        put("user_agent", &caps[9]);
+    }
+
+    Some(fields)
+}
+
+/// Flatten a JSON object into fields. Strings are taken as they are; other
+/// values keep their JSON text (`500`, `true`, `null`, `[1,2]`).
+fn parse_json(line: &str) -> Option<BTreeMap<String, String>> {
+    let serde_json::Value::Object(object) = serde_json::from_str(line).ok()? else {
+        return None;
+    };
+
+    let mut fields = BTreeMap::new();
+    flatten_json("", object, &mut fields);
+    Some(fields)
+}
+
+fn flatten_json(
+    prefix: &str,
+    object: serde_json::Map<String, serde_json::Value>,
+    fields: &mut BTreeMap<String, String>,
+) {
+    for (key, value) in object {
+        let key = if prefix.is_empty() {
+            key
+        } else {
+            format!("{prefix}.{key}")
+        };
+        match value {
+            serde_json::Value::Object(inner) => flatten_json(&key, inner, fields),
+            serde_json::Value::String(s) => {
+                fields.insert(key, s);
+            }
+            other => {
+                fields.insert(key, other.to_string());
+            }
+        }
+    }
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct FieldFilterConfig {
+    /// Name of the parsed field to test.
+    pub field: String,
+
+    /// The field must equal this value exactly.
+    #[serde(default)]
+    pub equals: Option<String>,
+
+    /// The field must match this regex.
+    #[serde(default)]
+    pub pattern: Option<String>,
+}
+
+/// Per-source filter on a field of JSON lines, used instead of the regexes.
+#[derive(Debug, Deserialize, Serialize, Clone, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct JsonFilterConfig {
+    /// Name of the field to test (`a.b` for nested objects).
+    pub field: String,
+
+    /// The field must equal this value exactly.
+    #[serde(default)]
+    pub equals: Option<String>,
+
+    /// The field must match this regex.
+    #[serde(default)]
+    pub pattern: Option<String>,
+
+    /// What to do with lines that are not JSON objects.
+    #[serde(default)]
+    pub on_invalid: OnInvalidJson,
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum OnInvalidJson {
+    /// Drop the line.
+    #[default]
+    Drop,
+
+    /// Emit the line unfiltered.
+    Pass,
+}
+
+impl JsonFilterConfig {
+    /// The predicate part, as a field filter.
+    pub fn field_filter(&self) -> FieldFilterConfig {
+        FieldFilterConfig {
+            field: self.field.clone(),
+            equals: self.equals.clone(),
+            pattern: self.pattern.clone(),
+        }
+    }
+}
+
+/// A compiled `json_filter`.
+#[derive(Debug)]
+pub struct JsonFilter {
+    test: FieldFilter,
+    on_invalid: OnInvalidJson,
+}
+
+impl JsonFilter {
+    pub fn compile(cfg: &JsonFilterConfig, size_limit: usize) -> Result<Self, ConfigError> {
+        Ok(Self {
+            test: FieldFilter::compile(&cfg.field_filter(), size_limit)?,
+            on_invalid: cfg.on_invalid,
+        })
+    }
+
+    /// Whether a line with these fields is kept, or `None` if it was not
+    /// parsed and `on_invalid` is `pass`.
+    pub fn keeps(&self, fields: &BTreeMap<String, String>) -> Option<bool> {
+        if fields.is_empty() {
+            return match self.on_invalid {
+                OnInvalidJson::Drop => Some(false),
+                OnInvalidJson::Pass => None,
+            };
+        }
+        Some(self.test.matches(fields))
+    }
+}
+
+/// A compiled predicate on one parsed field.
+#[derive(Debug)]
+pub struct FieldFilter {
+    field: FieldPath,
+    test: FieldTest,
+}
+
+#[derive(Debug)]
+enum FieldTest {
+    Equals(String),
+    Pattern(Regex),
+}
+
+impl FieldFilter {
+    pub fn compile(cfg: &FieldFilterConfig, size_limit: usize) -> Result<Self, ConfigError> {
+        let test = match (&cfg.equals, &cfg.pattern) {
+            (Some(v), None) => FieldTest::Equals(v.clone()),
+            (None, Some(p)) => {
+                FieldTest::Pattern(filters::build_regex(p, "field filter", size_limit)?)
+            }
+            _ => {
+                return Err(ConfigError::Invalid(format!(
+                    "Field filter on `{}` needs exactly one of `equals` or `pattern`.",
+                    cfg.field
+                )));
+            }
+        };
+
+        Ok(Self {
+            field: FieldPath::parse(&cfg.field)?,
+            test,
+        })
+    }
+
+    /// A missing field never matches.
+    pub fn matches(&self, fields: &BTreeMap<String, String>) -> bool {
+        let Some(value) = self.field.resolve(fields) else {
+            return false;
+        };
+        match &self.test {
+            FieldTest::Equals(v) => *value == **v,
+            FieldTest::Pattern(re) => re.is_match(&value),
+        }
+    }
+}
+
+/// A field selector such as `level`, `req.id` or `$.data.items[0].id`.
+///
+/// Nested objects are flattened when a line is parsed, so a path without
+/// indexes is just a flattened field name. Arrays keep their JSON text;
+/// an index parses that text and follows the rest of the path inside it.
+#[derive(Debug)]
+struct FieldPath {
+    /// Flattened name of the field, up to the first index.
+    field: String,
+    /// The steps from the first index on.
+    rest: Vec<Step>,
+}
+
+#[derive(Debug)]
+enum Step {
+    Key(String),
+    Index(usize),
+}
+
+impl FieldPath {
+    fn parse(path: &str) -> Result<Self, ConfigError> {
+        let invalid = || {
+            ConfigError::Invalid(format!(
+                "Field `{path}`: expected a name or a path like `data.items[0].id`."
+            ))
+        };
+
+        let mut steps = Vec::new();
+        for segment in path.strip_prefix("$.").unwrap_or(path).split('.') {
+            let (name, mut indexes) = segment.split_at(segment.find('[').unwrap_or(segment.len()));
+            if name.is_empty() {
+                return Err(invalid());
+            }
+            steps.push(Step::Key(name.to_string()));
+            while !indexes.is_empty() {
+                let (index, tail) = indexes
+                    .strip_prefix('[')
+                    .and_then(|s| s.split_once(']'))
+                    .ok_or_else(invalid)?;
+                steps.push(Step::Index(index.parse().map_err(|_| invalid())?));
+                indexes = tail;
+            }
+        }
+
+        let first_index = steps
+            .iter()
+            .position(|s| matches!(s, Step::Index(_)))
+            .unwrap_or(steps.len());
+        let rest = steps.split_off(first_index);
+        let keys: Vec<&str> = steps
+            .iter()
+            .map(|s| match s {
+                Step::Key(k) => k.as_str(),
+                Step::Index(_) => unreachable!("indexes were split off"),
+            })
+            .collect();
+        Ok(Self {
+            field: keys.join("."),
+            rest,
+        })
+    }
+
+    /// The value at this path, or `None` if the line doesn't have it.
+    /// Values inside arrays are given like parsed fields: strings as they
+    /// are, anything else as JSON text.
+    fn resolve<'a>(&self, fields: &'a BTreeMap<String, String>) -> Option<Cow<'a, str>> {
+        let value = fields.get(&self.field)?;
+        if self.rest.is_empty() {
+            return Some(Cow::Borrowed(value));
+        }
+
+        let mut value: serde_json::Value = serde_json::from_str(value).ok()?;
+        for step in &self.rest {
+            value = match (step, value) {
+                (Step::Key(k), serde_json::Value::Object(mut m)) => m.remove(k)?,
+                (Step::Index(i), serde_json::Value::Array(mut a)) if *i < a.len() => {
+                    a.swap_remove(*i)
+                }
+                _ => return None,
+            };
+        }
+        Some(Cow::Owned(match value {
+            serde_json::Value::String(s) => s,
+            other => other.to_string(),
+        }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::default_regex_size_limit;
+
+    fn status_filter(equals: Option<&str>, pattern: Option<&str>) -> FieldFilter {
+        let cfg = FieldFilterConfig {
+            field: "status".to_string(),
+            equals: equals.map(String::from),
+            pattern: pattern.map(String::from),
+        };
+        FieldFilter::compile(&cfg, default_regex_size_limit()).unwrap()
+    }
+
+    #[test]
+    fn combined_lines_are_filtered_by_status() {
+        let line = r#"203.0.113.7 - frank [10/Oct/2026:13:55:36 +0000] "GET /api/orders?id=5 HTTP/1.1" 503 2326 "https://example.com/" "curl/8.5.0""#;
+        let fields = parse(ParseFormat::Combined, line).expect("combined line");
+        assert_eq!(fields["client_ip"], "203.0.113.7");
+        assert_eq!(fields["method"], "GET");
+        assert_eq!(fields["path"], "/api/orders?id=5");
+        assert_eq!(fields["status"], "503");
+        assert_eq!(fields["bytes"], "2326");
+        assert_eq!(fields["referer"], "https://example.com/");
+        assert_eq!(fields["user_agent"], "curl/8.5.0");
+
+        assert!(status_filter(Some("503"), None).matches(&fields));
+        assert!(status_filter(None, Some("^5")).matches(&fields));
+        assert!(!status_filter(Some("200"), None).matches(&fields));
+
+        // Without the referer and user agent it is CLF, not combined
+        let clf = r#"203.0.113.7 - - [10/Oct/2026:13:55:36 +0000] "GET / HTTP/1.1" 200 512"#;
+        assert!(parse(ParseFormat::Combined, clf).is_none());
+        assert_eq!(parse(ParseFormat::Clf, clf).unwrap()["status"], "200");
+        assert!(parse(ParseFormat::Clf, "not an access log").is_none());
+    }
+}
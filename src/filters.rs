@@ -1,11 +1,148 @@
 // src/filters.rs
-use crate::config::{Config, ConfigError};
-use regex::Regex;
+use crate::config::{Config, ConfigError, MatchMode, PatternList, Precedence};
+use crate::fields::{FieldFilter, JsonFilter};
+use crate::level::LevelFilter;
+use crate::logline::LogLine;
+use crate::stats::PatternHits;
+use regex::{Regex, RegexBuilder};
+use serde::Serialize;
+use std::collections::{BTreeMap, HashMap};
+use std::sync::atomic::{AtomicU64, Ordering};
 
 #[derive(Debug)]
 pub struct Filters {
-    include: Vec<Regex>,
-    exclude: Vec<Regex>,
+    include: Vec<Matcher>,
+    /// Patterns a line must all match, on top of `include`.
+    include_all: Vec<Matcher>,
+    exclude: Vec<Matcher>,
+    /// Lines each include pattern was the first to match (any match with
+    /// `count_only`).
+    include_hits: Vec<AtomicU64>,
+    /// Lines each `include_all` pattern matched.
+    include_all_hits: Vec<AtomicU64>,
+    /// Lines each exclude pattern was the first to match.
+    exclude_hits: Vec<AtomicU64>,
+    /// The configured patterns, for reporting hits.
+    include_patterns: Vec<String>,
+    include_all_patterns: Vec<String>,
+    exclude_patterns: Vec<String>,
+    fields: Vec<FieldFilter>,
+    level: Option<LevelFilter>,
+    /// Per-source `json_filter`s, which replace the regexes for their source.
+    json: HashMap<String, JsonFilter>,
+    invert: bool,
+    precedence: Precedence,
+    /// Try every include pattern, so each counts all the lines it matches.
+    count_only: bool,
+}
+
+/// A compiled pattern, matching either text or raw bytes.
+#[derive(Debug)]
+enum Matcher {
+    Text(Regex),
+    Bytes(regex::bytes::Regex),
+}
+
+/// Settings shared by every include/exclude pattern.
+#[derive(Debug, Clone, Copy)]
+struct CompileOptions {
+    binary: bool,
+    match_mode: MatchMode,
+    literal: bool,
+    size_limit: usize,
+}
+
+impl CompileOptions {
+    fn from_config(cfg: &Config, list: PatternList) -> Self {
+        Self {
+            binary: cfg.binary_match,
+            match_mode: cfg.match_mode,
+            literal: cfg.literal.applies_to(list),
+            size_limit: cfg.regex_size_limit,
+        }
+    }
+}
+
+/// Compile a user-supplied pattern with the configured size limit, so huge
+/// patterns are rejected at startup instead of eating memory.
+///
+/// Matching itself cannot blow up: the `regex` crate never backtracks and
+/// runs in time linear in the line length for any pattern.
+pub(crate) fn build_regex(
+    pattern: &str,
+    kind: &'static str,
+    size_limit: usize,
+) -> Result<Regex, ConfigError> {
+    RegexBuilder::new(pattern)
+        .size_limit(size_limit)
+        .dfa_size_limit(size_limit)
+        .build()
+        .map_err(|e| ConfigError::InvalidRegex {
+            kind,
+            pattern: pattern.to_string(),
+            source: e,
+        })
+}
+
+impl Matcher {
+    /// Compile `pattern` as a text regex, or as a byte regex in binary mode.
+    ///
+    /// Byte regexes have Unicode mode disabled, so `\xFF` matches the single byte 0xFF.
+    /// In whole-line mode the pattern is wrapped as `^(?:...)$`; anchors already
+    /// in the pattern are harmless since `^^` and `$$` match the same as `^` and `$`.
+    fn compile(
+        pattern: &str,
+        kind: &'static str,
+        opts: CompileOptions,
+    ) -> Result<Self, ConfigError> {
+        let to_err = |e| ConfigError::InvalidRegex {
+            kind,
+            pattern: pattern.to_string(),
+            source: e,
+        };
+
+        // Literal patterns are escaped first, so `.` and `*` match themselves
+        let pattern_src = if opts.literal {
+            regex::escape(pattern)
+        } else {
+            pattern.to_string()
+        };
+
+        let source = match opts.match_mode {
+            MatchMode::Substring => pattern_src,
+            MatchMode::WholeLine => format!("^(?:{pattern_src})$"),
+        };
+
+        if opts.binary {
+            let re = regex::bytes::RegexBuilder::new(&source)
+                .unicode(false)
+                .size_limit(opts.size_limit)
+                .dfa_size_limit(opts.size_limit)
+                .build()
+                .map_err(to_err)?;
+            Ok(Matcher::Bytes(re))
+        } else {
+            let re = RegexBuilder::new(&source)
+                .size_limit(opts.size_limit)
+                .dfa_size_limit(opts.size_limit)
+                .build()
+                .map_err(to_err)?;
+            Ok(Matcher::Text(re))
+        }
+    }
+
+    /// Match against the raw bytes if present, otherwise the text.
+    fn is_match(&self, line: &str, raw: Option<&[u8]>) -> bool {
+        match self {
+            Matcher::Text(re) => re.is_match(line),
+            Matcher::Bytes(re) => re.is_match(raw.unwrap_or(line.as_bytes())),
+        }
+    }
+}
+
+/// Index of the first matcher that matches the line.
+fn first_match(matchers: &[Matcher], line: &str, raw: Option<&[u8]>) -> Option<usize> {
+    matchers.iter().position(|re| re.is_match(line, raw))
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
@@ -16,66 +153,333 @@ pub enum FilterDecision {
     /// Line passed and matched an include regex.
     Included,
 
-    /// Line passed without any include regex match (include list empty).
+    /// Line passed without any include regex match (include lists empty).
     Passed,
 
-    /// Line was dropped because it didn't match any include regex.
+    /// Line was dropped because it didn't match the include regexes.
     DroppedNoIncludeMatch,
 }
 
+/// How many lines got each decision (`Filters::classify_all`).
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct ClassificationSummary {
+    pub included: u64,
+    pub passed: u64,
+    pub excluded: u64,
+    pub dropped: u64,
+}
+
+impl ClassificationSummary {
+    pub fn add(&mut self, decision: FilterDecision) {
+        match decision {
+            FilterDecision::Included => self.included += 1,
+            FilterDecision::Passed => self.passed += 1,
+            FilterDecision::Excluded => self.excluded += 1,
+            FilterDecision::DroppedNoIncludeMatch => self.dropped += 1,
+        }
+    }
+
+    /// Lines that would be emitted (included or passed).
+    pub fn emitted(&self) -> u64 {
+        self.included + self.passed
+    }
+
+    pub fn total(&self) -> u64 {
+        self.included + self.passed + self.excluded + self.dropped
+    }
+}
+
+impl FilterDecision {
+    /// Short lowercase name, used when annotating output.
+    pub fn label(self) -> &'static str {
+        match self {
+            FilterDecision::Excluded => "excluded",
+            FilterDecision::Included => "included",
+            FilterDecision::Passed => "passed",
+            FilterDecision::DroppedNoIncludeMatch => "dropped",
+        }
+    }
+}
+
 impl Filters {
     /// Build Filters from Config.[include|exclude].
     /// Every pattern must be a valid regex; otherwise we throw ConfigError
     pub fn from_config(cfg: &Config) -> Result<Self, ConfigError> {
+        let include_opts = CompileOptions::from_config(cfg, PatternList::Include);
+        let exclude_opts = CompileOptions::from_config(cfg, PatternList::Exclude);
         let mut include = Vec::new();
+        let mut include_all = Vec::new();
         let mut exclude = Vec::new();
 
         for pattern in &cfg.include {
-            let re = Regex::new(pattern).map_err(|e| ConfigError::InvalidRegex {
-                kind: "include",
-                pattern: pattern.clone(),
-                source: e,
-            })?;
-            include.push(re);
+            include.push(Matcher::compile(pattern, "include", include_opts)?);
+        }
+
+        for pattern in &cfg.include_all {
+            include_all.push(Matcher::compile(pattern, "include_all", include_opts)?);
         }
 
         for pattern in &cfg.exclude {
-            let re = Regex::new(pattern).map_err(|e| ConfigError::InvalidRegex {
-                kind: "exclude",
-                pattern: pattern.clone(),
-                source: e,
-            })?;
-            exclude.push(re);
+            exclude.push(Matcher::compile(pattern, "exclude", exclude_opts)?);
         }
 
-        Ok(Self { include, exclude })
+        let fields = cfg
+            .field_filters
+            .iter()
+            .map(|f| FieldFilter::compile(f, cfg.regex_size_limit))
+            .collect::<Result<_, _>>()?;
+
+        let level = cfg
+            .min_level
+            .map(|min| {
+                LevelFilter::compile(
+                    min,
+                    cfg.level_pattern.as_deref(),
+                    cfg.keep_unleveled,
+                    cfg.regex_size_limit,
+                )
+            })
+            .transpose()?;
+
+        let mut json = HashMap::new();
+        for src in &cfg.sources {
+            if let Some(jf) = &src.json_filter {
+                json.insert(
+                    src.name.clone(),
+                    JsonFilter::compile(jf, cfg.regex_size_limit)?,
+                );
+            }
+        }
+
+        Ok(Self {
+            include_hits: include.iter().map(|_| AtomicU64::new(0)).collect(),
+            include_all_hits: include_all.iter().map(|_| AtomicU64::new(0)).collect(),
+            exclude_hits: exclude.iter().map(|_| AtomicU64::new(0)).collect(),
+            include,
+            include_all,
+            exclude,
+            include_patterns: cfg.include.clone(),
+            include_all_patterns: cfg.include_all.clone(),
+            exclude_patterns: cfg.exclude.clone(),
+            fields,
+            level,
+            json,
+            invert: cfg.invert,
+            precedence: cfg.precedence,
+            count_only: cfg.count_only,
+        })
     }
 
     /// Classify a line according to include/exclude rules.
     ///
     /// Rules:
     /// - If it matches any exclude regex -> Excluded
-    /// - Else if include list is empty -> Passed
-    /// - Else if it matches any include regex -> Included
+    /// - Else if its level is below `min_level` -> Excluded
+    /// - Else if it has parsed fields failing a field filter -> DroppedNoIncludeMatch
+    /// - Else if both include lists are empty -> Passed
+    /// - Else if it matches the includes -> Included
     /// - Else -> DroppedNoIncludeMatch
+    ///
+    /// A line matches the includes if it matches any `include` regex (or
+    /// that list is empty) and every `include_all` regex.
+    ///
+    /// With `invert`, the last two outcomes are swapped (like `grep -v`):
+    /// a line matching the includes is DroppedNoIncludeMatch and any other
+    /// line is Included. Excludes still win, and empty include lists still
+    /// pass everything.
+    ///
+    /// With `precedence: include_first`, a line that would be Included skips
+    /// the exclude check; every other line is classified as above. For a
+    /// non-empty include list (after `invert`):
+    ///
+    /// | include | exclude | `exclude_first` | `include_first` |
+    /// |---------|---------|-----------------|-----------------|
+    /// | match   | match   | Excluded        | Included        |
+    /// | match   | -       | Included        | Included        |
+    /// | -       | match   | Excluded        | Excluded        |
+    /// | -       | -       | Dropped         | Dropped         |
     pub fn classify(&self, line: &str) -> FilterDecision {
+        self.classify_raw(line, None, &BTreeMap::new())
+    }
+
+    /// Classify a `LogLine`, matching its raw bytes when they were kept
+    /// and applying field filters to its parsed fields.
+    ///
+    /// Sources with a `json_filter` are judged by it alone: a match is
+    /// Included, anything else DroppedNoIncludeMatch, and unparsed lines
+    /// Passed when `on_invalid` is `pass`.
+    pub fn classify_line(&self, msg: &LogLine) -> FilterDecision {
+        if let Some(jf) = self.json.get(&msg.source) {
+            return match jf.keeps(&msg.fields) {
+                Some(true) => FilterDecision::Included,
+                Some(false) => FilterDecision::DroppedNoIncludeMatch,
+                None => FilterDecision::Passed,
+            };
+        }
+        self.classify_raw(&msg.line, msg.raw.as_deref(), &msg.fields)
+    }
+
+    fn classify_raw(
+        &self,
+        line: &str,
+        raw: Option<&[u8]>,
+        fields: &BTreeMap<String, String>,
+    ) -> FilterDecision {
+        // Includes are matched at most once per line, so hits aren't counted twice
+        let mut include_hit = None;
+
+        // With `include_first`, an include match overrides the excludes
+        let include_wins = self.precedence == Precedence::IncludeFirst
+            && self.include_selects(*include_hit.insert(self.includes_match(line, raw)));
+
         // Check excludes first
-        if self.exclude.iter().any(|re| re.is_match(line)) {
+        if !include_wins && let Some(i) = first_match(&self.exclude, line, raw) {
+            self.exclude_hits[i].fetch_add(1, Ordering::Relaxed);
+            return FilterDecision::Excluded;
+        }
+
+        // The level threshold applies whatever the precedence
+        if let Some(level) = &self.level
+            && !level.keeps(line)
+        {
             return FilterDecision::Excluded;
         }
 
+        // Field filters only judge lines that were parsed; others pass through
+        if !fields.is_empty() && !self.fields.iter().all(|f| f.matches(fields)) {
+            return FilterDecision::DroppedNoIncludeMatch;
+        }
+
         // Then check includes. If empty, pass all.
-        if self.include.is_empty() {
+        if !self.has_includes() {
             return FilterDecision::Passed;
         }
 
-        if self.include.iter().any(|re| re.is_match(line)) {
+        let matched = include_hit.unwrap_or_else(|| self.includes_match(line, raw));
+        if include_wins || self.include_selects(matched) {
             FilterDecision::Included
         } else {
             FilterDecision::DroppedNoIncludeMatch
         }
     }
 
+    /// Whether the line matches an `include` pattern (if any) and every
+    /// `include_all` pattern, counting the hits. Every `include_all`
+    /// pattern is tried, so each counts all the lines it matches; with
+    /// `count_only`, so is every `include` pattern.
+    fn includes_match(&self, line: &str, raw: Option<&[u8]>) -> bool {
+        if self.count_only {
+            let mut any = self.include.is_empty();
+            for (re, hits) in self.include.iter().zip(&self.include_hits) {
+                if re.is_match(line, raw) {
+                    hits.fetch_add(1, Ordering::Relaxed);
+                    any = true;
+                }
+            }
+            return any && self.include_all_match(line, raw);
+        }
+
+        let any = match first_match(&self.include, line, raw) {
+            Some(i) => {
+                self.include_hits[i].fetch_add(1, Ordering::Relaxed);
+                true
+            }
+            None => self.include.is_empty(),
+        };
+        any && self.include_all_match(line, raw)
+    }
+
+    /// Whether the line matches every `include_all` pattern, counting the
+    /// hits of each.
+    fn include_all_match(&self, line: &str, raw: Option<&[u8]>) -> bool {
+        let mut all = true;
+        for (re, hits) in self.include_all.iter().zip(&self.include_all_hits) {
+            if re.is_match(line, raw) {
+                hits.fetch_add(1, Ordering::Relaxed);
+            } else {
+                all = false;
+            }
+        }
+        all
+    }
+
+    fn has_includes(&self) -> bool {
+        !self.include.is_empty() || !self.include_all.is_empty()
+    }
+
+    /// Whether there are include patterns and they select a line with this
+    /// outcome (taking `invert` into account).
+    fn include_selects(&self, matched: bool) -> bool {
+        self.has_includes() && matched != self.invert
+    }
+
+    /// How many lines each include and exclude pattern was the first to
+    /// match (each `include_all` pattern: matched at all), in config order.
+    pub fn pattern_hits(&self) -> Vec<PatternHits> {
+        let list = |kind, patterns: &[String], hits: &[AtomicU64]| {
+            patterns
+                .iter()
+                .zip(hits)
+                .map(|(pattern, hits)| PatternHits {
+                    list: kind,
+                    pattern: pattern.clone(),
+                    hits: hits.load(Ordering::Relaxed),
+                })
+                .collect::<Vec<_>>()
+        };
+        let mut all = list("include", &self.include_patterns, &self.include_hits);
+        all.extend(list(
+            "include_all",
+            &self.include_all_patterns,
+            &self.include_all_hits,
+        ));
+        all.extend(list("exclude", &self.exclude_patterns, &self.exclude_hits));
+        all
+    }
+
+    /// Add the hits counted by `old` to the same patterns here, so the
+    /// counts of patterns kept across a reload carry over.
+    pub fn carry_hits_from(&self, old: &Filters) {
+        let carry = |patterns: &[String],
+                     hits: &[AtomicU64],
+                     old_patterns: &[String],
+                     old_hits: &[AtomicU64]| {
+            for (pattern, hits) in patterns.iter().zip(hits) {
+                if let Some(i) = old_patterns.iter().position(|p| p == pattern) {
+                    hits.fetch_add(old_hits[i].load(Ordering::Relaxed), Ordering::Relaxed);
+                }
+            }
+        };
+        carry(
+            &self.include_patterns,
+            &self.include_hits,
+            &old.include_patterns,
+            &old.include_hits,
+        );
+        carry(
+            &self.include_all_patterns,
+            &self.include_all_hits,
+            &old.include_all_patterns,
+            &old.include_all_hits,
+        );
+        carry(
+            &self.exclude_patterns,
+            &self.exclude_hits,
+            &old.exclude_patterns,
+            &old.exclude_hits,
+        );
+    }
+
+    /// Classify each of `lines` (as `classify` does) and count the
+    /// decisions.
+    pub fn classify_all(&self, lines: &[&str]) -> ClassificationSummary {
+        let mut summary = ClassificationSummary::default();
+        for line in lines {
+            summary.add(self.classify(line));
+        }
+        summary
+    }
+
     /// Convenience wrapper if you only care about "should this be printed?"
     pub fn matches(&self, line: &str) -> bool {
         matches!(
@@ -84,3 +488,135 @@ impl Filters {
         )
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::default_regex_size_limit;
+
+    /// Filters for a config with these top-level settings and no sources.
+    fn filters(yaml: &str) -> Filters {
+        let cfg: Config = serde_yaml::from_str(&format!("follow: false\nsources: []\n{yaml}"))
+            .expect("test config");
+        Filters::from_config(&cfg).expect("test filters")
+    }
+
+    #[test]
+    fn byte_patterns_match_the_raw_bytes() {
+        let f = filters("binary_match: true\ninclude: ['\\xFF\\xFE']\n");
+        // Lossy decoding turns each invalid byte into U+FFFD
+        let raw = b"magic \xFF\xFE end".to_vec();
+        let msg = LogLine {
+            raw: Some(raw.clone()),
+            ..LogLine::new("app", String::from_utf8_lossy(&raw))
+        };
+        assert_eq!(f.classify_line(&msg), FilterDecision::Included);
+
+        // The decoded text alone no longer has those bytes
+        assert_eq!(
+            f.classify(&String::from_utf8_lossy(&raw)),
+            FilterDecision::DroppedNoIncludeMatch
+        );
+    }
+
+    #[test]
+    fn invert_swaps_only_the_include_outcomes() {
+        let f = filters("invert: true\ninclude: [ERROR]\nexclude: [healthcheck]\n");
+        // Excludes still win
+        assert_eq!(f.classify("ERROR in healthcheck"), FilterDecision::Excluded);
+        // A line matching the includes is dropped, any other kept
+        assert_eq!(
+            f.classify("ERROR boom"),
+            FilterDecision::DroppedNoIncludeMatch
+        );
+        assert_eq!(f.classify("all good"), FilterDecision::Included);
+
+        // Without include patterns everything still passes
+        let f = filters("invert: true\nexclude: [healthcheck]\n");
+        assert_eq!(f.classify("all good"), FilterDecision::Passed);
+    }
+
+    #[test]
+    fn literal_patterns_match_only_their_text() {
+        use FilterDecision::*;
+        let f = filters("literal: true\ninclude: ['a.b*']\n");
+        assert_eq!(f.classify("got a.b* here"), Included);
+        // As a regex, `a.b*` would match all of these
+        assert_eq!(f.classify("axb"), DroppedNoIncludeMatch);
+        assert_eq!(f.classify("a.bbb"), DroppedNoIncludeMatch);
+        assert_eq!(f.classify("a-"), DroppedNoIncludeMatch);
+
+        // Only the named list is literal
+        let f = filters("literal: [exclude]\ninclude: ['a.b*']\nexclude: ['x.y']\n");
+        assert_eq!(f.classify("axb"), Included);
+        assert_eq!(f.classify("axb x.y"), Excluded);
+        assert_eq!(f.classify("axb xzy"), Included);
+    }
+
+    #[test]
+    fn oversized_patterns_are_rejected() {
+        // Ten Unicode word classes compile to far more than 10 KiB
+        let pattern = r"\w{10}";
+        assert!(build_regex(pattern, "include", default_regex_size_limit()).is_ok());
+        let err = build_regex(pattern, "include", 10 * 1024).unwrap_err();
+        assert!(
+            matches!(
+                &err,
+                ConfigError::InvalidRegex {
+                    kind: "include",
+                    ..
+                }
+            ),
+            "{err}"
+        );
+        assert!(err.to_string().contains("size limit"), "{err}");
+    }
+
+    #[test]
+    fn precedence_decides_lines_matching_both_lists() {
+        use FilterDecision::*;
+        let rules = "include: [ERROR]\nexclude: [healthcheck]\n";
+        let lines = [
+            "ERROR in healthcheck",
+            "ERROR boom",
+            "healthcheck ok",
+            "all good",
+        ];
+
+        let f = filters(rules);
+        let got: Vec<_> = lines.iter().map(|l| f.classify(l)).collect();
+        assert_eq!(got, [Excluded, Included, Excluded, DroppedNoIncludeMatch]);
+
+        let f = filters(&format!("precedence: include_first\n{rules}"));
+        let got: Vec<_> = lines.iter().map(|l| f.classify(l)).collect();
+        assert_eq!(got, [Included, Included, Excluded, DroppedNoIncludeMatch]);
+
+        // With `invert`, a line outside the includes is the one that wins
+        let f = filters(&format!("precedence: include_first\ninvert: true\n{rules}"));
+        assert_eq!(f.classify("healthcheck ok"), Included);
+    }
+
+    #[test]
+    fn include_all_needs_every_pattern() {
+        use FilterDecision::*;
+        let f = filters("include: [warn, error]\ninclude_all: [payment, failed]\n");
+        assert_eq!(f.classify("error: payment failed"), Included);
+        // Some but not all of the group
+        assert_eq!(f.classify("error: payment ok"), DroppedNoIncludeMatch);
+        assert_eq!(f.classify("error: login failed"), DroppedNoIncludeMatch);
+        // The whole group, but no `include` pattern
+        assert_eq!(f.classify("info: payment failed"), DroppedNoIncludeMatch);
+
+        // Each `include_all` pattern counts every line it matched
+        let hits: Vec<_> = f
+            .pattern_hits()
+            .into_iter()
+            .filter(|h| h.list == "include_all")
+            .map(|h| (h.pattern, h.hits))
+            .collect();
+        assert_eq!(
+            hits,
+            [("payment".to_string(), 2), ("failed".to_string(), 2)]
+        );
+    }
+}
@@ -0,0 +1,223 @@
+// src/follow.rs
+use std::ffi::OsStr;
+#[cfg(target_os = "linux")]
+use std::ffi::OsString;
+use std::fs::{self, File};
+use std::io::{self, Read, Seek, SeekFrom};
+use std::path::{Path, PathBuf};
+use std::sync::{
+    Arc,
+    atomic::{AtomicBool, Ordering},
+};
+use std::thread;
+use std::time::Duration;
+
+/// How often a followed file is checked when no change notifications are
+/// available (and the longest a notified reader sleeps between checks).
+const POLL_INTERVAL: Duration = Duration::from_millis(250);
+
+/// A `Read` over a file that keeps growing (`tail -F`).
+///
+/// At end of file it waits for more data instead of returning EOF, and
+/// reopens the path when the file is truncated or replaced (log rotation).
+/// EOF is only reported once `shutdown` is set.
+pub struct FollowReader {
+    name: String,
+    path: PathBuf,
+    file: File,
+    pos: u64,
+    shutdown: Arc<AtomicBool>,
+    waker: Waker,
+}
+
+impl FollowReader {
+    /// Follow `file` (opened from `path`) from its current position.
+    /// Replacement files are always read from the start.
+    pub fn new(name: &str, path: &Path, mut file: File, shutdown: Arc<AtomicBool>) -> Self {
+        let pos = file.stream_position().unwrap_or(0);
+        Self {
+            name: name.to_string(),
+            path: path.to_path_buf(),
+            file,
+            pos,
+            shutdown,
+            waker: Waker::new(name, path),
+        }
+    }
+
+    /// Start over if the file was truncated, or switch to the new file if
+    /// the path now points somewhere else.
+    fn reopen_if_replaced(&mut self) -> io::Result<()> {
+        // Removed and not recreated yet: keep waiting for it
+        let Ok(meta) = fs::metadata(&self.path) else {
+            return Ok(());
+        };
+
+        if same_file(&self.file.metadata()?, &meta) {
+            if meta.len() < self.pos {
+                eprintln!(
+                    "[logscout] source `{}`: file `{}` was truncated, reading from the start",
+                    self.name,
+                    self.path.display()
+                );
+                self.file.seek(SeekFrom::Start(0))?;
+                self.pos = 0;
+            }
+        } else if let Ok(file) = File::open(&self.path) {
+            eprintln!(
+                "[logscout] source `{}`: file `{}` was replaced, reopening",
+                self.name,
+                self.path.display()
+            );
+            self.file = file;
+            self.pos = 0;
+        }
+        Ok(())
+    }
+}
+
+impl Read for FollowReader {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        loop {
+            let n = self.file.read(buf)?;
+            if n > 0 {
+                self.pos += n as u64;
+                return Ok(n);
+            }
+            if self.shutdown.load(Ordering::Relaxed) {
+                return Ok(0);
+            }
+
+            self.reopen_if_replaced()?;
+            self.waker.wait();
+        }
+    }
+}
+
+/// Whether `meta` describes a named pipe (always false off Unix).
+#[cfg(unix)]
+pub fn is_fifo(meta: &fs::Metadata) -> bool {
+    use std::os::unix::fs::FileTypeExt;
+    meta.file_type().is_fifo()
+}
+
+#[cfg(not(unix))]
+pub fn is_fifo(_meta: &fs::Metadata) -> bool {
+    false
+}
+
+#[cfg(unix)]
+fn same_file(a: &fs::Metadata, b: &fs::Metadata) -> bool {
+    use std::os::unix::fs::MetadataExt;
+    a.dev() == b.dev() && a.ino() == b.ino()
+}
+
+/// Without inode numbers only truncation can be detected.
+#[cfg(not(unix))]
+fn same_file(_a: &fs::Metadata, _b: &fs::Metadata) -> bool {
+    true
+}
+
+/// Sleeps until a file may have been added to a directory (`dir` sources).
+pub struct DirWaker(Waker);
+
+impl DirWaker {
+    pub fn new(name: &str, dir: &Path) -> Self {
+        Self(Waker::watching(name, dir, None))
+    }
+
+    /// Wait for a change in the directory, or at most `POLL_INTERVAL`.
+    pub fn wait(&self) {
+        self.0.wait();
+    }
+}
+
+/// Sleeps until the followed file may have changed.
+enum Waker {
+    /// Woken by inotify events for the file's directory.
+    #[cfg(target_os = "linux")]
+    Notify {
+        _watcher: notify::RecommendedWatcher,
+        events: std::sync::mpsc::Receiver<()>,
+    },
+
+    /// Checks again after a fixed interval.
+    Poll,
+}
+
+impl Waker {
+    /// Watch the file's directory, so rotation and re-creation are seen too.
+    fn new(name: &str, path: &Path) -> Self {
+        let dir = match path.parent() {
+            Some(p) if !p.as_os_str().is_empty() => p,
+            _ => Path::new("."),
+        };
+        Self::watching(name, dir, path.file_name())
+    }
+
+    /// Wake on events in `dir` that concern `file_name`, or any file.
+    #[cfg(target_os = "linux")]
+    fn watching(name: &str, dir: &Path, file_name: Option<&OsStr>) -> Self {
+        match watch(dir, file_name.map(OsStr::to_os_string)) {
+            Ok(waker) => waker,
+            Err(e) => {
+                let path = dir.join(file_name.unwrap_or_default());
+                eprintln!(
+                    "[logscout] source `{}`: cannot watch `{}` ({}), polling instead",
+                    name,
+                    path.display(),
+                    e
+                );
+                Waker::Poll
+            }
+        }
+    }
+
+    #[cfg(not(target_os = "linux"))]
+    fn watching(_name: &str, _dir: &Path, _file_name: Option<&OsStr>) -> Self {
+        Waker::Poll
+    }
+
+    fn wait(&self) {
+        match self {
+            #[cfg(target_os = "linux")]
+            Waker::Notify { events, .. } => {
+                // The timeout keeps shutdown responsive and covers missed events
+                if events.recv_timeout(POLL_INTERVAL).is_ok() {
+                    while events.try_recv().is_ok() {}
+                }
+            }
+            Waker::Poll => thread::sleep(POLL_INTERVAL),
+        }
+    }
+}
+
+/// Watch `dir` and signal on every event that concerns `file_name` (any
+/// event if `None`).
+#[cfg(target_os = "linux")]
+fn watch(dir: &Path, file_name: Option<OsString>) -> notify::Result<Waker> {
+    use notify::{RecursiveMode, Watcher};
+
+    let (tx, events) = std::sync::mpsc::channel();
+    let mut watcher = notify::recommended_watcher(move |res: notify::Result<notify::Event>| {
+        let relevant = match res {
+            Ok(event) => {
+                file_name.is_none()
+                    || event
+                        .paths
+                        .iter()
+                        .any(|p| p.file_name() == file_name.as_deref())
+            }
+            Err(_) => true, // Let the reader check for itself
+        };
+        if relevant {
+            let _ = tx.send(());
+        }
+    })?;
+    watcher.watch(dir, RecursiveMode::NonRecursive)?;
+
+    Ok(Waker::Notify {
+        _watcher: watcher,
+        events,
+    })
+}
@@ -0,0 +1,122 @@
+// src/health.rs
+use crate::stats::Stats;
+use serde::{Deserialize, Serialize};
+use std::io::{self, BufRead, BufReader, ErrorKind, Write};
+use std::net::{TcpListener, TcpStream};
+use std::sync::{
+    Arc,
+    atomic::{AtomicBool, Ordering},
+};
+use std::thread;
+use std::time::Duration;
+
+#[derive(Debug, Deserialize, Serialize, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct HealthConfig {
+    /// Address to serve `/healthz` on, e.g. `0.0.0.0:8080`.
+    pub bind: String,
+}
+
+/// How often the accept loop checks the shutdown flag.
+const ACCEPT_POLL: Duration = Duration::from_millis(100);
+
+/// A stuck probe must not hold up the next one for longer than this.
+const CLIENT_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// Answers readiness probes: `GET /healthz` is `200` once every source
+/// has started (file opened, command spawned, ...), `503` until then.
+/// Stops when dropped or on shutdown.
+pub struct HealthServer {
+    stop: Arc<AtomicBool>,
+}
+
+impl HealthServer {
+    /// Bind the listener and start answering probes in the background.
+    pub fn start(
+        cfg: &HealthConfig,
+        sources: Vec<String>,
+        stats: Arc<Stats>,
+        shutdown: Arc<AtomicBool>,
+    ) -> io::Result<Self> {
+        let listener = TcpListener::bind(&cfg.bind)?;
+        listener.set_nonblocking(true)?;
+
+        let stop = Arc::new(AtomicBool::new(false));
+        let stopped = stop.clone();
+        thread::spawn(move || {
+            let done = || shutdown.load(Ordering::Relaxed) || stopped.load(Ordering::Relaxed);
+            accept_loop(listener, &sources, &stats, done);
+        });
+        Ok(Self { stop })
+    }
+}
+
+impl Drop for HealthServer {
+    fn drop(&mut self) {
+        self.stop.store(true, Ordering::Relaxed);
+    }
+}
+
+fn accept_loop(listener: TcpListener, sources: &[String], stats: &Stats, done: impl Fn() -> bool) {
+    while !done() {
+        match listener.accept() {
+            Ok((stream, _)) => {
+                if let Err(e) = respond(stream, sources, stats) {
+                    eprintln!("[logscout] health: failed to answer a probe: {e}");
+                }
+            }
+            Err(e) if e.kind() == ErrorKind::WouldBlock => thread::sleep(ACCEPT_POLL),
+            Err(e) => {
+                eprintln!("[logscout] health: accept failed: {e}");
+                thread::sleep(ACCEPT_POLL);
+            }
+        }
+    }
+}
+
+/// Answer one request; the body lists the sources that have not started.
+fn respond(stream: TcpStream, sources: &[String], stats: &Stats) -> io::Result<()> {
+    stream.set_nonblocking(false)?;
+    stream.set_read_timeout(Some(CLIENT_TIMEOUT))?;
+    stream.set_write_timeout(Some(CLIENT_TIMEOUT))?;
+
+    // Only the request line matters, e.g. `GET /healthz HTTP/1.1`
+    let mut reader = BufReader::new(stream);
+    let mut request_line = String::new();
+    reader.read_line(&mut request_line)?;
+    let mut parts = request_line.split_whitespace();
+    let (method, target) = (parts.next(), parts.next());
+    let path = target.map(|t| t.split('?').next().unwrap_or(t));
+
+    let (status, body) = match (method, path) {
+        (Some("GET" | "HEAD"), Some("/healthz")) => {
+            let pending: Vec<&str> = sources
+                .iter()
+                .filter(|name| !stats.has_started(name))
+                .map(String::as_str)
+                .collect();
+            if pending.is_empty() {
+                ("200 OK", "ok\n".to_string())
+            } else {
+                (
+                    "503 Service Unavailable",
+                    format!("not started: {}\n", pending.join(", ")),
+                )
+            }
+        }
+        (Some(_), Some("/healthz")) => ("405 Method Not Allowed", String::new()),
+        _ => ("404 Not Found", String::new()),
+    };
+
+    let mut stream = reader.into_inner();
+    write!(
+        stream,
+        "HTTP/1.1 {}\r\nContent-Type: text/plain\r\nContent-Length: {}\r\nConnection: close\r\n\r\n",
+        status,
+        body.len()
+    )?;
+    if method != Some("HEAD") {
+        stream.write_all(body.as_bytes())?;
+    }
+    stream.flush()
+}
@@ -0,0 +1,161 @@
+// src/journald.rs
+//! Reading the systemd journal through libsystemd's `sd-journal` API.
+//! Only built with the `journald` feature on Linux.
+
+use crate::config::StartAt;
+use std::ffi::{c_char, c_int, c_void};
+use std::io;
+use std::ptr;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::Duration;
+
+/// Longest a followed journal sleeps before checking for shutdown again.
+const WAIT_INTERVAL: Duration = Duration::from_millis(250);
+
+/// Only journal files of the local machine.
+const SD_JOURNAL_LOCAL_ONLY: c_int = 1;
+
+/// Returned (negated) when an entry lacks the requested field.
+const ENOENT: c_int = 2;
+
+#[repr(C)]
+struct SdJournal {
+    _private: [u8; 0],
+}
+
+#[link(name = "systemd")]
+unsafe extern "C" {
+    fn sd_journal_open(ret: *mut *mut SdJournal, flags: c_int) -> c_int;
+    fn sd_journal_close(j: *mut SdJournal);
+    fn sd_journal_add_match(j: *mut SdJournal, data: *const c_void, size: usize) -> c_int;
+    fn sd_journal_seek_tail(j: *mut SdJournal) -> c_int;
+    fn sd_journal_previous(j: *mut SdJournal) -> c_int;
+    fn sd_journal_next(j: *mut SdJournal) -> c_int;
+    fn sd_journal_get_data(
+        j: *mut SdJournal,
+        field: *const c_char,
+        data: *mut *const c_void,
+        length: *mut usize,
+    ) -> c_int;
+    fn sd_journal_wait(j: *mut SdJournal, timeout_usec: u64) -> c_int;
+}
+
+/// Turn a negative errno return value into an error.
+fn check(ret: c_int) -> io::Result<c_int> {
+    if ret < 0 {
+        Err(io::Error::from_raw_os_error(-ret))
+    } else {
+        Ok(ret)
+    }
+}
+
+/// An open journal handle, closed on drop.
+struct Journal(*mut SdJournal);
+
+impl Journal {
+    fn open() -> io::Result<Self> {
+        let mut j = ptr::null_mut();
+        // SAFETY: `j` is a valid out-pointer; on success it owns a handle
+        check(unsafe { sd_journal_open(&mut j, SD_JOURNAL_LOCAL_ONLY) })?;
+        Ok(Self(j))
+    }
+
+    /// Only show entries with this `FIELD=value`.
+    fn add_match(&mut self, field_value: &str) -> io::Result<()> {
+        let data = field_value.as_bytes();
+        // SAFETY: the data pointer and length describe a live byte slice
+        check(unsafe { sd_journal_add_match(self.0, data.as_ptr().cast(), data.len()) })?;
+        Ok(())
+    }
+
+    /// Position the cursor so that the next entry is the first new one.
+    fn seek_end(&mut self) -> io::Result<()> {
+        // SAFETY: `self.0` is an open handle
+        unsafe {
+            check(sd_journal_seek_tail(self.0))?;
+            // After seeking to the tail, step back onto the last entry so
+            // that `next` moves past it instead of re-reading it
+            check(sd_journal_previous(self.0))?;
+        }
+        Ok(())
+    }
+
+    /// Advance to the next entry; `false` at the end of the journal.
+    fn next(&mut self) -> io::Result<bool> {
+        // SAFETY: `self.0` is an open handle
+        Ok(check(unsafe { sd_journal_next(self.0) })? > 0)
+    }
+
+    /// The `MESSAGE` of the current entry, if it has one.
+    fn message(&mut self) -> io::Result<Option<Vec<u8>>> {
+        const FIELD: &[u8] = b"MESSAGE\0";
+        const PREFIX: &[u8] = b"MESSAGE=";
+        let mut data: *const c_void = ptr::null();
+        let mut len = 0usize;
+        // SAFETY: `FIELD` is NUL-terminated and the out-pointers are valid
+        let ret =
+            unsafe { sd_journal_get_data(self.0, FIELD.as_ptr().cast(), &mut data, &mut len) };
+        if ret == -ENOENT {
+            return Ok(None);
+        }
+        check(ret)?;
+
+        // SAFETY: on success `data` points to `len` bytes, valid until the
+        // cursor moves; copy them out right away
+        let bytes = unsafe { std::slice::from_raw_parts(data.cast::<u8>(), len) };
+        Ok(Some(bytes.strip_prefix(PREFIX).unwrap_or(bytes).to_vec()))
+    }
+
+    /// Sleep until the journal changes or `timeout` passes.
+    fn wait(&mut self, timeout: Duration) -> io::Result<()> {
+        // SAFETY: `self.0` is an open handle
+        check(unsafe { sd_journal_wait(self.0, timeout.as_micros() as u64) })?;
+        Ok(())
+    }
+}
+
+impl Drop for Journal {
+    fn drop(&mut self) {
+        // SAFETY: the handle is open and not used after this
+        unsafe { sd_journal_close(self.0) };
+    }
+}
+
+/// Read journal entries (optionally only those of `unit`) and pass each
+/// `MESSAGE` to `emit`, until the end of the journal (or, when following,
+/// until shutdown) or `emit` returns `false`. `started` is called once the
+/// journal is open and positioned.
+pub fn read(
+    unit: Option<&str>,
+    start_at: StartAt,
+    follow: bool,
+    shutdown: &AtomicBool,
+    started: impl FnOnce(),
+    mut emit: impl FnMut(Vec<u8>) -> bool,
+) -> io::Result<()> {
+    let mut journal = Journal::open()?;
+    if let Some(unit) = unit {
+        journal.add_match(&format!("_SYSTEMD_UNIT={unit}"))?;
+    }
+    if start_at == StartAt::End {
+        journal.seek_end()?;
+    }
+    started();
+
+    while !shutdown.load(Ordering::Relaxed) {
+        if !journal.next()? {
+            if !follow {
+                break;
+            }
+            journal.wait(WAIT_INTERVAL)?;
+            continue;
+        }
+
+        if let Some(message) = journal.message()?
+            && !emit(message)
+        {
+            break;
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,132 @@
+// src/level.rs
+use crate::config::ConfigError;
+use crate::filters;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+
+/// Finds the level token when no `level_pattern` is configured.
+const DEFAULT_LEVEL_PATTERN: &str = r"(?i)\b(trace|debug|info|notice|warn(?:ing)?|err(?:or)?|crit(?:ical)?|fatal|panic|emerg|alert)\b";
+
+/// Log severity, from least to most severe.
+#[derive(Debug, Deserialize, Serialize, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+#[serde(rename_all = "snake_case")]
+pub enum Level {
+    Trace,
+    Debug,
+    Info,
+    Warn,
+    Error,
+    Fatal,
+}
+
+impl Level {
+    /// Map a common level spelling (any case) to a `Level`.
+    pub fn parse(token: &str) -> Option<Self> {
+        let level = match token.to_ascii_lowercase().as_str() {
+            "trace" | "trc" => Level::Trace,
+            "debug" | "dbg" => Level::Debug,
+            "info" | "inf" | "information" | "notice" => Level::Info,
+            "warn" | "warning" | "wrn" => Level::Warn,
+            "error" | "err" | "eror" => Level::Error,
+            "fatal" | "crit" | "critical" | "panic" | "emerg" | "alert" => Level::Fatal,
+            _ => return None,
+        };
+        Some(level)
+    }
+}
+
+/// Drops lines whose level is below `min_level`.
+#[derive(Debug)]
+pub struct LevelFilter {
+    min: Level,
+    pattern: Regex,
+    keep_unleveled: bool,
+}
+
+impl LevelFilter {
+    pub fn compile(
+        min: Level,
+        pattern: Option<&str>,
+        keep_unleveled: bool,
+        size_limit: usize,
+    ) -> Result<Self, ConfigError> {
+        let pattern = pattern.unwrap_or(DEFAULT_LEVEL_PATTERN);
+        Ok(Self {
+            min,
+            pattern: filters::build_regex(pattern, "level_pattern", size_limit)?,
+            keep_unleveled,
+        })
+    }
+
+    /// Whether `line` is at or above the minimum level.
+    pub fn keeps(&self, line: &str) -> bool {
+        match level_of(&self.pattern, line) {
+            Some(level) => level >= self.min,
+            None => self.keep_unleveled,
+        }
+    }
+}
+
+/// The level of `line`: the first capture group of the first match of
+/// `pattern` (or the whole match if the pattern has no groups).
+fn level_of(pattern: &Regex, line: &str) -> Option<Level> {
+    let caps = pattern.captures(line)?;
+    let token = caps.get(1).or_else(|| caps.get(0))?;
+    Level::parse(token.as_str())
+}
+
+/// Picks the glyph shown before a line of each level (`output.level_icons`).
+#[derive(Debug, Clone)]
+pub struct LevelIcons {
+    pattern: Regex,
+    ascii: bool,
+}
+
+impl LevelIcons {
+    /// Detect levels with `pattern` (the default level pattern if `None`),
+    /// using `[E]`-style markers instead of emoji if `ascii` is set.
+    pub fn compile(
+        pattern: Option<&str>,
+        ascii: bool,
+        size_limit: usize,
+    ) -> Result<Self, ConfigError> {
+        let pattern = pattern.unwrap_or(DEFAULT_LEVEL_PATTERN);
+        Ok(Self {
+            pattern: filters::build_regex(pattern, "level_pattern", size_limit)?,
+            ascii,
+        })
+    }
+
+    /// The icon for the level of `line`, if it has one.
+    pub fn icon(&self, line: &str) -> Option<&'static str> {
+        let level = level_of(&self.pattern, line)?;
+        let icon = match (level, self.ascii) {
+            (Level::Trace, false) => "🔎",
+            (Level::Debug, false) => "🐛",
+            (Level::Info, false) => "ℹ️",
+            (Level::Warn, false) => "⚠️",
+            (Level::Error, false) => "❌",
+            (Level::Fatal, false) => "💀",
+            (Level::Trace, true) => "[T]",
+            (Level::Debug, true) => "[D]",
+            (Level::Info, true) => "[I]",
+            (Level::Warn, true) => "[W]",
+            (Level::Error, true) => "[E]",
+            (Level::Fatal, true) => "[F]",
+        };
+        Some(icon)
+    }
+}
+
+/// Whether the locale (`LC_ALL`, `LC_CTYPE` or `LANG`, the first one set)
+/// uses UTF-8. Assumed when none is set.
+pub fn unicode_locale() -> bool {
+    ["LC_ALL", "LC_CTYPE", "LANG"]
+        .iter()
+        .filter_map(|key| std::env::var(key).ok())
+        .find(|value| !value.is_empty())
+        .is_none_or(|value| {
+            let value = value.to_ascii_lowercase();
+            value.contains("utf-8") || value.contains("utf8")
+        })
+}
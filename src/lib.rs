@@ -0,0 +1,46 @@
+// src/lib.rs
+//! Filtering and aggregation of log lines from multiple sources.
+//!
+//! The `logscout` binary is a thin wrapper around [`run_with_config`].
+
+mod ansi;
+pub mod config;
+pub mod context;
+pub mod dedup;
+pub mod dir_output;
+pub mod fields;
+pub mod filters;
+mod follow;
+pub mod health;
+#[cfg(all(feature = "journald", target_os = "linux"))]
+mod journald;
+pub mod level;
+pub mod logline;
+pub mod multiline;
+pub mod output;
+mod pipeline;
+pub mod raw_output;
+mod reader;
+pub mod redact;
+pub mod reorder;
+pub mod replay;
+pub mod rollup;
+pub mod sample;
+pub mod sink_policy;
+pub mod stats;
+pub mod stream;
+pub mod syslog;
+pub mod tags;
+pub mod tcp;
+mod timeutil;
+pub mod webhook;
+pub mod websocket;
+#[cfg(all(feature = "windows_event", windows))]
+mod windows_event;
+
+pub use config::{Config, ConfigError};
+pub use filters::{ClassificationSummary, FilterDecision, Filters};
+pub use logline::LogLine;
+pub use pipeline::{Controls, run_with_config, run_with_controls};
+pub use stats::Stats;
+pub use stream::StreamFilter;
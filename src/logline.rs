@@ -1,4 +1,10 @@
 // src/logline.rs
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::fmt;
+use std::net::SocketAddr;
+use std::path::PathBuf;
+use std::sync::Arc;
 use std::time::SystemTime;
 
 #[derive(Debug, Clone)]
@@ -9,6 +15,80 @@ pub struct LogLine {
     /// Raw text of the line
     pub line: String,
 
+    /// Original bytes of the line, kept only in `binary_match` mode
+    pub raw: Option<Vec<u8>>,
+
+    /// Fields parsed from the line (empty unless the source has `parse` set)
+    pub fields: BTreeMap<String, String>,
+
     /// When we read it
     pub timestamp: SystemTime,
+
+    /// When the event happened according to the line itself. Only parsed
+    /// when something needs it (`replay`).
+    pub event_time: Option<SystemTime>,
+
+    /// Where exactly the line came from (file path or command PID), if known.
+    /// Shared by every line of a reader.
+    pub meta: Option<Arc<SourceMeta>>,
+
+    /// Line number within the file or stream being read (from 1), counted
+    /// from where reading started. A multiline record has the number of its
+    /// first line. 0 if unknown.
+    pub lineno: u64,
+
+    /// Name of the first `tags` rule the line matched, if any.
+    pub tag: Option<Arc<str>>,
+
+    /// Set on the marker a reader sends when its source ends
+    /// (`emit_lifecycle_events`). Such a line is written as an event and
+    /// never filtered or counted.
+    pub closed: bool,
+
+    /// Position among all lines of the run, across sources (from 1).
+    /// Set when the consumer takes the line, so it never ties.
+    pub seq: u64,
+}
+
+impl LogLine {
+    /// A plain line of `source`, read just now.
+    pub fn new(source: impl Into<String>, line: impl Into<String>) -> Self {
+        Self {
+            source: source.into(),
+            line: line.into(),
+            raw: None,
+            fields: BTreeMap::new(),
+            timestamp: SystemTime::now(),
+            event_time: None,
+            meta: None,
+            lineno: 0,
+            tag: None,
+            closed: false,
+            seq: 0,
+        }
+    }
+}
+
+/// Details about the origin of a line, beyond the source name.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SourceMeta {
+    /// Absolute path of the file (or named pipe) being read.
+    Path(PathBuf),
+
+    /// Process ID of the running command.
+    Pid(u32),
+
+    /// Address of the TCP peer that sent the line.
+    Peer(SocketAddr),
+}
+
+impl fmt::Display for SourceMeta {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SourceMeta::Path(path) => write!(f, "{}", path.display()),
+            SourceMeta::Pid(pid) => write!(f, "{pid}"),
+            SourceMeta::Peer(addr) => write!(f, "{addr}"),
+        }
+    }
 }
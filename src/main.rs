@@ -1,45 +1,58 @@
 // src/main.rs
-mod config;
-mod filters;
-mod logline;
-mod reader;
-mod stats;
-
-use crate::config::{Config, ConfigError};
-use crate::filters::Filters;
-use crate::logline::LogLine;
-use crate::stats::Stats;
+mod cli;
+
+use crate::cli::{Cli, Command, PrintFormat, USAGE};
+use logscout::output::Destination;
+#[cfg(unix)]
+use logscout::syslog::{SyslogWriter, default_socket};
+use logscout::{Config, ConfigError, Controls, Stats, run_with_controls};
 use std::env;
-use std::path::Path;
-use std::sync::{
-    Arc,
-    atomic::{AtomicBool, Ordering},
-    mpsc,
-};
+use std::io;
+use std::sync::atomic::Ordering;
+#[cfg(unix)]
+use std::sync::{Arc, Mutex, atomic::AtomicBool};
 
 fn main() {
-    if let Err(err) = run() {
-        eprintln!("[logscout]: error: {err}");
-        std::process::exit(1);
+    let cli = match Cli::parse(env::args().skip(1)) {
+        Ok(Command::Run(cli)) => cli,
+        Ok(Command::Help) => {
+            print!("{USAGE}");
+            return;
+        }
+        Ok(Command::Version) => {
+            println!("logscout {}", env!("CARGO_PKG_VERSION"));
+            return;
+        }
+        Err(err) => {
+            eprintln!("[logscout]: error: {err}");
+            eprint!("{USAGE}");
+            std::process::exit(2);
+        }
+    };
+
+    // Like grep: 0 if any line was emitted, 1 if none, 2 on errors
+    match run(&cli) {
+        Ok(true) => {}
+        Ok(false) => std::process::exit(1),
+        Err(err) => {
+            eprintln!("[logscout]: error: {err}");
+            std::process::exit(2);
+        }
     }
 }
 
-fn run() -> Result<(), ConfigError> {
-    let config_path = env::args()
-        .nth(1)
-        .unwrap_or_else(|| "config.yaml".to_string());
-
-    let path = Path::new(&config_path);
-
-    let cfg = Config::from_file(path)?;
-
-    // Build filters (can fil if regex is invalid)
-    let filters = Filters::from_config(&cfg)?;
+/// Run with the given options. Returns whether any line passed the filters.
+fn run(cli: &Cli) -> Result<bool, ConfigError> {
+    let cfg = load_config(cli)?;
+    if let Some(format) = cli.print_config {
+        print_config(&cfg, format);
+        return Ok(true);
+    }
 
     // Shared shutdown flag (Ctrl+C)
-    let shutdown = Arc::new(AtomicBool::new(false));
+    let controls = Controls::default();
     {
-        let shutdown_flag = shutdown.clone();
+        let shutdown_flag = controls.shutdown.clone();
         ctrlc::set_handler(move || {
             // Only print on first [Ctrl]+[C]
             let first = !shutdown_flag.swap(true, Ordering::SeqCst);
@@ -50,52 +63,249 @@ fn run() -> Result<(), ConfigError> {
         .expect("[logscout] Error setting Ctrl-C handler");
     }
 
-    // Set up channels
-    let (tx, rx) = mpsc::channel::<LogLine>();
-
-    // Spawn reader threads for all source with shutdown flag
-    let _handles = reader::spawn_readers(&cfg.sources, tx, shutdown.clone());
+    // SIGUSR1 toggles count-through (count lines without printing them)
+    #[cfg(unix)]
+    spawn_count_through_toggle(controls.count_through.clone());
 
-    // Stats (atomic counters)
-    let stats = Arc::new(Stats::new());
+    // SIGHUP reloads the config (sources and filters)
+    #[cfg(unix)]
+    spawn_reload_on_sighup(cli.clone(), controls.reload.clone());
 
-    // Consume data
-    println!("[logscout] Waiting for log lines...");
-    for msg in rx {
-        if shutdown.load(Ordering::SeqCst) {
-            break;
+    if !cfg.quiet {
+        println!("[logscout] Waiting for log lines...");
+    }
+    let stats = match cfg.output.destination {
+        Destination::Stdout => run_with_controls(&cfg, io::stdout().lock(), controls)?,
+        #[cfg(unix)]
+        Destination::Syslog => {
+            let syslog = SyslogWriter::connect(&cfg.output).map_err(|source| {
+                let socket = cfg.output.syslog_socket.clone();
+                ConfigError::OutputIo {
+                    path: socket.unwrap_or_else(default_socket).display().to_string(),
+                    source,
+                }
+            })?;
+            run_with_controls(&cfg, syslog, controls)?
         }
+        // Rejected when the config is validated
+        #[cfg(not(unix))]
+        Destination::Syslog => unreachable!("syslog output on a non-Unix system"),
+    };
 
-        stats.inc_total();
-
-        match filters.classify(&msg.line) {
-            filters::FilterDecision::Excluded => {
-                stats.inc_excluded();
-                // Silently ignore excluded lines
-            }
+    if cli.stats_json {
+        let summary =
+            serde_json::to_string(&stats.summary()).expect("summary serialization failed");
+        println!("{summary}");
+    } else if cfg.count_only {
+        print_counts(&stats);
+    } else {
+        print_summary(&cfg, &stats);
+    }
 
-            filters::FilterDecision::Included => {
-                stats.inc_included();
-                println!("[{}] {}", msg.source, msg.line);
-            }
+    // Sampled-out and duplicate lines matched too; they just weren't printed
+    let (_, included, _) = stats.snapshot();
+    Ok(included + stats.sampled_out() + stats.duplicates() > 0)
+}
 
-            filters::FilterDecision::Passed => {
-                stats.inc_included();
-                println!("[{}] {}", msg.source, msg.line);
-            }
+/// Load the config files and apply the command-line overrides.
+fn load_config(cli: &Cli) -> Result<Config, ConfigError> {
+    let mut cfg = Config::from_files(&cli.configs)?;
+    cfg.quiet |= cli.quiet;
+    cfg.output.no_unicode |= cli.no_unicode;
+    if cli.duration_secs.is_some() {
+        cfg.duration_secs = cli.duration_secs;
+    }
+    if cli.max_lines.is_some() {
+        cfg.max_lines = cli.max_lines;
+    }
+    cfg.select_sources(&cli.only, &cli.mute)?;
+    if cli.ordered && !cfg.ordered {
+        cfg.ordered = true;
+        cfg.check_ordered()?;
+    }
+    Ok(cfg)
+}
 
-            filters::FilterDecision::DroppedNoIncludeMatch => {
-                // Do nothing
-            }
+/// Print the config as it will be used: merged, with the command-line
+/// overrides and source defaults applied (`--print-config`).
+fn print_config(cfg: &Config, format: PrintFormat) {
+    let text = match format {
+        PrintFormat::Yaml => serde_yaml::to_string(cfg).expect("config serialization failed"),
+        PrintFormat::Json => {
+            serde_json::to_string_pretty(cfg).expect("config serialization failed") + "\n"
         }
-    }
+    };
+    print!("{text}");
+}
 
-    // After loop, print the summary
+/// Print the human-readable summary.
+fn print_summary(cfg: &Config, stats: &Stats) {
     let (total, included, excluded) = stats.snapshot();
     println!("\n[logscout] Summary:");
     println!("  Total lines processed: {}", total);
+    let bytes = stats.total_bytes();
+    let avg = if total > 0 {
+        bytes as f64 / total as f64
+    } else {
+        0.0
+    };
+    println!("  Bytes processed: {} ({:.1} bytes/line)", bytes, avg);
     println!("  Included lines: {}", included);
     println!("  Excluded lines: {}", excluded);
+    if cfg.sample.is_some() {
+        println!("  Sampled out lines: {}", stats.sampled_out());
+    }
+    if cfg.dedup_window_secs.is_some() {
+        println!("  Duplicate lines: {}", stats.duplicates());
+    }
+    if cfg.since.is_some() || cfg.until.is_some() {
+        println!("  Lines outside since/until: {}", stats.out_of_range());
+    }
+    if cfg.min_severity.is_some() {
+        println!("  Lines below min_severity: {}", stats.below_severity());
+    }
+    if cfg.skip_empty_lines {
+        println!("  Empty lines skipped: {}", stats.skipped_empty());
+    }
+
+    if let Some((source, counts)) = stats.busiest_source() {
+        println!(
+            "  Busiest source: {} ({} lines, peak {} lines/s at {})",
+            source,
+            counts.total,
+            counts.peak_per_sec,
+            counts.peak_at()
+        );
+    }
 
-    Ok(())
+    let (waits, waited) = stats.queue_waits();
+    if waits > 0 {
+        println!(
+            "  Reader waits on a full queue: {} ({:.1}s total)",
+            waits,
+            waited.as_secs_f64()
+        );
+    }
+
+    let pattern_hits = stats.pattern_hits();
+    if !pattern_hits.is_empty() {
+        println!("  Pattern hits:");
+        for p in pattern_hits {
+            let note = if p.hits == 0 { " (never matched)" } else { "" };
+            println!("    {} `{}`: {}{}", p.list, p.pattern, p.hits, note);
+        }
+    }
+
+    let tag_counts = stats.tag_counts();
+    if !tag_counts.is_empty() {
+        println!("  Tags:");
+        for t in tag_counts {
+            println!("    {}: {}", t.tag, t.lines);
+        }
+    }
+
+    let samples = stats.samples();
+    if !samples.is_empty() {
+        println!("  Sample lines:");
+        for (source, sample) in samples {
+            println!("    [{}]", source);
+            for line in &sample.first {
+                println!("      {}", line);
+            }
+            if sample.skipped() > 0 {
+                println!("      ... ({} more)", sample.skipped());
+            }
+            for line in &sample.last {
+                println!("      {}", line);
+            }
+        }
+    }
+
+    let first_lines = stats.first_lines();
+    println!("  Time to first line:");
+    for src in &cfg.sources {
+        match first_lines.get(&src.name) {
+            Some(after) => println!("    {}: {:.3}s", src.name, after.as_secs_f64()),
+            None => println!("    {}: no lines", src.name),
+        }
+    }
+
+    let exit_statuses = stats.exit_statuses();
+    if !exit_statuses.is_empty() {
+        println!("  Command exit statuses:");
+        for (source, status) in exit_statuses {
+            println!("    {}: {}", source, status);
+        }
+    }
+}
+
+/// Print how many lines matched each include pattern (`count_only`), one
+/// `pattern: count` per line, like `grep -c`.
+fn print_counts(stats: &Stats) {
+    for p in stats.pattern_hits() {
+        if p.list != "exclude" {
+            println!("{}: {}", p.pattern, p.hits);
+        }
+    }
+}
+
+/// Flip `flag` on every SIGUSR1 and say which mode we are in.
+#[cfg(unix)]
+fn spawn_count_through_toggle(flag: Arc<AtomicBool>) {
+    use signal_hook::{consts::SIGUSR1, iterator::Signals};
+
+    let mut signals = match Signals::new([SIGUSR1]) {
+        Ok(s) => s,
+        Err(e) => {
+            eprintln!("[logscout] could not install SIGUSR1 handler: {e}");
+            return;
+        }
+    };
+
+    std::thread::spawn(move || {
+        for _ in signals.forever() {
+            let now_on = !flag.fetch_xor(true, Ordering::SeqCst);
+            if now_on {
+                eprintln!(
+                    "[logscout] Count-through on: counting lines without printing (SIGUSR1 to resume)"
+                );
+            } else {
+                eprintln!("[logscout] Count-through off: printing resumed");
+            }
+        }
+    });
+}
+
+/// Re-read the config on every SIGHUP and hand it to the running pipeline.
+/// A config that fails to load is reported and the current one kept, as
+/// is a config read from stdin, which can't be read again.
+#[cfg(unix)]
+fn spawn_reload_on_sighup(cli: Cli, slot: Arc<Mutex<Option<Config>>>) {
+    use signal_hook::{consts::SIGHUP, iterator::Signals};
+
+    let mut signals = match Signals::new([SIGHUP]) {
+        Ok(s) => s,
+        Err(e) => {
+            eprintln!("[logscout] could not install SIGHUP handler: {e}");
+            return;
+        }
+    };
+
+    // Stdin was read to its end for the first load
+    let from_stdin = cli.configs.iter().any(|path| path.as_os_str() == "-");
+
+    std::thread::spawn(move || {
+        for _ in signals.forever() {
+            if from_stdin {
+                eprintln!("[logscout] reload ignored: the config was read from stdin");
+                continue;
+            }
+            match load_config(&cli) {
+                Ok(cfg) => *slot.lock().unwrap() = Some(cfg),
+                Err(e) => {
+                    eprintln!("[logscout] reload failed, keeping the current config: {e}")
+                }
+            }
+        }
+    });
 }
@@ -0,0 +1,175 @@
+// src/multiline.rs
+use crate::config::ConfigError;
+use crate::filters;
+use crate::logline::LogLine;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+
+/// Exactly one of `continuation` or `start_pattern` must be set.
+#[derive(Debug, Deserialize, Serialize, Clone, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct MultilineConfig {
+    /// Lines of this shape are appended to the previous record.
+    #[serde(default)]
+    pub continuation: Option<Continuation>,
+
+    /// A line matching this regex starts a new record; any other line
+    /// is appended to the current one.
+    #[serde(default)]
+    pub start_pattern: Option<String>,
+
+    /// Maximum size of a joined record in bytes. A line that would push a
+    /// record past this starts a new record instead.
+    #[serde(default = "default_max_bytes")]
+    pub max_bytes: usize,
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone, Copy, PartialEq, Eq)]
+pub enum Continuation {
+    /// Lines starting with a space or tab belong to the previous record.
+    #[serde(rename = "indented")]
+    Indented,
+}
+
+fn default_max_bytes() -> usize {
+    64 * 1024
+}
+
+impl MultilineConfig {
+    /// Check the settings for the source named `source`.
+    pub fn validate(&self, source: &str, size_limit: usize) -> Result<(), ConfigError> {
+        match (&self.continuation, &self.start_pattern) {
+            (Some(_), Some(_)) | (None, None) => {
+                return Err(ConfigError::Invalid(format!(
+                    "Source `{source}`: multiline needs exactly one of `continuation` or `start_pattern`."
+                )));
+            }
+            (None, Some(pattern)) => {
+                filters::build_regex(pattern, "multiline start", size_limit)?;
+            }
+            (Some(_), None) => {}
+        }
+
+        if self.max_bytes == 0 {
+            return Err(ConfigError::Invalid(format!(
+                "Source `{source}`: multiline.max_bytes must be greater than zero."
+            )));
+        }
+
+        Ok(())
+    }
+}
+
+/// How a `Joiner` decides where records begin.
+#[derive(Debug)]
+enum Mode {
+    Continuation(Continuation),
+    Start(Regex),
+}
+
+/// Joins continuation lines into the record that precedes them.
+#[derive(Debug)]
+pub struct Joiner {
+    mode: Mode,
+    max_bytes: usize,
+    pending: Option<LogLine>,
+}
+
+impl Joiner {
+    /// Build a joiner from a config that already passed `validate` with
+    /// the same `size_limit`.
+    pub fn new(cfg: MultilineConfig, size_limit: usize) -> Self {
+        let mode = match (cfg.continuation, cfg.start_pattern) {
+            (Some(c), _) => Mode::Continuation(c),
+            (None, Some(p)) => Mode::Start(
+                filters::build_regex(&p, "multiline start", size_limit)
+                    .expect("validated start_pattern"),
+            ),
+            (None, None) => unreachable!("validated multiline config"),
+        };
+
+        Self {
+            mode,
+            max_bytes: cfg.max_bytes,
+            pending: None,
+        }
+    }
+
+    /// Feed one line. Returns a finished record when `line` starts a new one.
+    pub fn push(&mut self, line: LogLine) -> Option<LogLine> {
+        let continues = self.is_continuation(&line);
+        if let Some(pending) = self.pending.as_mut()
+            && continues
+            && pending.line.len() + 1 + line.line.len() <= self.max_bytes
+        {
+            pending.line.push('\n');
+            pending.line.push_str(&line.line);
+            if let (Some(raw), Some(extra)) = (pending.raw.as_mut(), line.raw) {
+                raw.push(b'\n');
+                raw.extend_from_slice(&extra);
+            }
+            return None;
+        }
+
+        self.pending.replace(line)
+    }
+
+    /// Take the record being built, if any (on EOF or shutdown).
+    pub fn flush(&mut self) -> Option<LogLine> {
+        self.pending.take()
+    }
+
+    fn is_continuation(&self, line: &LogLine) -> bool {
+        match &self.mode {
+            Mode::Continuation(Continuation::Indented) => line.line.starts_with([' ', '\t']),
+            Mode::Start(re) => !re.is_match(&line.line),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn indented(max_bytes: usize) -> Joiner {
+        let cfg = MultilineConfig {
+            continuation: Some(Continuation::Indented),
+            start_pattern: None,
+            max_bytes,
+        };
+        Joiner::new(cfg, 1 << 20)
+    }
+
+    /// Feed `lines`, then flush, returning the text of every record.
+    fn join(joiner: &mut Joiner, lines: &[&str]) -> Vec<String> {
+        let mut records: Vec<_> = lines
+            .iter()
+            .filter_map(|l| joiner.push(LogLine::new("app", *l)))
+            .collect();
+        records.extend(joiner.flush());
+        records.into_iter().map(|r| r.line).collect()
+    }
+
+    #[test]
+    fn indented_lines_join_the_record_before_them() {
+        let mut joiner = indented(default_max_bytes());
+        let records = join(
+            &mut joiner,
+            &["panic: boom", "  at main.rs:1", "\tat lib.rs:2", "next"],
+        );
+        // `next` only comes out with the flush at EOF
+        assert_eq!(
+            records,
+            ["panic: boom\n  at main.rs:1\n\tat lib.rs:2", "next"]
+        );
+        assert!(joiner.flush().is_none());
+    }
+
+    #[test]
+    fn max_bytes_starts_a_new_record() {
+        // `head` plus one 4-byte continuation is 9 bytes; a second is too many
+        let mut joiner = indented(9);
+        let records = join(&mut joiner, &["head", "  a1", "  a2"]);
+        assert_eq!(records, ["head\n  a1", "  a2"]);
+    }
+}
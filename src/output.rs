@@ -0,0 +1,802 @@
+// src/output.rs
+use crate::config::{Config, ConfigError, SourceConfig, default_regex_size_limit};
+use crate::dir_output::DirOutputConfig;
+use crate::filters;
+use crate::level::{self, LevelIcons};
+use crate::logline::{LogLine, SourceMeta};
+use crate::sink_policy::OnError;
+use crate::syslog::{Facility, Severity, SyslogFormat};
+use crate::timeutil;
+use crate::webhook::WebhookConfig;
+use crate::websocket::WebSocketConfig;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::io::{self, IsTerminal, Write};
+use std::path::PathBuf;
+use std::time::{Duration, Instant, UNIX_EPOCH};
+
+/// Version of the JSON record shape. Bump whenever a field is renamed,
+/// removed, or changes meaning. Adding new optional fields does not bump it.
+pub const SCHEMA_VERSION: u32 = 1;
+
+#[derive(Debug, Deserialize, Serialize, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct OutputConfig {
+    /// Where the main output goes: stdout, or the local syslog daemon.
+    #[serde(default)]
+    pub destination: Destination,
+
+    /// How each emitted line is rendered.
+    #[serde(default)]
+    pub format: OutputFormat,
+
+    /// Line layout for the text format, e.g. `{timestamp} {source}: {line}`.
+    /// Defaults to `[{source}] {line}`.
+    #[serde(default)]
+    pub template: Option<String>,
+
+    /// Prefix text lines with the source name (shorthand for a template).
+    #[serde(default = "default_prefix")]
+    pub prefix: bool,
+
+    /// Write `source<separator>line` instead of `[source] line`.
+    #[serde(default)]
+    pub prefix_separator: Option<String>,
+
+    /// Regex whose named groups the template can show as `{cap.<name>}`.
+    #[serde(default)]
+    pub extract: Option<String>,
+
+    /// Flush buffered output after this many lines.
+    #[serde(default = "default_flush_lines")]
+    pub flush_lines: usize,
+
+    /// Flush buffered output at least this often (milliseconds).
+    #[serde(default = "default_flush_interval_ms")]
+    pub flush_interval_ms: u64,
+
+    /// What to do when writing to the main output fails.
+    #[serde(default)]
+    pub on_error: OnError,
+
+    /// Also archive emitted lines into dated per-source files.
+    #[serde(default)]
+    pub dir: Option<DirOutputConfig>,
+
+    /// Serve emitted lines (as JSON) to WebSocket clients.
+    #[serde(default)]
+    pub websocket: Option<WebSocketConfig>,
+
+    /// POST emitted lines (as JSON) to an HTTP endpoint in batches.
+    #[serde(default)]
+    pub webhook: Option<WebhookConfig>,
+
+    /// Syslog facility with `destination: syslog` (default `user`).
+    #[serde(default)]
+    pub facility: Option<Facility>,
+
+    /// Syslog severity with `destination: syslog` (default `info`).
+    #[serde(default)]
+    pub severity: Option<Severity>,
+
+    /// Start text lines with an icon for their level, e.g. `❌` for errors.
+    #[serde(default)]
+    pub level_icons: bool,
+
+    /// Use ASCII markers such as `[E]` instead of emoji for `level_icons`.
+    #[serde(default)]
+    pub no_unicode: bool,
+
+    /// Message format with `destination: syslog`.
+    #[serde(default)]
+    pub syslog_format: SyslogFormat,
+
+    /// Syslog daemon socket. Defaults to `/dev/log`.
+    #[serde(default)]
+    pub syslog_socket: Option<PathBuf>,
+}
+
+impl Default for OutputConfig {
+    fn default() -> Self {
+        Self {
+            destination: Destination::default(),
+            format: OutputFormat::default(),
+            template: None,
+            prefix: default_prefix(),
+            prefix_separator: None,
+            extract: None,
+            flush_lines: default_flush_lines(),
+            flush_interval_ms: default_flush_interval_ms(),
+            on_error: OnError::default(),
+            dir: None,
+            websocket: None,
+            webhook: None,
+            facility: None,
+            severity: None,
+            level_icons: false,
+            no_unicode: false,
+            syslog_format: SyslogFormat::default(),
+            syslog_socket: None,
+        }
+    }
+}
+
+/// Where the main output is written.
+#[derive(Debug, Deserialize, Serialize, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum Destination {
+    #[default]
+    Stdout,
+
+    /// One message per line to the syslog daemon's Unix socket (Unix only).
+    Syslog,
+}
+
+const DEFAULT_TEMPLATE: &str = "[{source}] {line}";
+
+fn default_prefix() -> bool {
+    true
+}
+
+fn default_flush_lines() -> usize {
+    256
+}
+
+fn default_flush_interval_ms() -> u64 {
+    100
+}
+
+impl OutputConfig {
+    /// The text layout from `template`, `prefix` and `prefix_separator`,
+    /// with the `extract` regex for its `{cap.<name>}` placeholders.
+    pub fn text_template(&self) -> Result<Template, ConfigError> {
+        let extract = self
+            .extract
+            .as_deref()
+            .map(|p| filters::build_regex(p, "output.extract", default_regex_size_limit()))
+            .transpose()?;
+        self.layout()?.with_extract(extract)
+    }
+
+    fn layout(&self) -> Result<Template, ConfigError> {
+        let shorthand = !self.prefix || self.prefix_separator.is_some();
+        match (&self.template, &self.prefix_separator) {
+            (Some(_), _) if shorthand => Err(ConfigError::Invalid(
+                "output.template cannot be combined with `prefix` or `prefix_separator`.".into(),
+            )),
+            (Some(template), _) => Template::parse(template),
+            (None, Some(_)) if !self.prefix => Err(ConfigError::Invalid(
+                "output.prefix_separator has no effect with `prefix: false`.".into(),
+            )),
+            (None, Some(sep)) => Ok(Template {
+                parts: vec![Part::Source, Part::Literal(sep.clone()), Part::Line],
+                extract: None,
+            }),
+            (None, None) if !self.prefix => Ok(Template {
+                parts: vec![Part::Line],
+                extract: None,
+            }),
+            (None, None) => Template::parse(DEFAULT_TEMPLATE),
+        }
+    }
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone, Copy, Default, PartialEq, Eq)]
+pub enum OutputFormat {
+    /// Lines laid out by `template` (`[source] line` by default).
+    #[default]
+    #[serde(rename = "text")]
+    Text,
+
+    /// One JSON object per line.
+    #[serde(rename = "json")]
+    Json,
+
+    /// One CSV row per line, after a header row.
+    #[serde(rename = "csv")]
+    Csv,
+}
+
+/// Header row of the CSV format.
+pub const CSV_HEADER: &str = "source,timestamp,line,fields";
+
+/// A single JSON output record.
+#[derive(Debug, Serialize)]
+struct JsonRecord<'a> {
+    v: u32,
+    source: &'a str,
+    line: &'a str,
+    /// Milliseconds since the UNIX epoch when the line was read.
+    timestamp_ms: u128,
+    /// Order of the line among all sources.
+    seq: u64,
+    /// Line number within its file or stream.
+    lineno: u64,
+    /// Name of the first `tags` rule the line matched.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    tag: Option<&'a str>,
+    /// File path or command PID, e.g. `{"path": "/var/log/app.log"}`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    meta: Option<&'a SourceMeta>,
+    /// Parsed fields, if the source has `parse` set.
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    fields: &'a BTreeMap<String, String>,
+    /// Shown only as context around an emitted line.
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    context: bool,
+}
+
+/// A JSON event about a source rather than a line of it.
+#[derive(Debug, Serialize)]
+struct EventRecord<'a> {
+    v: u32,
+    /// What happened, e.g. `source_closed`.
+    event: &'static str,
+    source: &'a str,
+    /// Milliseconds since the UNIX epoch when it happened.
+    timestamp_ms: u128,
+}
+
+/// One piece of a parsed output template.
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Part {
+    Literal(String),
+    Source,
+    Line,
+    Timestamp,
+    Seq,
+    Lineno,
+    Tag,
+    Meta,
+    /// A named group of the `extract` regex.
+    Capture(String),
+}
+
+/// An output template, parsed once and applied to every line.
+#[derive(Debug, Clone)]
+pub struct Template {
+    parts: Vec<Part>,
+    extract: Option<Regex>,
+}
+
+impl Template {
+    /// Parse `{source}`, `{line}`, `{timestamp}`, `{seq}`, `{lineno}`,
+    /// `{tag}`, `{meta}` and `{cap.<name>}` placeholders.
+    pub fn parse(template: &str) -> Result<Self, ConfigError> {
+        let mut parts = Vec::new();
+        let mut rest = template;
+
+        while let Some(start) = rest.find('{') {
+            let Some(len) = rest[start..].find('}') else {
+                return Err(ConfigError::Invalid(format!(
+                    "output.template: unclosed `{{` in `{template}`"
+                )));
+            };
+            if start > 0 {
+                parts.push(Part::Literal(rest[..start].to_string()));
+            }
+            parts.push(match &rest[start + 1..start + len] {
+                "source" => Part::Source,
+                "line" => Part::Line,
+                "timestamp" => Part::Timestamp,
+                "seq" => Part::Seq,
+                "lineno" => Part::Lineno,
+                "tag" => Part::Tag,
+                "meta" => Part::Meta,
+                name if name.starts_with("cap.") => Part::Capture(name[4..].to_string()),
+                name => {
+                    return Err(ConfigError::Invalid(format!(
+                        "output.template: unknown placeholder `{{{name}}}`"
+                    )));
+                }
+            });
+            rest = &rest[start + len + 1..];
+        }
+        if !rest.is_empty() {
+            parts.push(Part::Literal(rest.to_string()));
+        }
+
+        Ok(Self {
+            parts,
+            extract: None,
+        })
+    }
+
+    /// Resolve `{cap.<name>}` placeholders from the named groups of
+    /// `extract`, checking that each one exists.
+    pub fn with_extract(mut self, extract: Option<Regex>) -> Result<Self, ConfigError> {
+        for part in &self.parts {
+            let Part::Capture(name) = part else { continue };
+            let Some(re) = &extract else {
+                return Err(ConfigError::Invalid(format!(
+                    "output.template: `{{cap.{name}}}` needs an `output.extract` regex."
+                )));
+            };
+            if !re.capture_names().flatten().any(|n| n == name) {
+                return Err(ConfigError::Invalid(format!(
+                    "output.template: `output.extract` has no group named `{name}`."
+                )));
+            }
+        }
+        self.extract = extract;
+        Ok(self)
+    }
+
+    pub fn render(&self, msg: &LogLine) -> String {
+        self.render_styled(msg, &msg.source, None)
+    }
+
+    /// Render with `source` shown in place of the source name, in `color`.
+    fn render_styled(&self, msg: &LogLine, source: &str, color: Option<Color>) -> String {
+        let mut out = String::with_capacity(source.len() + msg.line.len() + 16);
+        // Only lines the regex matches have captures; the others show none
+        let captures = self.extract.as_ref().and_then(|re| re.captures(&msg.line));
+        for part in &self.parts {
+            match part {
+                Part::Literal(s) => out.push_str(s),
+                Part::Source => match color {
+                    Some(c) => {
+                        out.push_str(c.ansi());
+                        out.push_str(source);
+                        out.push_str(ANSI_RESET);
+                    }
+                    None => out.push_str(source),
+                },
+                Part::Line => out.push_str(&msg.line),
+                Part::Timestamp => out.push_str(&timeutil::rfc3339(msg.timestamp)),
+                Part::Seq => out.push_str(&msg.seq.to_string()),
+                Part::Lineno => out.push_str(&msg.lineno.to_string()),
+                Part::Tag => out.push_str(msg.tag.as_deref().unwrap_or_default()),
+                Part::Meta => {
+                    if let Some(meta) = &msg.meta {
+                        out.push_str(&meta.to_string());
+                    }
+                }
+                Part::Capture(name) => {
+                    if let Some(m) = captures.as_ref().and_then(|c| c.name(name)) {
+                        out.push_str(m.as_str());
+                    }
+                }
+            }
+        }
+        out
+    }
+}
+
+/// A terminal color for a source's name (`color` of a source).
+#[derive(Debug, Deserialize, Serialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum Color {
+    Black,
+    Red,
+    Green,
+    Yellow,
+    Blue,
+    Magenta,
+    Cyan,
+    White,
+    BrightBlack,
+    BrightRed,
+    BrightGreen,
+    BrightYellow,
+    BrightBlue,
+    BrightMagenta,
+    BrightCyan,
+    BrightWhite,
+}
+
+const ANSI_RESET: &str = "\x1b[0m";
+
+impl Color {
+    /// The SGR escape sequence that switches to this color.
+    fn ansi(self) -> &'static str {
+        match self {
+            Color::Black => "\x1b[30m",
+            Color::Red => "\x1b[31m",
+            Color::Green => "\x1b[32m",
+            Color::Yellow => "\x1b[33m",
+            Color::Blue => "\x1b[34m",
+            Color::Magenta => "\x1b[35m",
+            Color::Cyan => "\x1b[36m",
+            Color::White => "\x1b[37m",
+            Color::BrightBlack => "\x1b[90m",
+            Color::BrightRed => "\x1b[91m",
+            Color::BrightGreen => "\x1b[92m",
+            Color::BrightYellow => "\x1b[93m",
+            Color::BrightBlue => "\x1b[94m",
+            Color::BrightMagenta => "\x1b[95m",
+            Color::BrightCyan => "\x1b[96m",
+            Color::BrightWhite => "\x1b[97m",
+        }
+    }
+}
+
+/// How a source is shown in text output (`label` and `color`).
+#[derive(Debug, Clone, Default)]
+struct SourceStyle {
+    label: Option<String>,
+    color: Option<Color>,
+}
+
+/// Renders emitted lines in the configured format.
+#[derive(Debug, Clone)]
+pub struct Renderer {
+    format: OutputFormat,
+    template: Template,
+    styles: HashMap<String, SourceStyle>,
+    /// The main output isn't a terminal (`destination: syslog`).
+    no_color: bool,
+    icons: Option<LevelIcons>,
+}
+
+impl Renderer {
+    pub fn new(cfg: &OutputConfig) -> Result<Self, ConfigError> {
+        Ok(Self {
+            format: cfg.format,
+            template: cfg.text_template()?,
+            styles: HashMap::new(),
+            no_color: cfg.destination != Destination::Stdout,
+            icons: None,
+        })
+    }
+
+    /// Show sources by their `label` and in their `color` in text output.
+    /// Colors are left out when `NO_COLOR` is set or the output goes to
+    /// syslog, or to a pipe or file rather than a terminal.
+    pub fn with_sources(mut self, sources: &[SourceConfig]) -> Self {
+        let no_color = self.no_color
+            || !io::stdout().is_terminal()
+            || std::env::var_os("NO_COLOR").is_some_and(|v| !v.is_empty());
+        self.styles = sources
+            .iter()
+            .filter(|s| s.label.is_some() || s.color.is_some())
+            .map(|s| {
+                let style = SourceStyle {
+                    label: s.label.clone(),
+                    color: s.color.filter(|_| !no_color),
+                };
+                (s.name.clone(), style)
+            })
+            .collect();
+        self
+    }
+
+    /// Start text lines with the icon of their level (`output.level_icons`),
+    /// found with `level_pattern`. ASCII markers are used with `no_unicode`
+    /// or when the locale isn't UTF-8.
+    pub fn with_level_icons(mut self, cfg: &Config) -> Result<Self, ConfigError> {
+        if cfg.output.level_icons {
+            let ascii = cfg.output.no_unicode || !level::unicode_locale();
+            self.icons = Some(LevelIcons::compile(
+                cfg.level_pattern.as_deref(),
+                ascii,
+                cfg.regex_size_limit,
+            )?);
+        }
+        Ok(self)
+    }
+
+    /// Render a log line (without trailing newline) for files and other
+    /// non-terminal outputs: labels apply, colors do not. Context lines
+    /// get a `-` prefix in text and `"context": true` in JSON.
+    pub fn render(&self, msg: &LogLine, context: bool) -> String {
+        match self.format {
+            OutputFormat::Text => {
+                let (source, _) = self.style(msg);
+                let line = self.template.render_styled(msg, &source, None);
+                text_line(self.with_icon(msg, line), context)
+            }
+            OutputFormat::Json => render_json(msg, context),
+            OutputFormat::Csv => {
+                let (source, _) = self.style(msg);
+                render_csv(msg, &source)
+            }
+        }
+    }
+
+    /// Like `render`, with the source name in its color, for the main
+    /// output. `None` if the source has no color (or for JSON).
+    pub fn render_colored(&self, msg: &LogLine, context: bool) -> Option<String> {
+        if self.format != OutputFormat::Text {
+            return None;
+        }
+        let (source, color) = self.style(msg);
+        let line = self.template.render_styled(msg, &source, Some(color?));
+        Some(text_line(self.with_icon(msg, line), context))
+    }
+
+    /// `rendered` after the icon of `msg`'s level, if icons are on and the
+    /// level is known.
+    fn with_icon(&self, msg: &LogLine, rendered: String) -> String {
+        match self.icons.as_ref().and_then(|icons| icons.icon(&msg.line)) {
+            Some(icon) => format!("{icon} {rendered}"),
+            None => rendered,
+        }
+    }
+
+    /// The shown name and color of `msg`'s source. A command's stderr
+    /// lines show as `label:stderr`, in the source's color.
+    fn style(&self, msg: &LogLine) -> (String, Option<Color>) {
+        let style = self.styles.get(&msg.source);
+        let name = style
+            .and_then(|s| s.label.as_deref())
+            .unwrap_or(&msg.source);
+        let shown = match msg.fields.get("stream") {
+            Some(stream) if stream == "stderr" => format!("{name}:stderr"),
+            _ => name.to_string(),
+        };
+        (shown, style.and_then(|s| s.color))
+    }
+}
+
+/// Mark context lines with a `-` prefix.
+fn text_line(line: String, context: bool) -> String {
+    if context { format!("-{line}") } else { line }
+}
+
+/// Render a log line as a JSON record (without trailing newline).
+/// A source's end marker becomes a `source_closed` event.
+pub fn render_json(msg: &LogLine, context: bool) -> String {
+    if msg.closed {
+        let event = EventRecord {
+            v: SCHEMA_VERSION,
+            event: "source_closed",
+            source: &msg.source,
+            timestamp_ms: msg
+                .timestamp
+                .duration_since(UNIX_EPOCH)
+                .map(|d| d.as_millis())
+                .unwrap_or(0),
+        };
+        return serde_json::to_string(&event).expect("JSON event serialization failed");
+    }
+
+    let record = JsonRecord {
+        v: SCHEMA_VERSION,
+        source: &msg.source,
+        line: &msg.line,
+        timestamp_ms: msg
+            .timestamp
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_millis())
+            .unwrap_or(0),
+        seq: msg.seq,
+        lineno: msg.lineno,
+        tag: msg.tag.as_deref(),
+        meta: msg.meta.as_deref(),
+        fields: &msg.fields,
+        context,
+    };
+    // Serializing plain strings and integers cannot fail.
+    serde_json::to_string(&record).expect("JSON record serialization failed")
+}
+
+/// Render a log line as a CSV row (without trailing newline): the shown
+/// source name, the RFC 3339 time it was read, the line, and its parsed
+/// fields as a JSON object (empty if it has none). Every value is quoted.
+pub fn render_csv(msg: &LogLine, source: &str) -> String {
+    let fields = if msg.fields.is_empty() {
+        String::new()
+    } else {
+        serde_json::to_string(&msg.fields).expect("JSON fields serialization failed")
+    };
+    let timestamp = timeutil::rfc3339(msg.timestamp);
+
+    let mut row = csv::WriterBuilder::new()
+        .quote_style(csv::QuoteStyle::Always)
+        .terminator(csv::Terminator::Any(b'\n'))
+        .from_writer(Vec::new());
+    // Writing to memory cannot fail.
+    row.write_record([source, &timestamp, &msg.line, &fields])
+        .expect("CSV row serialization failed");
+    let mut row = row.into_inner().expect("CSV row serialization failed");
+    row.pop(); // the terminator
+    // Lines are valid UTF-8, and quoting only adds ASCII.
+    String::from_utf8(row).expect("CSV row is UTF-8")
+}
+
+/// Bytes of buffered records that trigger a flush on their own.
+const BUFFER_BYTES: usize = 8 * 1024;
+
+/// Buffered line writer (usually around a held `StdoutLock`).
+///
+/// Output is flushed every `flush_lines` lines, when `flush_interval` has
+/// elapsed since the last flush, and when the writer is dropped. Only the
+/// consumer thread writes, and each record (a line, or a whole multiline
+/// record) is handed to the writer in a single `write_all`, so records of
+/// different sources never interleave and a datagram writer such as syslog
+/// sends each one as one message.
+///
+/// Adding a line (`push_line`) never fails; only flushing does. A failed
+/// flush keeps the records the writer didn't take, so retrying it writes
+/// each record once.
+pub struct BufferedWriter<W: Write> {
+    writer: W,
+    /// Written before the first line (the CSV header).
+    header: Option<&'static str>,
+    /// Records not yet written, each ending in a newline.
+    buffer: Vec<u8>,
+    /// Where each record in `buffer` ends.
+    ends: Vec<usize>,
+    /// How many records the writer took before a write failed.
+    written: usize,
+    pending: usize,
+    last_flush: Instant,
+    flush_lines: usize,
+    flush_interval: Duration,
+}
+
+impl<W: Write> BufferedWriter<W> {
+    pub fn new(inner: W, cfg: &OutputConfig) -> Self {
+        Self {
+            writer: inner,
+            // Syslog gets no header, as each line is a separate message
+            header: (cfg.format == OutputFormat::Csv && cfg.destination == Destination::Stdout)
+                .then_some(CSV_HEADER),
+            buffer: Vec::new(),
+            ends: Vec::new(),
+            written: 0,
+            pending: 0,
+            last_flush: Instant::now(),
+            flush_lines: cfg.flush_lines.max(1),
+            flush_interval: Duration::from_millis(cfg.flush_interval_ms),
+        }
+    }
+
+    /// How long the consumer may wait for input before calling `tick`.
+    pub fn flush_interval(&self) -> Duration {
+        self.flush_interval
+    }
+
+    /// Write one line (a newline is appended) and flush if a threshold is hit.
+    pub fn write_line(&mut self, line: &str) -> io::Result<()> {
+        self.push_line(line);
+        self.flush_if_due()
+    }
+
+    /// Buffer one line (a newline is appended) without writing anything.
+    pub fn push_line(&mut self, line: &str) {
+        if let Some(header) = self.header.take() {
+            self.push_record(header);
+        }
+        self.push_record(line);
+        self.pending += 1;
+    }
+
+    fn push_record(&mut self, line: &str) {
+        self.buffer.extend_from_slice(line.as_bytes());
+        self.buffer.push(b'\n');
+        self.ends.push(self.buffer.len());
+    }
+
+    /// Flush if `flush_lines` lines or enough bytes are buffered, or the
+    /// flush interval has elapsed.
+    pub fn flush_if_due(&mut self) -> io::Result<()> {
+        if self.pending >= self.flush_lines || self.buffer.len() >= BUFFER_BYTES {
+            self.flush()
+        } else {
+            self.tick()
+        }
+    }
+
+    /// Flush if the flush interval has elapsed and something is pending.
+    pub fn tick(&mut self) -> io::Result<()> {
+        if self.pending > 0 && self.last_flush.elapsed() >= self.flush_interval {
+            self.flush()?;
+        }
+        Ok(())
+    }
+
+    /// Write every buffered record, each with one `write_all`. After a
+    /// failure, the record that failed and those after it stay buffered for
+    /// the next flush.
+    pub fn flush(&mut self) -> io::Result<()> {
+        while let Some(&end) = self.ends.get(self.written) {
+            let start = self.written.checked_sub(1).map_or(0, |i| self.ends[i]);
+            // The whole record in one call: on a held `StdoutLock` nothing
+            // else can write in between, however many lines it has
+            self.writer.write_all(&self.buffer[start..end])?;
+            self.written += 1;
+        }
+        self.writer.flush()?;
+        self.discard();
+        self.last_flush = Instant::now();
+        Ok(())
+    }
+
+    /// Forget the buffered records, e.g. once `on_error` gave up on them.
+    pub fn discard(&mut self) {
+        self.buffer.clear();
+        self.ends.clear();
+        self.written = 0;
+        self.pending = 0;
+    }
+}
+
+impl<W: Write> Drop for BufferedWriter<W> {
+    fn drop(&mut self) {
+        // Final flush so no output is lost on shutdown
+        let _ = self.flush();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::Value;
+
+    #[test]
+    fn json_records_carry_the_schema_version() {
+        let line: Value =
+            serde_json::from_str(&render_json(&LogLine::new("app", "hi"), false)).unwrap();
+        assert_eq!(line["v"], SCHEMA_VERSION);
+
+        let closed = LogLine {
+            closed: true,
+            ..LogLine::new("app", "")
+        };
+        let event: Value = serde_json::from_str(&render_json(&closed, false)).unwrap();
+        assert_eq!(event["event"], "source_closed");
+        assert_eq!(event["v"], SCHEMA_VERSION);
+
+        // Consumers check this number; changing it is a breaking change
+        assert_eq!(SCHEMA_VERSION, 1);
+    }
+
+    /// Records the size of every `write` call.
+    #[derive(Default)]
+    struct WriteSizes(Vec<usize>);
+
+    impl Write for WriteSizes {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            self.0.push(buf.len());
+            Ok(buf.len())
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn a_multiline_record_is_written_at_once() {
+        let cfg = OutputConfig {
+            flush_lines: 1,
+            ..OutputConfig::default()
+        };
+        let mut out = BufferedWriter::new(WriteSizes::default(), &cfg);
+
+        let trace = "panic: boom\n  at main.rs:1\n  at lib.rs:2";
+        out.write_line(trace).unwrap();
+        // Larger than `BUFFER_BYTES`, and still written in one piece
+        let big = vec!["  at frame"; 2000].join("\n");
+        out.write_line(&big).unwrap();
+
+        assert_eq!(out.writer.0, [trace.len() + 1, big.len() + 1]);
+    }
+
+    #[test]
+    fn colors_need_a_terminal() {
+        let source: SourceConfig = serde_yaml::from_str(
+            "name: app
+type: file
+path: app.log
+color: red
+",
+        )
+        .unwrap();
+        let renderer = Renderer::new(&OutputConfig::default())
+            .unwrap()
+            .with_sources(&[source]);
+        let colored = renderer.render_colored(&LogLine::new("app", "hi"), false);
+        // Test output is captured, so this is usually a pipe
+        if !io::stdout().is_terminal() {
+            assert_eq!(colored, None);
+        }
+        assert_eq!(
+            renderer.render(&LogLine::new("app", "hi"), false),
+            "[app] hi"
+        );
+    }
+}
@@ -0,0 +1,855 @@
+// src/pipeline.rs
+use crate::config::{Config, ConfigError};
+use crate::context::Context;
+use crate::dedup::Deduper;
+use crate::dir_output::DirWriter;
+use crate::filters::FilterDecision;
+use crate::health::HealthServer;
+use crate::logline::LogLine;
+use crate::output::{BufferedWriter, CSV_HEADER, OutputFormat, Renderer, render_json};
+use crate::raw_output::RawWriter;
+use crate::reader::Readers;
+use crate::reorder::Reorder;
+use crate::replay::Replayer;
+use crate::rollup::Rollup;
+use crate::sample::Sampler;
+use crate::sink_policy::SinkGuard;
+use crate::stats::{Reporter, Stats};
+use crate::stream::Classifier;
+use crate::syslog::{self, Severity};
+use crate::timeutil::TimeRange;
+use crate::webhook::Webhook;
+#[cfg(feature = "websocket")]
+use crate::websocket::WebSocketServer;
+use std::collections::VecDeque;
+use std::io::{self, Write};
+use std::sync::{
+    Arc, Mutex,
+    atomic::{AtomicBool, Ordering},
+    mpsc::{self, Receiver, RecvTimeoutError, TryRecvError},
+};
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// Longest the consumer waits for a line before checking on the readers.
+const READER_POLL: Duration = Duration::from_millis(25);
+
+/// Most lines classified together when `workers` is above 1.
+pub(crate) const BATCH_LINES: usize = 1024;
+
+/// Fewest lines worth handing to a worker thread of their own.
+const MIN_CHUNK: usize = 64;
+
+/// Flags the caller can flip while a run is in progress.
+#[derive(Debug, Clone, Default)]
+pub struct Controls {
+    /// Stop reading and return once set.
+    pub shutdown: Arc<AtomicBool>,
+
+    /// While set, lines are still classified and counted but not written
+    /// to any output ("count-through").
+    pub count_through: Arc<AtomicBool>,
+
+    /// A new config to switch to. The run takes it and applies its sources
+    /// and filters; other settings keep their values.
+    pub reload: Arc<Mutex<Option<Config>>>,
+}
+
+/// Read all configured sources, filter their lines, and write the emitted
+/// lines to `writer` until every source ends or `shutdown` is set.
+///
+/// Returns the final statistics. The caller decides how to report them.
+pub fn run_with_config<W: Write>(
+    cfg: &Config,
+    writer: W,
+    shutdown: Arc<AtomicBool>,
+) -> Result<Arc<Stats>, ConfigError> {
+    let controls = Controls {
+        shutdown,
+        ..Controls::default()
+    };
+    run_with_controls(cfg, writer, controls)
+}
+
+/// Like `run_with_config`, with every runtime control exposed.
+pub fn run_with_controls<W: Write>(
+    cfg: &Config,
+    writer: W,
+    controls: Controls,
+) -> Result<Arc<Stats>, ConfigError> {
+    let Controls {
+        shutdown,
+        count_through,
+        reload,
+    } = controls;
+
+    // Stats (atomic counters)
+    let stats = Arc::new(Stats::new());
+
+    // Build filters (can fail if regex is invalid) and open every output
+    // before starting any readers
+    let mut consumer = Consumer::new(cfg, writer, stats.clone(), shutdown.clone())?;
+
+    // Set up channels; readers wait when the consumer falls behind
+    let (tx, rx) = mpsc::sync_channel::<LogLine>(cfg.queue_capacity);
+
+    // Readiness probe; answers 503 until every reader has started
+    let _health = match &cfg.health {
+        Some(h) => {
+            let sources = cfg.sources.iter().map(|s| s.name.clone()).collect();
+            let server = HealthServer::start(h, sources, stats.clone(), shutdown.clone());
+            Some(server.map_err(|e| ConfigError::OutputBind {
+                addr: h.bind.clone(),
+                source: e,
+            })?)
+        }
+        None => None,
+    };
+
+    // Spawn reader threads for all sources. The loop below ends once every
+    // reader has finished, e.g. when all sources are non-followed files.
+    let mut readers = Readers::spawn(cfg, tx, stats.clone());
+
+    // Periodic stats on stderr; stopped when dropped at the end of the run
+    let reporter = cfg
+        .stats_interval_secs
+        .map(|secs| Reporter::spawn(stats.clone(), Duration::from_secs(secs)));
+
+    // Stop once `duration_secs` is up; dropping the sender cancels the timer
+    let timer = cfg.duration_secs.map(|secs| {
+        let (cancel, cancelled) = mpsc::channel::<()>();
+        let shutdown = shutdown.clone();
+        thread::spawn(move || {
+            if cancelled.recv_timeout(Duration::from_secs(secs)) == Err(RecvTimeoutError::Timeout) {
+                eprintln!("[logscout] Duration of {secs}s reached, terminating...");
+                shutdown.store(true, Ordering::SeqCst);
+            }
+        });
+        cancel
+    });
+
+    let mut reorder = cfg
+        .reorder_window_ms
+        .map(|ms| Reorder::new(Duration::from_millis(ms), cfg.queue_capacity));
+
+    // Lines taken off the queue and classified, but not yet written
+    let mut pending: VecDeque<(LogLine, Option<FilterDecision>)> = VecDeque::new();
+
+    // Wake up at least this often to flush output and notice finished readers
+    let poll = consumer.flush_interval().min(READER_POLL);
+
+    // A source that couldn't be opened, with `on_source_error: fail`
+    let mut failure = None;
+
+    // Set once a write error has been reported, so the final flush doesn't
+    // report it again
+    let mut write_failed = false;
+
+    // Consume data
+    loop {
+        if let Some(f) = readers.failure() {
+            eprintln!("[logscout] source `{}` failed, terminating...", f.source);
+            shutdown.store(true, Ordering::SeqCst);
+            failure = Some(f);
+            break;
+        }
+
+        // Stop right after the last allowed line, leaving the rest unread
+        if let Some(limit) = cfg.max_lines
+            && consumer.seq >= limit
+            && pending.is_empty()
+        {
+            eprintln!("[logscout] Line limit of {limit} reached, terminating...");
+            shutdown.store(true, Ordering::SeqCst);
+            break;
+        }
+
+        // On shutdown, lines still held for reordering are flushed first
+        let stopping = shutdown.load(Ordering::SeqCst);
+        if stopping && pending.is_empty() && reorder.as_ref().is_none_or(Reorder::is_empty) {
+            break;
+        }
+
+        // Switch to a reloaded config if one is waiting
+        if let Some(new_cfg) = reload.lock().unwrap().take() {
+            match consumer.reload(&new_cfg) {
+                Ok(()) => {
+                    let (started, stopped) = readers.reconcile(&new_cfg);
+                    eprintln!(
+                        "[logscout] config reloaded: {started} source(s) started, {stopped} stopped"
+                    );
+                    let kept = cfg.restart_only_changes(&new_cfg);
+                    if !kept.is_empty() {
+                        eprintln!(
+                            "[logscout] changes to {} take effect after a restart",
+                            kept.join(", ")
+                        );
+                    }
+                }
+                Err(e) => {
+                    eprintln!("[logscout] reload failed, keeping the current config: {e}");
+                }
+            }
+        }
+
+        // Take the next classified line, classifying a new batch if needed
+        let (msg, decision) = match pending.pop_front() {
+            Some(next) => next,
+            None => {
+                // Checked before waiting: if every reader was already done, all
+                // of their lines are queued, so a timeout means nothing is left
+                let finished = readers.finished();
+
+                // Wake up periodically so buffered output is flushed on time
+                let first = match next_line(&rx, reorder.as_mut(), poll, finished, stopping) {
+                    Ok(msg) => msg,
+                    Err(RecvTimeoutError::Timeout) => {
+                        if let Err(e) = consumer.tick() {
+                            report_write_error(&e, &shutdown);
+                            write_failed = true;
+                            break;
+                        }
+                        if finished {
+                            break;
+                        }
+                        continue;
+                    }
+                    Err(RecvTimeoutError::Disconnected) => break,
+                };
+
+                // With several workers, lines already waiting are classified
+                // together (never more than `max_lines` allows)
+                let mut batch = vec![first];
+                let room = cfg.max_lines.map_or(BATCH_LINES, |limit| {
+                    (limit - consumer.seq).min(BATCH_LINES as u64) as usize
+                });
+                while cfg.workers > 1
+                    && batch.len() < room
+                    && let Ok(msg) =
+                        next_line(&rx, reorder.as_mut(), Duration::ZERO, finished, stopping)
+                {
+                    batch.push(msg);
+                }
+
+                pending.extend(consumer.classify(batch));
+                match pending.pop_front() {
+                    Some(next) => next,
+                    None => continue,
+                }
+            }
+        };
+
+        match consumer.process(msg, decision, count_through.load(Ordering::Relaxed)) {
+            Ok(true) => {}
+            Ok(false) => break,
+            Err(e) => {
+                report_write_error(&e, &shutdown);
+                write_failed = true;
+                break;
+            }
+        }
+    }
+
+    // Stop the readers, unblocking those waiting for room in the queue
+    readers.stop_all();
+    // A source may have failed just as the last of the others finished
+    let failure = failure.or_else(|| readers.failure());
+    drop(rx);
+
+    // A reader stuck in a read (e.g. a command that prints nothing) is not
+    // waited for past the timeout
+    let timeout = cfg.shutdown_timeout_secs;
+    for source in readers.join(Duration::from_secs(timeout)) {
+        eprintln!(
+            "[logscout] source `{source}`: reader did not stop within {timeout}s, leaving it"
+        );
+    }
+
+    // Final flush (and release the writer) before the caller prints anything
+    drop(timer);
+    drop(reporter);
+    if let Err(e) = consumer.finish()
+        && !write_failed
+    {
+        report_write_error(&e, &shutdown);
+    }
+
+    match failure {
+        Some(f) => Err(ConfigError::SourceFailed {
+            name: f.source,
+            message: f.message,
+        }),
+        None => Ok(stats),
+    }
+}
+
+/// What happens to a line once it is off the queue: the checks before the
+/// filters, classification, dedup, sampling, context, replay, and writing
+/// it to every output. `run_with_controls` feeds it the lines of the
+/// readers, `StreamFilter` lines already in memory.
+pub(crate) struct Consumer<W: Write> {
+    classifier: Classifier,
+    renderer: Renderer,
+    sampler: Option<Sampler>,
+    deduper: Option<Deduper>,
+    context: Option<Context>,
+    replayer: Option<Replayer>,
+    time_range: Option<TimeRange>,
+    sinks: Sinks<W>,
+    stats: Arc<Stats>,
+    shutdown: Arc<AtomicBool>,
+    quiet: bool,
+    skip_empty_lines: bool,
+    min_severity: Option<Severity>,
+    summary_samples: usize,
+    workers: usize,
+    max_output_bytes: Option<u64>,
+    /// Sequence number of the last classified line.
+    pub seq: u64,
+    /// Bytes written to the output so far (for `max_output_bytes`).
+    emitted_bytes: u64,
+}
+
+impl<W: Write> Consumer<W> {
+    pub fn new(
+        cfg: &Config,
+        writer: W,
+        stats: Arc<Stats>,
+        shutdown: Arc<AtomicBool>,
+    ) -> Result<Self, ConfigError> {
+        let classifier = Classifier::from_config(cfg)?;
+        let renderer = Renderer::new(&cfg.output)?
+            .with_sources(&cfg.sources)
+            .with_level_icons(cfg)?;
+        let time_range = cfg.time_range()?;
+        let sinks = Sinks::open(cfg, writer, &shutdown)?;
+        Ok(Self {
+            classifier,
+            renderer,
+            sampler: cfg.sample.as_ref().map(Sampler::new),
+            deduper: cfg
+                .dedup_window_secs
+                .map(|secs| Deduper::new(Duration::from_secs(secs), cfg.dedup_capacity)),
+            context: cfg.context.as_ref().map(Context::new),
+            replayer: cfg.replay.then(|| Replayer::new(cfg.replay_speed)),
+            time_range,
+            sinks,
+            stats,
+            shutdown,
+            quiet: cfg.quiet,
+            skip_empty_lines: cfg.skip_empty_lines,
+            min_severity: cfg.min_severity,
+            summary_samples: cfg.summary_samples,
+            workers: cfg.workers,
+            max_output_bytes: cfg.max_output_bytes,
+            seq: 0,
+            emitted_bytes: 0,
+        })
+    }
+
+    /// Switch to the filters, redaction, tags, rendering, dedup, sampling,
+    /// context and pre-filter checks of `cfg`, keeping the pattern and tag
+    /// counts so far. Outputs and run limits keep their settings (see
+    /// `Config::restart_only_changes`). Nothing changes if `cfg` fails.
+    pub fn reload(&mut self, cfg: &Config) -> Result<(), ConfigError> {
+        let classifier = Classifier::from_config(cfg)?;
+        let renderer = Renderer::new(&cfg.output)?
+            .with_sources(&cfg.sources)
+            .with_level_icons(cfg)?;
+        let time_range = cfg.time_range()?;
+
+        classifier.carry_counts_from(&self.classifier);
+        self.classifier = classifier;
+        self.renderer = renderer;
+        self.time_range = time_range;
+        self.sampler = cfg.sample.as_ref().map(Sampler::new);
+        self.deduper = cfg
+            .dedup_window_secs
+            .map(|secs| Deduper::new(Duration::from_secs(secs), cfg.dedup_capacity));
+        self.context = cfg.context.as_ref().map(Context::new);
+        self.quiet = cfg.quiet;
+        self.skip_empty_lines = cfg.skip_empty_lines;
+        self.min_severity = cfg.min_severity;
+        self.summary_samples = cfg.summary_samples;
+        Ok(())
+    }
+
+    /// How long to wait for input at most before calling `tick`.
+    pub fn flush_interval(&self) -> Duration {
+        self.sinks.out.sink.flush_interval()
+    }
+
+    /// Periodic flush of every output.
+    pub fn tick(&mut self) -> io::Result<()> {
+        self.sinks.tick(&self.shutdown)
+    }
+
+    /// Count and classify `batch` in order, over up to `workers` threads.
+    ///
+    /// Empty lines, lines below `min_severity` and lines outside
+    /// `since`/`until` are counted, then left out. Source end markers are
+    /// kept but not classified (their decision is `None`).
+    pub fn classify(&mut self, mut batch: Vec<LogLine>) -> Vec<(LogLine, Option<FilterDecision>)> {
+        let stats = &self.stats;
+        if self.skip_empty_lines || self.min_severity.is_some() || self.time_range.is_some() {
+            batch.retain(|m| {
+                let skipped: fn(&Stats) = if m.closed {
+                    return true;
+                } else if self.skip_empty_lines && m.line.trim().is_empty() {
+                    Stats::inc_skipped_empty
+                } else if self
+                    .min_severity
+                    .is_some_and(|min| syslog::severity(&m.line).is_some_and(|s| s > min))
+                {
+                    Stats::inc_below_severity
+                } else if self
+                    .time_range
+                    .as_ref()
+                    .is_some_and(|range| !range.contains(m.event_time))
+                {
+                    Stats::inc_out_of_range
+                } else {
+                    return true;
+                };
+                stats.inc_total();
+                stats.add_bytes(m.raw.as_ref().map_or(m.line.len(), Vec::len) as u64);
+                skipped(stats);
+                false
+            });
+        }
+
+        let sizes: Vec<u64> = batch
+            .iter()
+            .map(|m| m.raw.as_ref().map_or(m.line.len(), Vec::len) as u64)
+            .collect();
+        let classifier = &self.classifier;
+        let decisions = classify_batch(&mut batch, self.workers, &|msg: &mut LogLine| {
+            (!msg.closed).then(|| classifier.classify(msg))
+        });
+
+        let mut classified = Vec::with_capacity(batch.len());
+        for ((mut msg, bytes), decision) in batch.into_iter().zip(sizes).zip(decisions) {
+            if let Some(decision) = decision {
+                self.seq += 1;
+                msg.seq = self.seq;
+                stats.inc_total();
+                stats.add_bytes(bytes);
+                stats.count_source(&msg.source, bytes, msg.timestamp, decision);
+            }
+            classified.push((msg, decision));
+        }
+        classified
+    }
+
+    /// Count a classified line and write it wherever it goes. With
+    /// `quiet` or `count_through`, nothing is written to the main output;
+    /// dedup, sampling, the stats and the other outputs work as usual.
+    ///
+    /// Returns `false` once `max_output_bytes` is reached, and an error if
+    /// writing failed and the run should stop.
+    pub fn process(
+        &mut self,
+        mut msg: LogLine,
+        decision: Option<FilterDecision>,
+        count_through: bool,
+    ) -> io::Result<bool> {
+        let shutdown = &*self.shutdown;
+        let stats = &self.stats;
+        // Quiet / count-through: everything as usual, but the main output
+        let main = !self.quiet && !count_through;
+
+        // A source ended: write its marker, which is not a real line
+        let Some(decision) = decision else {
+            self.emitted_bytes += self
+                .sinks
+                .emit(&self.renderer, &msg, false, main, shutdown)?;
+            return Ok(true);
+        };
+
+        let emitting = matches!(decision, FilterDecision::Included | FilterDecision::Passed);
+
+        // Dedup and sampling only thin out lines that would have been emitted
+        let duplicate = emitting
+            && self
+                .deduper
+                .as_mut()
+                .is_some_and(|d| d.is_repeat(&msg.line, Instant::now()));
+        let sampled_out =
+            emitting && !duplicate && self.sampler.as_mut().is_some_and(|s| !s.keep(&msg.source));
+
+        // Unmatched lines may still be shown as context
+        let maybe_context =
+            self.context.is_some() && decision == FilterDecision::DroppedNoIncludeMatch;
+
+        // Redact after filtering, before the line is written anywhere
+        if self.classifier.redacts_output()
+            && ((emitting && !duplicate && !sampled_out)
+                || maybe_context
+                || self.sinks.raw.is_some()
+                || self.sinks.rollup.is_some())
+        {
+            self.classifier.redact_output(&mut msg);
+        }
+
+        // The raw sink sees every line, whatever the decision
+        self.sinks.write_raw(&msg, decision, shutdown)?;
+
+        // The rollup also counts every line, so errors are seen even if hidden
+        self.sinks.observe_rollup(&msg, shutdown)?;
+
+        match decision {
+            FilterDecision::Excluded => {
+                stats.inc_excluded();
+                // Silently ignore excluded lines
+            }
+
+            FilterDecision::Included | FilterDecision::Passed if duplicate => {
+                stats.inc_duplicates();
+            }
+
+            FilterDecision::Included | FilterDecision::Passed if sampled_out => {
+                stats.inc_sampled_out();
+            }
+
+            FilterDecision::Included | FilterDecision::Passed => {
+                stats.inc_included();
+                if self.summary_samples > 0 {
+                    stats.sample_line(&msg.source, &msg.line, self.summary_samples);
+                }
+
+                // Wait out the original gap, still flushing on time
+                let mut wait = self
+                    .replayer
+                    .as_mut()
+                    .map_or(Duration::ZERO, |r| r.delay(&msg));
+                while !wait.is_zero() && !shutdown.load(Ordering::SeqCst) {
+                    let step = wait.min(self.sinks.out.sink.flush_interval());
+                    thread::sleep(step);
+                    wait -= step;
+                    self.sinks.tick(shutdown)?;
+                }
+
+                let before = match self.context.as_mut() {
+                    Some(c) => c.on_emit(&msg.source),
+                    None => Vec::new(),
+                };
+                let lines = before.iter().map(|m| (m, true)).chain([(&msg, false)]);
+                self.emitted_bytes += self.sinks.emit_all(&self.renderer, lines, main, shutdown)?;
+            }
+
+            FilterDecision::DroppedNoIncludeMatch => {
+                stats.inc_dropped();
+                if let Some(after) = self.context.as_mut().and_then(|c| c.on_unmatched(msg)) {
+                    self.emitted_bytes +=
+                        self.sinks
+                            .emit(&self.renderer, &after, true, main, shutdown)?;
+                }
+            }
+        }
+
+        if let Some(limit) = self.max_output_bytes
+            && self.emitted_bytes >= limit
+        {
+            let _ = self.sinks.out.sink.flush();
+            eprintln!("[logscout] Output limit of {limit} bytes reached, terminating...");
+            shutdown.store(true, Ordering::SeqCst);
+            return Ok(false);
+        }
+        Ok(true)
+    }
+
+    /// Hand the final pattern and tag counts to the stats.
+    pub fn record_counts(&self) {
+        self.classifier.record_counts(&self.stats);
+    }
+
+    /// Record the final counts, then flush and close every output. Each
+    /// flush runs under its output's `on_error` policy; an error is
+    /// returned only when that policy stops the run.
+    pub fn finish(mut self) -> io::Result<()> {
+        self.record_counts();
+        self.sinks.flush_all(&self.shutdown)
+    }
+}
+
+/// An output together with the policy applied when writing to it fails.
+struct Guarded<S> {
+    sink: S,
+    guard: SinkGuard,
+}
+
+impl<W: Write> Guarded<BufferedWriter<W>> {
+    /// Run `flush` under the output's policy. Lines are buffered before
+    /// this, so a retry only flushes again and never writes a line twice;
+    /// lines the policy gives up on are discarded rather than written later.
+    fn flush_with(
+        &mut self,
+        shutdown: &AtomicBool,
+        flush: fn(&mut BufferedWriter<W>) -> io::Result<()>,
+    ) -> io::Result<bool> {
+        let Guarded { sink, guard } = self;
+        let written = guard.run(shutdown, || flush(sink))?;
+        if !written {
+            sink.discard();
+        }
+        Ok(written)
+    }
+}
+
+/// Every place emitted (or raw) lines are written to.
+struct Sinks<W: Write> {
+    out: Guarded<BufferedWriter<W>>,
+    dir: Option<Guarded<DirWriter>>,
+    raw: Option<Guarded<RawWriter>>,
+    rollup: Option<Guarded<Rollup>>,
+    webhook: Option<Webhook>,
+    #[cfg(feature = "websocket")]
+    ws: Option<WebSocketServer>,
+}
+
+impl<W: Write> Sinks<W> {
+    fn open(cfg: &Config, writer: W, shutdown: &Arc<AtomicBool>) -> Result<Self, ConfigError> {
+        let raw = match &cfg.raw_output {
+            Some(r) => Some(Guarded {
+                sink: RawWriter::open(r).map_err(|e| ConfigError::OutputIo {
+                    path: r.path.display().to_string(),
+                    source: e,
+                })?,
+                guard: SinkGuard::new("raw_output", r.on_error),
+            }),
+            None => None,
+        };
+
+        let rollup = match &cfg.rollup {
+            Some(r) => Some(Guarded {
+                sink: Rollup::open(r, cfg.regex_size_limit)?,
+                guard: SinkGuard::new("rollup", r.on_error),
+            }),
+            None => None,
+        };
+
+        let webhook = match &cfg.output.webhook {
+            Some(w) => Some(Webhook::start(w, shutdown.clone())?),
+            None => None,
+        };
+
+        #[cfg(feature = "websocket")]
+        let ws = match &cfg.output.websocket {
+            Some(w) => Some(WebSocketServer::start(w, shutdown.clone()).map_err(|e| {
+                ConfigError::OutputBind {
+                    addr: w.bind.clone(),
+                    source: e,
+                }
+            })?),
+            None => None,
+        };
+
+        // Each archive file is a CSV document of its own
+        let csv_header = (cfg.output.format == OutputFormat::Csv).then_some(CSV_HEADER);
+        Ok(Self {
+            out: Guarded {
+                sink: BufferedWriter::new(writer, &cfg.output),
+                guard: SinkGuard::new("output", cfg.output.on_error),
+            },
+            dir: cfg.output.dir.clone().map(|d| Guarded {
+                guard: SinkGuard::new("output.dir", d.on_error),
+                sink: DirWriter::new(d, csv_header),
+            }),
+            raw,
+            rollup,
+            webhook,
+            #[cfg(feature = "websocket")]
+            ws,
+        })
+    }
+
+    /// Write an emitted line everywhere (to the main output only if `main`)
+    /// and return the number of bytes written to the main output (including
+    /// the newline). Color escapes are not counted, so `max_output_bytes`
+    /// stops at the same line with or without a terminal.
+    ///
+    /// Failed writes are handled by each output's `on_error` policy; an
+    /// error is returned only when the run should stop.
+    fn emit(
+        &mut self,
+        renderer: &Renderer,
+        msg: &LogLine,
+        context: bool,
+        main: bool,
+        shutdown: &AtomicBool,
+    ) -> io::Result<u64> {
+        let rendered = renderer.render(msg, context);
+        let written = main && {
+            let colored = renderer.render_colored(msg, context);
+            self.out
+                .sink
+                .push_line(colored.as_deref().unwrap_or(&rendered));
+            self.out
+                .flush_with(shutdown, BufferedWriter::flush_if_due)?
+        };
+        if let Some(dir) = self.dir.as_mut() {
+            dir.guard
+                .run(shutdown, || dir.sink.write_line(msg, &rendered))?;
+        }
+
+        if let Some(hook) = self.webhook.as_mut() {
+            hook.send(render_json(msg, context));
+        }
+
+        #[cfg(feature = "websocket")]
+        if let Some(ws) = &self.ws {
+            // Browsers always get JSON, whatever the terminal format is
+            ws.broadcast(&render_json(msg, context));
+        }
+
+        Ok(if written {
+            rendered.len() as u64 + 1
+        } else {
+            0
+        })
+    }
+
+    /// `emit` each `(line, is_context)` in order, returning the total bytes.
+    fn emit_all<'a>(
+        &mut self,
+        renderer: &Renderer,
+        lines: impl Iterator<Item = (&'a LogLine, bool)>,
+        main: bool,
+        shutdown: &AtomicBool,
+    ) -> io::Result<u64> {
+        let mut total = 0;
+        for (msg, context) in lines {
+            total += self.emit(renderer, msg, context, main, shutdown)?;
+        }
+        Ok(total)
+    }
+
+    /// Flush every output, and write the summary of the partial rollup
+    /// window.
+    fn flush_all(&mut self, shutdown: &AtomicBool) -> io::Result<()> {
+        self.out.flush_with(shutdown, BufferedWriter::flush)?;
+        if let Some(dir) = self.dir.as_mut() {
+            dir.guard.run(shutdown, || dir.sink.flush())?;
+        }
+        if let Some(raw) = self.raw.as_mut() {
+            raw.guard.run(shutdown, || raw.sink.flush())?;
+        }
+        if let Some(rollup) = self.rollup.as_mut() {
+            rollup.guard.run(shutdown, || rollup.sink.roll())?;
+        }
+        Ok(())
+    }
+
+    fn write_raw(
+        &mut self,
+        msg: &LogLine,
+        decision: FilterDecision,
+        shutdown: &AtomicBool,
+    ) -> io::Result<()> {
+        if let Some(raw) = self.raw.as_mut() {
+            raw.guard
+                .run(shutdown, || raw.sink.write_line(msg, decision))?;
+        }
+        Ok(())
+    }
+
+    /// Count a line towards the rollup and write a summary if one is due.
+    fn observe_rollup(&mut self, msg: &LogLine, shutdown: &AtomicBool) -> io::Result<()> {
+        if let Some(rollup) = self.rollup.as_mut() {
+            rollup.sink.observe(msg);
+            rollup.guard.run(shutdown, || rollup.sink.roll_if_due())?;
+        }
+        Ok(())
+    }
+
+    /// Periodic flush of every active output.
+    fn tick(&mut self, shutdown: &AtomicBool) -> io::Result<()> {
+        self.out.flush_with(shutdown, BufferedWriter::tick)?;
+        if let Some(dir) = self.dir.as_mut() {
+            dir.guard.run(shutdown, || dir.sink.flush())?;
+        }
+        if let Some(raw) = self.raw.as_mut() {
+            raw.guard.run(shutdown, || raw.sink.flush())?;
+        }
+        if let Some(rollup) = self.rollup.as_mut() {
+            rollup.guard.run(shutdown, || rollup.sink.roll_if_due())?;
+        }
+        Ok(())
+    }
+}
+
+/// Classify a batch of lines in order, spreading the work over up to
+/// `workers` threads when the batch is big enough to be worth it.
+fn classify_batch<F>(
+    batch: &mut [LogLine],
+    workers: usize,
+    classify: &F,
+) -> Vec<Option<FilterDecision>>
+where
+    F: Fn(&mut LogLine) -> Option<FilterDecision> + Sync,
+{
+    let chunk = batch.len().div_ceil(workers).max(MIN_CHUNK);
+    if batch.len() <= chunk {
+        return batch.iter_mut().map(classify).collect();
+    }
+    thread::scope(|s| {
+        let handles: Vec<_> = batch
+            .chunks_mut(chunk)
+            .map(|part| s.spawn(move || part.iter_mut().map(classify).collect::<Vec<_>>()))
+            .collect();
+        handles
+            .into_iter()
+            .flat_map(|h| h.join().expect("classifier thread panicked"))
+            .collect()
+    })
+}
+
+/// The next line to process: straight off the queue, or through the
+/// reorder buffer when there is one.
+///
+/// Buffered lines are released as they come due; once the readers are done
+/// and the queue is empty, or when `stopping`, the rest are flushed in order.
+fn next_line(
+    rx: &Receiver<LogLine>,
+    reorder: Option<&mut Reorder>,
+    poll: Duration,
+    finished: bool,
+    stopping: bool,
+) -> Result<LogLine, RecvTimeoutError> {
+    let Some(reorder) = reorder else {
+        return rx.recv_timeout(poll);
+    };
+    if stopping {
+        return reorder.pop(None).ok_or(RecvTimeoutError::Timeout);
+    }
+    loop {
+        if let Some(msg) = reorder.pop(Some(Instant::now())) {
+            return Ok(msg);
+        }
+        // Once the readers are done, an empty queue means nothing is left
+        let received = if finished {
+            rx.try_recv().map_err(|e| match e {
+                TryRecvError::Empty => RecvTimeoutError::Timeout,
+                TryRecvError::Disconnected => RecvTimeoutError::Disconnected,
+            })
+        } else {
+            rx.recv_timeout(poll)
+        };
+        match received {
+            Ok(msg) => reorder.push(msg, Instant::now()),
+            Err(e) => {
+                let now = (!finished && e == RecvTimeoutError::Timeout).then(Instant::now);
+                return reorder.pop(now).ok_or(e);
+            }
+        }
+    }
+}
+
+/// Report a failed output write and stop the readers.
+fn report_write_error(err: &io::Error, shutdown: &AtomicBool) {
+    // A closed pipe (e.g. `| head`) is a normal way to stop; stay quiet
+    if err.kind() != io::ErrorKind::BrokenPipe {
+        eprintln!("[logscout] error writing output: {err}");
+    }
+    shutdown.store(true, Ordering::SeqCst);
+}
@@ -0,0 +1,99 @@
+// src/raw_output.rs
+use crate::filters::FilterDecision;
+use crate::logline::LogLine;
+use crate::sink_policy::OnError;
+use serde::{Deserialize, Serialize};
+use std::fs::{File, OpenOptions};
+use std::io::{self, BufWriter, Write};
+use std::path::PathBuf;
+
+#[derive(Debug, Deserialize, Serialize, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct RawOutputConfig {
+    /// File that receives every line read, before filtering.
+    pub path: PathBuf,
+
+    /// Prefix each line with the filter decision, e.g. `[excluded]`.
+    #[serde(default)]
+    pub annotate_decision: bool,
+
+    /// What to do when writing the raw file fails.
+    #[serde(default)]
+    pub on_error: OnError,
+}
+
+/// Tee sink that captures all lines regardless of the filter decision.
+pub struct RawWriter {
+    writer: BufWriter<File>,
+    annotate: bool,
+}
+
+impl RawWriter {
+    pub fn open(cfg: &RawOutputConfig) -> io::Result<Self> {
+        let file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&cfg.path)?;
+        Ok(Self {
+            writer: BufWriter::new(file),
+            annotate: cfg.annotate_decision,
+        })
+    }
+
+    pub fn write_line(&mut self, msg: &LogLine, decision: FilterDecision) -> io::Result<()> {
+        if self.annotate {
+            write!(self.writer, "[{}] ", decision.label())?;
+        }
+        writeln!(self.writer, "[{}] {}", msg.source, msg.line)
+    }
+
+    pub fn flush(&mut self) -> io::Result<()> {
+        self.writer.flush()
+    }
+}
+
+impl Drop for RawWriter {
+    fn drop(&mut self) {
+        let _ = self.flush();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    #[test]
+    fn lines_are_annotated_with_the_decision() {
+        let path = std::env::temp_dir().join(format!("logscout-raw-{}.log", std::process::id()));
+        let _ = fs::remove_file(&path);
+        let cfg = RawOutputConfig {
+            path: path.clone(),
+            annotate_decision: true,
+            on_error: OnError::Stop,
+        };
+
+        let mut raw = RawWriter::open(&cfg).unwrap();
+        raw.write_line(&LogLine::new("app", "ERROR boom"), FilterDecision::Included)
+            .unwrap();
+        raw.write_line(
+            &LogLine::new("app", "healthcheck ok"),
+            FilterDecision::Excluded,
+        )
+        .unwrap();
+        raw.write_line(
+            &LogLine::new("db", "all good"),
+            FilterDecision::DroppedNoIncludeMatch,
+        )
+        .unwrap();
+        raw.flush().unwrap();
+
+        assert_eq!(
+            fs::read_to_string(&path).unwrap(),
+            "[included] [app] ERROR boom\n\
+             [excluded] [app] healthcheck ok\n\
+             [dropped] [db] all good\n"
+        );
+        let _ = fs::remove_file(&path);
+    }
+}
@@ -1,121 +1,942 @@
 // src/reader.rs
-use crate::config::{SourceConfig, SourceKind};
-use crate::logline::LogLine;
+use crate::ansi;
+use crate::config::{Compression, Config, OnSourceError, SourceConfig, SourceKind, StartAt};
+use crate::fields::{self, ParseFormat};
+use crate::filters;
+use crate::follow::{self, DirWaker, FollowReader};
+#[cfg(all(feature = "journald", target_os = "linux"))]
+use crate::journald;
+use crate::logline::{LogLine, SourceMeta};
+use crate::multiline::{Joiner, MultilineConfig};
+use crate::stats::Stats;
+use crate::tcp::{Listener, TlsConfig};
+use crate::timeutil;
+#[cfg(all(feature = "windows_event", windows))]
+use crate::windows_event;
 
-use std::fs::File;
-use std::io::{BufRead, BufReader};
+use flate2::read::GzDecoder;
+use regex::Regex;
+use std::collections::HashSet;
+use std::fs::{self, File};
+use std::io::{self, BufRead, BufReader, Read, Seek, SeekFrom};
+use std::path::{Path, PathBuf};
 use std::process::{Command, Stdio};
 use std::sync::{
     Arc,
-    atomic::{AtomicBool, Ordering},
-    mpsc::Sender,
+    atomic::{AtomicBool, AtomicU64, Ordering},
+    mpsc::{self, Receiver, Sender, SyncSender, TrySendError},
 };
 use std::thread;
 use std::thread::JoinHandle;
-use std::time::SystemTime;
+use std::time::{Duration, Instant};
 
-/// Spawn one reader thread per source.
-/// Returns the join handles
-pub fn spawn_readers(
-    sources: &[SourceConfig],
-    tx: Sender<LogLine>,
-    shutdown: Arc<AtomicBool>,
-) -> Vec<JoinHandle<()>> {
-    let mut handles = Vec::new();
+/// Settings that affect how raw input is turned into `LogLine`s.
+#[derive(Debug, Clone)]
+struct LineOptions {
+    /// Keep the original bytes of each line for byte-regex matching.
+    binary_match: bool,
+
+    /// Stop the source on invalid UTF-8 instead of decoding it lossily.
+    strict_utf8: bool,
+
+    /// Remove ANSI escape sequences before anything else sees the line.
+    strip_ansi: bool,
+
+    /// Remove every carriage return, not just that of a `\r\n` ending.
+    normalize_crlf: bool,
+
+    /// Trim whitespace around each finished record.
+    trim_whitespace: bool,
+
+    /// Join continuation lines into records (per source).
+    multiline: Option<MultilineConfig>,
+
+    /// Parse records into fields (per source).
+    parse: Option<ParseFormat>,
+
+    /// Compiled size limit for the multiline start pattern.
+    regex_size_limit: usize,
+
+    /// Capacity of the `BufReader` around the input.
+    read_buffer_bytes: usize,
+
+    /// Lines are cut to this many bytes; the rest is skipped.
+    max_line_bytes: usize,
+
+    /// Parse each record's own timestamp into `event_time`.
+    event_time: bool,
+
+    /// Attached to every line; set by the reader once it is known.
+    meta: Option<Arc<SourceMeta>>,
+
+    /// The lines come from a command's stderr (`stream: stderr` field).
+    stderr: bool,
+}
+
+/// How to read a dir source.
+struct DirSpec {
+    path: PathBuf,
+    /// Only files whose names match.
+    pattern: Option<Regex>,
+    follow: bool,
+}
+
+/// How to read one file of a file source.
+struct FileSpec {
+    path: PathBuf,
+    compression: Compression,
+    follow: bool,
+    start_at: StartAt,
+    /// Start with this many of the file's last lines.
+    tail: Option<u64>,
+}
+
+/// What to run for a command source.
+#[derive(Debug, Clone)]
+struct CommandSpec {
+    command: String,
+    args: Vec<String>,
+    capture_stderr: bool,
+}
+
+/// Where a TCP source listens.
+struct TcpSpec {
+    bind: String,
+    tls: Option<TlsConfig>,
+}
 
-    for src in sources {
-        let name = src.name.clone();
-        let kind = src.kind.clone();
-        let tx_clone = tx.clone(); // Multiple threads need their own sender
-        let shutdown_clone = shutdown.clone();
+/// Which journal entries to read for a journald source.
+#[cfg_attr(not(all(feature = "journald", target_os = "linux")), allow(dead_code))]
+struct JournalSpec {
+    unit: Option<String>,
+    start_at: StartAt,
+    follow: bool,
+}
+
+/// Which events to read for a windows_event source.
+#[cfg_attr(not(all(feature = "windows_event", windows)), allow(dead_code))]
+struct EventLogSpec {
+    channel: String,
+    start_at: StartAt,
+    follow: bool,
+}
 
-        let handle = match kind {
-            SourceKind::File { path } => spawn_file_reader(name, path, tx_clone, shutdown_clone),
-            SourceKind::Command { command, args } => {
-                spawn_command_reader(name, command, args, tx_clone, shutdown_clone)
+/// A source that could not be opened (`on_source_error: fail`).
+#[derive(Debug)]
+pub(crate) struct SourceFailure {
+    pub source: String,
+    pub message: String,
+}
+
+/// How often `Readers::join` checks whether the readers have stopped.
+const JOIN_POLL: Duration = Duration::from_millis(10);
+
+/// Warn when a reader has waited this long for room in the queue.
+const FULL_QUEUE_WARN: Duration = Duration::from_secs(1);
+
+/// Hands lines to the consumer over the bounded queue, recording how long
+/// readers wait when it is full.
+#[derive(Clone)]
+struct LineSender {
+    tx: SyncSender<LogLine>,
+    stats: Arc<Stats>,
+    /// Lines this source may still send (`max_lines`), shared by its threads.
+    remaining: Option<Arc<AtomicU64>>,
+    /// Sends the source's end marker once its last thread is done (held
+    /// only to be dropped).
+    _close_notice: Option<Arc<CloseNotice>>,
+    /// Where sources that can't be opened are reported, if that ends the run.
+    failures: Option<Sender<SourceFailure>>,
+    /// Notes the source's first line in the stats, shared by its threads.
+    first_line: Option<Arc<FirstLine>>,
+}
+
+/// Whether a source has sent a line yet.
+#[derive(Debug)]
+struct FirstLine {
+    source: String,
+    sent: AtomicBool,
+}
+
+impl LineSender {
+    /// A sender for one source, limited to `max_lines` lines if set, that
+    /// sends an end marker when dropped by all of the source's threads if
+    /// `lifecycle` is set.
+    fn for_source(&self, src: &SourceConfig, lifecycle: bool) -> Self {
+        Self {
+            tx: self.tx.clone(),
+            stats: self.stats.clone(),
+            remaining: src.max_lines.map(|n| Arc::new(AtomicU64::new(n))),
+            _close_notice: lifecycle.then(|| {
+                Arc::new(CloseNotice {
+                    tx: self.tx.clone(),
+                    source: src.name.clone(),
+                })
+            }),
+            failures: self.failures.clone(),
+            first_line: Some(Arc::new(FirstLine {
+                source: src.name.clone(),
+                sent: AtomicBool::new(false),
+            })),
+        }
+    }
+
+    /// Report that `source` could not be opened, and with
+    /// `on_source_error: fail` have the consumer end the run.
+    fn open_failed(&self, source: &str, message: String) {
+        eprintln!("[logscout] source `{source}`: {message}");
+        if let Some(failures) = &self.failures {
+            let _ = failures.send(SourceFailure {
+                source: source.to_string(),
+                message,
+            });
+        }
+    }
+
+    /// Whether the source has sent all the lines it may send.
+    fn exhausted(&self) -> bool {
+        self.remaining
+            .as_ref()
+            .is_some_and(|r| r.load(Ordering::Relaxed) == 0)
+    }
+
+    /// Send `msg`, blocking while the queue is full. Returns `false` once the
+    /// reader should stop: the consumer is gone (it drops the queue on
+    /// shutdown, which unblocks us) or the source reached `max_lines`.
+    fn send(&self, msg: LogLine) -> bool {
+        let Some(remaining) = &self.remaining else {
+            return self.send_unlimited(msg);
+        };
+
+        // Claim a slot first, so a source's stdout and stderr threads
+        // together never exceed the limit
+        let Ok(left) =
+            remaining.fetch_update(Ordering::Relaxed, Ordering::Relaxed, |n| n.checked_sub(1))
+        else {
+            return false;
+        };
+
+        let source = msg.source.clone();
+        if !self.send_unlimited(msg) {
+            return false;
+        }
+        if left == 1 {
+            eprintln!(
+                "[logscout] source `{}`: reached max_lines, stopping",
+                source
+            );
+            return false;
+        }
+        true
+    }
+
+    fn send_unlimited(&self, msg: LogLine) -> bool {
+        if let Some(first) = &self.first_line
+            && !first.sent.load(Ordering::Relaxed)
+            && !first.sent.swap(true, Ordering::Relaxed)
+        {
+            self.stats.mark_first_line(&first.source);
+        }
+
+        let msg = match self.tx.try_send(msg) {
+            Ok(()) => return true,
+            Err(TrySendError::Disconnected(_)) => return false,
+            Err(TrySendError::Full(msg)) => msg,
+        };
+
+        // The consumer is falling behind
+        let source = msg.source.clone();
+        let started = Instant::now();
+        if self.tx.send(msg).is_err() {
+            return false;
+        }
+
+        let waited = started.elapsed();
+        self.stats.add_queue_wait(waited);
+        if waited >= FULL_QUEUE_WARN {
+            eprintln!(
+                "[logscout] source `{}`: output is falling behind, reader waited {:.1}s for the queue",
+                source,
+                waited.as_secs_f64()
+            );
+        }
+        true
+    }
+}
+
+/// Sends a `closed` marker for `source` when dropped.
+struct CloseNotice {
+    tx: SyncSender<LogLine>,
+    source: String,
+}
+
+impl Drop for CloseNotice {
+    fn drop(&mut self) {
+        let marker = LogLine {
+            closed: true,
+            ..LogLine::new(std::mem::take(&mut self.source), "<EOF>")
+        };
+        // Fails only once the consumer is gone, when nobody would see it
+        let _ = self.tx.send(marker);
+    }
+}
+
+/// The global settings readers are started with. A running reader can't
+/// switch to new ones, so on reload every reader restarts when they change.
+#[derive(Debug, PartialEq)]
+struct ReaderSettings {
+    binary_match: bool,
+    strict_utf8: bool,
+    strip_ansi: bool,
+    normalize_crlf: bool,
+    trim_whitespace: bool,
+    regex_size_limit: usize,
+    read_buffer_bytes: usize,
+    max_line_bytes: usize,
+    /// Lines need their own timestamp (`replay`, `reorder_window_ms`,
+    /// `since`/`until`).
+    event_time: bool,
+    emit_lifecycle_events: bool,
+    follow: bool,
+}
+
+impl ReaderSettings {
+    fn of(cfg: &Config) -> Self {
+        Self {
+            binary_match: cfg.binary_match,
+            strict_utf8: cfg.strict_utf8,
+            strip_ansi: cfg.strip_ansi,
+            normalize_crlf: cfg.normalize_crlf,
+            trim_whitespace: cfg.trim_whitespace,
+            regex_size_limit: cfg.regex_size_limit,
+            read_buffer_bytes: cfg.read_buffer_bytes,
+            max_line_bytes: cfg.max_line_bytes,
+            event_time: cfg.replay
+                || cfg.reorder_window_ms.is_some()
+                || cfg.since.is_some()
+                || cfg.until.is_some(),
+            emit_lifecycle_events: cfg.emit_lifecycle_events,
+            follow: cfg.follow,
+        }
+    }
+}
+
+/// A running source reader and what it was started with.
+struct Reader {
+    source: SourceConfig,
+    stop: Arc<AtomicBool>,
+    handle: JoinHandle<()>,
+}
+
+/// The reader threads, one per source. Each source has its own stop flag,
+/// so sources can be started and stopped one by one (on reload).
+pub struct Readers {
+    tx: LineSender,
+    running: Vec<Reader>,
+    /// Readers of sources removed on reload, asked to stop but not joined.
+    stopped: Vec<Reader>,
+    failures: Option<Receiver<SourceFailure>>,
+    /// Closed once the last reader started with `ordered` is done.
+    last: Option<Receiver<()>>,
+    /// What the running readers were started with.
+    settings: ReaderSettings,
+}
+
+impl Readers {
+    /// Spawn one reader thread per source.
+    pub fn spawn(cfg: &Config, tx: SyncSender<LogLine>, stats: Arc<Stats>) -> Self {
+        let (failures_tx, failures) = match cfg.on_source_error {
+            OnSourceError::Fail => {
+                let (tx, rx) = mpsc::channel();
+                (Some(tx), Some(rx))
             }
+            OnSourceError::Ignore => (None, None),
         };
+        let mut readers = Self {
+            tx: LineSender {
+                tx,
+                stats,
+                remaining: None,
+                _close_notice: None,
+                failures: failures_tx,
+                first_line: None,
+            },
+            running: Vec::new(),
+            stopped: Vec::new(),
+            failures,
+            last: None,
+            settings: ReaderSettings::of(cfg),
+        };
+        for src in &cfg.sources {
+            readers.start(cfg, src);
+        }
+        readers
+    }
 
-        handles.push(handle);
+    /// Start the reader of `src`; with `ordered`, once the reader started
+    /// before it is done.
+    fn start(&mut self, cfg: &Config, src: &SourceConfig) {
+        if cfg.ordered {
+            let previous = self.last.take();
+            self.last = Some(self.start_after(cfg, src, previous));
+        } else {
+            self.start_now(cfg, src);
+        }
     }
 
-    handles
+    fn start_now(&mut self, cfg: &Config, src: &SourceConfig) {
+        let stop = Arc::new(AtomicBool::new(false));
+        let handle = spawn_source(cfg, src, &self.tx, stop.clone());
+        self.running.push(Reader {
+            source: src.clone(),
+            stop,
+            handle,
+        });
+    }
+
+    /// Like `start_now`, but the reader waits for the one started before it
+    /// (`previous`) to finish first (`ordered`). Returns what the next
+    /// reader waits on: a channel closed when this one is done.
+    fn start_after(
+        &mut self,
+        cfg: &Config,
+        src: &SourceConfig,
+        previous: Option<Receiver<()>>,
+    ) -> Receiver<()> {
+        let stop = Arc::new(AtomicBool::new(false));
+        let (done, finished) = mpsc::channel::<()>();
+        let (cfg, source, tx, reader_stop) =
+            (cfg.clone(), src.clone(), self.tx.clone(), stop.clone());
+        let handle = thread::spawn(move || {
+            if let Some(previous) = previous {
+                let _ = previous.recv();
+            }
+            if !reader_stop.load(Ordering::Relaxed) {
+                let _ = spawn_source(&cfg, &source, &tx, reader_stop).join();
+            }
+            drop(done);
+        });
+        self.running.push(Reader {
+            source: src.clone(),
+            stop,
+            handle,
+        });
+        finished
+    }
+
+    /// Switch to the sources of `cfg`: stop the readers of sources that
+    /// were removed or changed, and start readers for new or changed ones.
+    /// If the global settings readers use changed, every reader restarts.
+    /// Returns how many readers were started and stopped.
+    pub fn reconcile(&mut self, cfg: &Config) -> (usize, usize) {
+        let settings = ReaderSettings::of(cfg);
+        let restart_all = settings != self.settings;
+        if restart_all {
+            eprintln!("[logscout] settings for reading lines changed, restarting every source");
+        }
+        self.settings = settings;
+
+        let (kept, gone): (Vec<_>, Vec<_>) = self
+            .running
+            .drain(..)
+            .partition(|r| !restart_all && cfg.sources.contains(&r.source));
+        self.running = kept;
+        let stopped = gone.len();
+        for reader in gone {
+            reader.stop.store(true, Ordering::Relaxed);
+            self.stopped.push(reader);
+        }
+        self.join_stopped();
+
+        let mut started = 0;
+        for src in &cfg.sources {
+            if !self.running.iter().any(|r| r.source == *src) {
+                self.start(cfg, src);
+                started += 1;
+            }
+        }
+        (started, stopped)
+    }
+
+    /// Join the readers of removed sources that have ended by now.
+    fn join_stopped(&mut self) {
+        let (done, left): (Vec<_>, Vec<_>) =
+            self.stopped.drain(..).partition(|r| r.handle.is_finished());
+        self.stopped = left;
+        for reader in done {
+            let _ = reader.handle.join();
+        }
+    }
+
+    /// Whether every reader has finished (all sources read to the end).
+    pub fn finished(&self) -> bool {
+        self.running.iter().all(|r| r.handle.is_finished())
+    }
+
+    /// The first source that couldn't be opened, with `on_source_error: fail`.
+    pub fn failure(&self) -> Option<SourceFailure> {
+        self.failures.as_ref()?.try_recv().ok()
+    }
+
+    /// Ask every reader to stop.
+    pub fn stop_all(&self) {
+        for reader in &self.running {
+            reader.stop.store(true, Ordering::Relaxed);
+        }
+    }
+
+    /// Wait up to `timeout` for the readers to end (after `stop_all`), so
+    /// they can kill their commands and record exit statuses. Returns the
+    /// sources whose readers are still running; they are left behind.
+    pub fn join(self, timeout: Duration) -> Vec<String> {
+        let deadline = Instant::now() + timeout;
+        let all_finished =
+            |r: &Readers| r.finished() && r.stopped.iter().all(|s| s.handle.is_finished());
+        while !all_finished(&self) && Instant::now() < deadline {
+            thread::sleep(JOIN_POLL);
+        }
+
+        let mut stuck = Vec::new();
+        for reader in self.running.into_iter().chain(self.stopped) {
+            if reader.handle.is_finished() {
+                let _ = reader.handle.join();
+            } else {
+                stuck.push(reader.source.name);
+            }
+        }
+        stuck
+    }
+}
+
+/// Spawn the reader thread of one source; it stops once `stop` is set.
+fn spawn_source(
+    cfg: &Config,
+    src: &SourceConfig,
+    tx: &LineSender,
+    stop: Arc<AtomicBool>,
+) -> JoinHandle<()> {
+    let name = src.name.clone();
+    let kind = src.kind.clone();
+    let settings = ReaderSettings::of(cfg);
+    let tx_clone = tx.for_source(src, settings.emit_lifecycle_events); // Multiple threads need their own sender
+    let opts = LineOptions {
+        binary_match: settings.binary_match,
+        strict_utf8: settings.strict_utf8,
+        strip_ansi: settings.strip_ansi,
+        normalize_crlf: settings.normalize_crlf,
+        trim_whitespace: settings.trim_whitespace,
+        multiline: src.multiline.clone(),
+        parse: src.parse_format(),
+        regex_size_limit: settings.regex_size_limit,
+        read_buffer_bytes: settings.read_buffer_bytes,
+        max_line_bytes: settings.max_line_bytes,
+        event_time: settings.event_time,
+        meta: None,
+        stderr: false,
+    };
+
+    match kind {
+        SourceKind::File {
+            path,
+            paths,
+            compression,
+            start_at,
+            tail,
+        } => {
+            let follow = src.follow.unwrap_or(cfg.follow);
+            let paths: Vec<PathBuf> = path.into_iter().chain(paths).collect();
+            let last = paths.len() - 1;
+
+            // Earlier files (e.g. rotated archives) are read whole;
+            // `follow`, `start_at` and `tail` apply to the last one only
+            let files = paths
+                .into_iter()
+                .enumerate()
+                .map(|(i, path)| FileSpec {
+                    compression: Compression::resolve(compression, &path),
+                    path,
+                    follow: follow && i == last,
+                    start_at: if i == last {
+                        start_at
+                    } else {
+                        StartAt::Beginning
+                    },
+                    tail: tail.filter(|_| i == last),
+                })
+                .collect();
+            spawn_file_reader(name, files, opts, tx_clone, stop)
+        }
+        SourceKind::Dir { path, pattern } => {
+            let pattern = pattern.map(|p| {
+                filters::build_regex(&p, "dir pattern", cfg.regex_size_limit)
+                    .expect("validated dir pattern")
+            });
+            let dir = DirSpec {
+                path,
+                pattern,
+                follow: src.follow.unwrap_or(cfg.follow),
+            };
+            spawn_dir_reader(name, dir, opts, tx_clone, stop)
+        }
+        SourceKind::Command {
+            command,
+            args,
+            capture_stderr,
+        } => {
+            let cmd = CommandSpec {
+                command,
+                args,
+                capture_stderr,
+            };
+            let stats = tx.stats.clone();
+            spawn_command_reader(name, cmd, opts, tx_clone, stop, stats)
+        }
+        SourceKind::Tcp { bind, tls } => {
+            spawn_tcp_reader(name, TcpSpec { bind, tls }, opts, tx_clone, stop)
+        }
+        SourceKind::Journald { unit, start_at } => {
+            let journal = JournalSpec {
+                unit,
+                start_at,
+                follow: src.follow.unwrap_or(cfg.follow),
+            };
+            spawn_journald_reader(name, journal, opts, tx_clone, stop)
+        }
+        SourceKind::WindowsEvent { channel, start_at } => {
+            let spec = EventLogSpec {
+                channel,
+                start_at,
+                follow: src.follow.unwrap_or(cfg.follow),
+            };
+            spawn_windows_event_reader(name, spec, opts, tx_clone, stop)
+        }
+    }
 }
 
-/// Spawn a thread to read lines from a file
+/// Spawn a thread to read lines from one or more files, one after another
 fn spawn_file_reader(
     name: String,
-    path: std::path::PathBuf,
-    tx: Sender<LogLine>,
+    files: Vec<FileSpec>,
+    mut opts: LineOptions,
+    tx: LineSender,
     shutdown: Arc<AtomicBool>,
 ) -> JoinHandle<()> {
     thread::spawn(move || {
-        // This can still fail at runtime (file removed/permissions changed)
-        let file = match File::open(&path) {
-            Ok(f) => f,
-            Err(e) => {
-                eprintln!(
-                    "[logscout] soruce `{}`: failed to open file `{}`: {}",
-                    name,
-                    path.display(),
-                    e
-                );
-                return;
+        for spec in files {
+            if shutdown.load(Ordering::Relaxed) || tx.exhausted() {
+                break;
             }
-        };
+            read_file(&name, spec, &mut opts, &tx, &shutdown);
+        }
+    })
+}
 
-        let reader = BufReader::new(file);
+/// Read a single file of a file source. Errors are reported, so the
+/// source can go on with its next file.
+fn read_file(
+    name: &str,
+    spec: FileSpec,
+    opts: &mut LineOptions,
+    tx: &LineSender,
+    shutdown: &Arc<AtomicBool>,
+) {
+    let FileSpec {
+        path,
+        compression,
+        follow,
+        start_at,
+        tail,
+    } = spec;
 
-        for line_result in reader.lines() {
-            if shutdown.load(Ordering::Relaxed) {
-                break;
-            }
-            let line = match line_result {
-                Ok(l) => l,
+    let absolute = std::path::absolute(&path).unwrap_or_else(|_| path.clone());
+    opts.meta = Some(Arc::new(SourceMeta::Path(absolute)));
+
+    if fs::metadata(&path).is_ok_and(|m| follow::is_fifo(&m)) {
+        read_fifo(name, &path, follow, opts, tx, shutdown);
+        return;
+    }
+
+    // This can still fail at runtime (file removed/permissions changed)
+    let mut file = match File::open(&path) {
+        Ok(f) => f,
+        Err(e) => {
+            tx.open_failed(
+                name,
+                format!("failed to open file `{}`: {}", path.display(), e),
+            );
+            return;
+        }
+    };
+
+    // Only lines appended from now on (compressed files are read whole)
+    if start_at == StartAt::End
+        && compression == Compression::None
+        && let Err(e) = file.seek(SeekFrom::End(0))
+    {
+        tx.open_failed(
+            name,
+            format!("failed to seek to the end of `{}`: {}", path.display(), e),
+        );
+        return;
+    }
+
+    // Start with the last `tail` lines (compressed files are rejected by
+    // `Config::validate`)
+    if let Some(n) = tail
+        && let Err(e) = tail_offset(&mut file, n).and_then(|at| file.seek(SeekFrom::Start(at)))
+    {
+        tx.open_failed(
+            name,
+            format!(
+                "failed to find the last {} lines of `{}`: {}",
+                n,
+                path.display(),
+                e
+            ),
+        );
+        return;
+    }
+    tx.stats.mark_started(name);
+
+    // Transparently decompress gzip files (one-shot reads only)
+    let capacity = opts.read_buffer_bytes;
+    let reader: Box<dyn BufRead> = match compression {
+        Compression::Gzip => Box::new(BufReader::with_capacity(capacity, GzDecoder::new(file))),
+        Compression::None if follow => {
+            let follower = FollowReader::new(name, &path, file, shutdown.clone());
+            Box::new(BufReader::with_capacity(capacity, follower))
+        }
+        Compression::None => Box::new(BufReader::with_capacity(capacity, file)),
+    };
+
+    match forward_lines(name, reader, opts, tx, shutdown) {
+        Ok(()) => {}
+        Err(e) if compression == Compression::Gzip => {
+            eprintln!(
+                "[logscout] source `{}`: failed to decompress gzip file `{}`: {}",
+                name,
+                path.display(),
+                e
+            );
+        }
+        Err(e) => {
+            eprintln!(
+                "[logscout] source `{}`: error reading line from file `{}`: {}",
+                name,
+                path.display(),
+                e
+            );
+        }
+    }
+}
+
+/// Spawn a thread reading every matching file of a directory, each on its
+/// own thread. When following, the directory is watched and files that
+/// appear later are read from their start.
+fn spawn_dir_reader(
+    name: String,
+    spec: DirSpec,
+    opts: LineOptions,
+    tx: LineSender,
+    shutdown: Arc<AtomicBool>,
+) -> JoinHandle<()> {
+    thread::spawn(move || {
+        // Watch before listing, so no file created in between is missed
+        let waker = spec.follow.then(|| DirWaker::new(&name, &spec.path));
+        let mut seen = HashSet::new();
+        let mut files = Vec::new();
+        let mut started = false;
+        let done = || shutdown.load(Ordering::Relaxed) || tx.exhausted();
+
+        loop {
+            let mut new = match list_dir(&spec.path, spec.pattern.as_ref()) {
+                Ok(paths) => paths,
+                Err(e) if !started => {
+                    tx.open_failed(
+                        &name,
+                        format!("failed to read directory `{}`: {}", spec.path.display(), e),
+                    );
+                    return;
+                }
                 Err(e) => {
                     eprintln!(
-                        "[logscout] source `{}`: error reading line from file `{}`: {}",
+                        "[logscout] source `{}`: failed to read directory `{}`: {}",
                         name,
-                        path.display(),
+                        spec.path.display(),
                         e
                     );
-                    break;
+                    Vec::new()
                 }
             };
+            new.retain(|path| !seen.contains(path));
+            if !started {
+                tx.stats.mark_started(&name);
+                started = true;
+            }
 
-            let msg = LogLine {
-                source: name.clone(),
-                line: line,
-                timestamp: SystemTime::now(),
-            };
+            for path in new {
+                seen.insert(path.clone());
+                let file = FileSpec {
+                    compression: Compression::resolve(None, &path),
+                    path,
+                    follow: spec.follow,
+                    start_at: StartAt::Beginning,
+                    tail: None,
+                };
+                // Without `follow`, files are read one after another, in
+                // name order
+                if !spec.follow {
+                    read_file(&name, file, &mut opts.clone(), &tx, &shutdown);
+                    continue;
+                }
+                let (name, mut opts, tx, shutdown) =
+                    (name.clone(), opts.clone(), tx.clone(), shutdown.clone());
+                files.push(thread::spawn(move || {
+                    read_file(&name, file, &mut opts, &tx, &shutdown);
+                }));
+            }
 
-            if tx.send(msg).is_err() {
-                break; // Receiver has been dropped
+            let Some(waker) = &waker else { break };
+            if done() {
+                break;
             }
+            waker.wait();
+        }
+
+        for handle in files {
+            let _ = handle.join();
         }
     })
 }
 
+/// Regular files in `dir` whose names match `pattern`, sorted by name.
+fn list_dir(dir: &Path, pattern: Option<&Regex>) -> io::Result<Vec<PathBuf>> {
+    let mut paths = Vec::new();
+    for entry in fs::read_dir(dir)? {
+        let entry = entry?;
+        let file_name = entry.file_name();
+        let matches = pattern.is_none_or(|re| re.is_match(&file_name.to_string_lossy()));
+        if matches && entry.path().is_file() {
+            paths.push(entry.path());
+        }
+    }
+    paths.sort();
+    Ok(paths)
+}
+
+/// Offset where the last `n` lines of `file` start, found by scanning back
+/// from the end in fixed-size chunks. 0 if the file has fewer lines.
+fn tail_offset(file: &mut File, n: u64) -> io::Result<u64> {
+    const CHUNK: u64 = 64 * 1024;
+
+    let len = file.seek(SeekFrom::End(0))?;
+    if n == 0 {
+        return Ok(len);
+    }
+
+    let mut buf = vec![0; CHUNK as usize];
+    let mut end = len;
+    let mut newlines = 0;
+    while end > 0 {
+        let start = end.saturating_sub(CHUNK);
+        let chunk = &mut buf[..(end - start) as usize];
+        file.seek(SeekFrom::Start(start))?;
+        file.read_exact(chunk)?;
+
+        for (i, _) in chunk.iter().enumerate().rev().filter(|(_, b)| **b == b'\n') {
+            let line_start = start + i as u64 + 1;
+            // A final newline ends the last line rather than starting one
+            if line_start == len {
+                continue;
+            }
+            newlines += 1;
+            if newlines == n {
+                return Ok(line_start);
+            }
+        }
+        end = start;
+    }
+    Ok(0)
+}
+
+/// Read a named pipe. Each writer's data ends with EOF when it disconnects;
+/// when following, reopen the pipe and wait for the next writer.
+fn read_fifo(
+    name: &str,
+    path: &Path,
+    follow: bool,
+    opts: &LineOptions,
+    tx: &LineSender,
+    shutdown: &AtomicBool,
+) {
+    loop {
+        // Blocks until a writer opens the other end
+        let file = match File::open(path) {
+            Ok(f) => f,
+            Err(e) => {
+                tx.open_failed(
+                    name,
+                    format!("failed to open pipe `{}`: {}", path.display(), e),
+                );
+                return;
+            }
+        };
+        tx.stats.mark_started(name);
+
+        if let Err(e) = forward_lines(
+            name,
+            BufReader::with_capacity(opts.read_buffer_bytes, file),
+            opts,
+            tx,
+            shutdown,
+        ) {
+            eprintln!(
+                "[logscout] source `{}`: error reading line from pipe `{}`: {}",
+                name,
+                path.display(),
+                e
+            );
+            return;
+        }
+
+        if !follow || shutdown.load(Ordering::Relaxed) || tx.exhausted() {
+            return;
+        }
+    }
+}
+
 fn spawn_command_reader(
     name: String,
-    command: String,
-    args: Vec<String>,
-    tx: Sender<LogLine>,
+    cmd: CommandSpec,
+    mut opts: LineOptions,
+    tx: LineSender,
     shutdown: Arc<AtomicBool>,
+    stats: Arc<Stats>,
 ) -> JoinHandle<()> {
     thread::spawn(move || {
-        // Execute the command and capture its stdout
+        let CommandSpec {
+            command,
+            args,
+            capture_stderr,
+        } = cmd;
+
+        // Execute the command and capture its stdout (and stderr if asked)
+        let stderr_cfg = if capture_stderr {
+            Stdio::piped()
+        } else {
+            Stdio::null()
+        };
         let mut child = match Command::new(&command)
             .args(&args)
             .stdout(Stdio::piped())
-            .stderr(Stdio::null())
+            .stderr(stderr_cfg)
             .spawn()
         {
             Ok(c) => c,
             Err(e) => {
-                eprintln!(
-                    "[logscout] source `{}`: failed to spawn command `{}`: {}",
-                    name, command, e
+                tx.open_failed(
+                    &name,
+                    format!("failed to spawn command `{}`: {}", command, e),
                 );
                 return;
             }
@@ -124,48 +945,488 @@ fn spawn_command_reader(
         let stdout = match child.stdout.take() {
             Some(s) => s,
             None => {
-                eprintln!(
-                    "[logscout] source `{}`: failed to capture stdout of command `{}`",
-                    name, command
+                tx.open_failed(
+                    &name,
+                    format!("failed to capture stdout of command `{}`", command),
                 );
                 return;
             }
         };
 
-        let reader = BufReader::new(stdout);
+        opts.meta = Some(Arc::new(SourceMeta::Pid(child.id())));
+        stats.mark_started(&name);
 
-        for line_result in reader.lines() {
-            if shutdown.load(Ordering::Relaxed) {
-                break;
-            }
-            let line = match line_result {
-                Ok(l) => l,
-                Err(e) => {
+        // stderr gets its own thread so neither pipe can fill up and stall the child
+        let stderr_handle = child.stderr.take().map(|stderr| {
+            let (name, command) = (name.clone(), command.clone());
+            let (mut opts, tx, shutdown) = (opts.clone(), tx.clone(), shutdown.clone());
+            opts.stderr = true;
+            thread::spawn(move || {
+                let reader = BufReader::with_capacity(opts.read_buffer_bytes, stderr);
+                if let Err(e) = forward_lines(&name, reader, &opts, &tx, &shutdown) {
                     eprintln!(
-                        "[logscout] source `{}`: error reading line from command `{}`: {}",
+                        "[logscout] source `{}`: error reading stderr of command `{}`: {}",
                         name, command, e
                     );
-                    break;
                 }
-            };
+            })
+        });
 
-            let msg = LogLine {
-                source: name.clone(),
-                line: line,
-                timestamp: SystemTime::now(),
-            };
+        let reader = BufReader::with_capacity(opts.read_buffer_bytes, stdout);
+
+        if let Err(e) = forward_lines(&name, reader, &opts, &tx, &shutdown) {
+            eprintln!(
+                "[logscout] source `{}`: error reading line from command `{}`: {}",
+                name, command, e
+            );
+        }
+
+        // If we're shutting down (or done with this source), kill the child
+        // process so it doesn't linger!
+        let stopping = shutdown.load(Ordering::Relaxed) || tx.exhausted();
+        let killed = stopping && child.kill().is_ok();
 
-            if tx.send(msg).is_err() {
-                break; // Receiver has been dropped
+        // stderr hits EOF once the child exits (or is killed)
+        if let Some(handle) = stderr_handle {
+            let _ = handle.join();
+        }
+
+        // Wait for the child to exit and record how it went
+        match child.wait() {
+            Ok(status) => {
+                // Don't report the status of a child we killed ourselves
+                if !killed {
+                    let code = match status.code() {
+                        Some(c) => format!("status {c}"),
+                        None => status.to_string(), // e.g. "signal: 9 (SIGKILL)"
+                    };
+                    eprintln!("[logscout] source `{}`: command exited with {}", name, code);
+                }
+                stats.set_exit_status(&name, status);
+            }
+            Err(e) => {
+                eprintln!(
+                    "[logscout] source `{}`: failed to wait for command `{}`: {}",
+                    name, command, e
+                );
             }
         }
+    })
+}
 
-        // If we're shutting down, kill the child process so it doesn't linger!
+/// Spawn a thread accepting TCP connections, each read on its own thread
+/// until the peer disconnects.
+fn spawn_tcp_reader(
+    name: String,
+    spec: TcpSpec,
+    opts: LineOptions,
+    tx: LineSender,
+    shutdown: Arc<AtomicBool>,
+) -> JoinHandle<()> {
+    thread::spawn(move || {
+        let listener = match Listener::bind(&spec.bind, spec.tls.as_ref()) {
+            Ok(l) => l,
+            Err(e) => {
+                tx.open_failed(&name, format!("failed to listen on `{}`: {}", spec.bind, e));
+                return;
+            }
+        };
+        tx.stats.mark_started(&name);
+
+        let done = || shutdown.load(Ordering::Relaxed) || tx.exhausted();
+        while let Some((conn, peer)) = listener.accept(&shutdown, done) {
+            let mut opts = opts.clone();
+            opts.meta = Some(Arc::new(SourceMeta::Peer(peer)));
+            let (name, tx, shutdown) = (name.clone(), tx.clone(), shutdown.clone());
+            thread::spawn(move || {
+                let reader = BufReader::with_capacity(opts.read_buffer_bytes, conn);
+                if let Err(e) = forward_lines(&name, reader, &opts, &tx, &shutdown) {
+                    eprintln!(
+                        "[logscout] source `{}`: error reading line from `{}`: {}",
+                        name, peer, e
+                    );
+                }
+            });
+        }
+    })
+}
+
+/// Spawn a thread streaming `MESSAGE`s from the systemd journal.
+#[cfg(all(feature = "journald", target_os = "linux"))]
+fn spawn_journald_reader(
+    name: String,
+    spec: JournalSpec,
+    opts: LineOptions,
+    tx: LineSender,
+    shutdown: Arc<AtomicBool>,
+) -> JoinHandle<()> {
+    thread::spawn(move || {
+        let mut failed = None;
+        let mut lineno = 0;
+        let mut opened = false;
+        let result = journald::read(
+            spec.unit.as_deref(),
+            spec.start_at,
+            spec.follow,
+            &shutdown,
+            || {
+                opened = true;
+                tx.stats.mark_started(&name)
+            },
+            |message| match make_line(&name, message, &opts) {
+                Ok(msg) => {
+                    lineno += 1;
+                    tx.send(with_fields(LogLine { lineno, ..msg }, &opts))
+                }
+                Err(e) => {
+                    failed = Some(e);
+                    false
+                }
+            },
+        );
+
+        match result.err().or(failed) {
+            Some(e) if !opened => tx.open_failed(&name, format!("failed to open the journal: {e}")),
+            Some(e) => eprintln!(
+                "[logscout] source `{}`: error reading the journal: {}",
+                name, e
+            ),
+            None => {}
+        }
+    })
+}
+
+/// Without journal support `Config::validate` rejects journald sources.
+#[cfg(not(all(feature = "journald", target_os = "linux")))]
+fn spawn_journald_reader(
+    name: String,
+    _spec: JournalSpec,
+    _opts: LineOptions,
+    _tx: LineSender,
+    _shutdown: Arc<AtomicBool>,
+) -> JoinHandle<()> {
+    eprintln!(
+        "[logscout] source `{}`: built without journald support, skipping it",
+        name
+    );
+    thread::spawn(|| {})
+}
+
+/// Spawn a thread streaming formatted events from a Windows Event Log channel.
+#[cfg(all(feature = "windows_event", windows))]
+fn spawn_windows_event_reader(
+    name: String,
+    spec: EventLogSpec,
+    opts: LineOptions,
+    tx: LineSender,
+    shutdown: Arc<AtomicBool>,
+) -> JoinHandle<()> {
+    thread::spawn(move || {
+        let mut failed = None;
+        let mut lineno = 0;
+        let mut opened = false;
+        let result = windows_event::read(
+            &spec.channel,
+            spec.start_at,
+            spec.follow,
+            &shutdown,
+            || {
+                opened = true;
+                tx.stats.mark_started(&name)
+            },
+            |event| match make_line(&name, event, &opts) {
+                Ok(msg) => {
+                    lineno += 1;
+                    tx.send(with_fields(LogLine { lineno, ..msg }, &opts))
+                }
+                Err(e) => {
+                    failed = Some(e);
+                    false
+                }
+            },
+        );
+
+        match result.err().or(failed) {
+            Some(e) if !opened => tx.open_failed(
+                &name,
+                format!("failed to open event log channel `{}`: {e}", spec.channel),
+            ),
+            Some(e) => eprintln!(
+                "[logscout] source `{}`: error reading event log channel `{}`: {}",
+                name, spec.channel, e
+            ),
+            None => {}
+        }
+    })
+}
+
+/// Without event log support `Config::validate` rejects windows_event sources.
+#[cfg(not(all(feature = "windows_event", windows)))]
+fn spawn_windows_event_reader(
+    name: String,
+    _spec: EventLogSpec,
+    _opts: LineOptions,
+    _tx: LineSender,
+    _shutdown: Arc<AtomicBool>,
+) -> JoinHandle<()> {
+    eprintln!(
+        "[logscout] source `{}`: built without windows_event support, skipping it",
+        name
+    );
+    thread::spawn(|| {})
+}
+
+/// Read lines from `reader` and send them to `tx` until EOF, shutdown,
+/// or the receiver is dropped. Read errors are returned to the caller.
+fn forward_lines<R: BufRead>(
+    name: &str,
+    mut reader: R,
+    opts: &LineOptions,
+    tx: &LineSender,
+    shutdown: &AtomicBool,
+) -> io::Result<()> {
+    let mut joiner = opts
+        .multiline
+        .clone()
+        .map(|ml| Joiner::new(ml, opts.regex_size_limit));
+    let mut buf = Vec::new();
+    let mut lineno = 0;
+    let mut warned = false;
+
+    let result = loop {
         if shutdown.load(Ordering::Relaxed) {
-            let _ = child.kill();
+            break Ok(());
+        }
+
+        // One byte more than allowed tells a line that is too long apart
+        // from one that just fits
+        buf.clear();
+        let limit = opts.max_line_bytes as u64 + 1;
+        match reader.by_ref().take(limit).read_until(b'\n', &mut buf) {
+            Ok(0) => break Ok(()), // EOF
+            Ok(_) => {}
+            Err(e) => break Err(e),
+        }
+        lineno += 1;
+        if buf.len() > opts.max_line_bytes && buf.last() != Some(&b'\n') {
+            if !warned {
+                eprintln!(
+                    "[logscout] source `{name}`: line {lineno} is longer than {} bytes, cutting it (and any later ones) short",
+                    opts.max_line_bytes
+                );
+                warned = true;
+            }
+            truncate_line(&mut buf, opts.max_line_bytes);
+            if let Err(e) = reader.skip_until(b'\n') {
+                break Err(e);
+            }
+        }
+        strip_line_ending(&mut buf);
+
+        let msg = match make_line(name, std::mem::take(&mut buf), opts) {
+            Ok(m) => LogLine { lineno, ..m },
+            Err(e) => break Err(e),
+        };
+
+        let ready = match joiner.as_mut() {
+            Some(j) => j.push(msg),
+            None => Some(msg),
+        };
+        if let Some(msg) = ready
+            && !tx.send(with_fields(msg, opts))
+        {
+            return Ok(()); // Receiver has been dropped
+        }
+    };
+
+    // Emit a dangling partial record before giving up on this source
+    if let Some(msg) = joiner.and_then(|mut j| j.flush()) {
+        let _ = tx.send(with_fields(msg, opts));
+    }
+
+    result
+}
+
+/// Cut `buf` to at most `max` bytes, leaving out a UTF-8 character the
+/// cut would split.
+fn truncate_line(buf: &mut Vec<u8>, max: usize) {
+    let cut = match std::str::from_utf8(&buf[..max]) {
+        // Only the last character is incomplete
+        Err(e) if e.error_len().is_none() => e.valid_up_to(),
+        _ => max,
+    };
+    buf.truncate(cut);
+}
+
+/// Trim a finished record if asked, then parse it into fields (and its
+/// event time, if needed); malformed lines are left as they are.
+fn with_fields(mut msg: LogLine, opts: &LineOptions) -> LogLine {
+    // After joining, so `multiline` still sees indented continuation lines
+    if opts.trim_whitespace {
+        if msg.line.trim_ascii().len() != msg.line.len() {
+            msg.line = msg.line.trim_ascii().to_string();
+        }
+        if let Some(raw) = &mut msg.raw
+            && raw.trim_ascii().len() != raw.len()
+        {
+            *raw = raw.trim_ascii().to_vec();
         }
+    }
+    if let Some(format) = opts.parse
+        && let Some(fields) = fields::parse(format, &msg.line)
+    {
+        msg.fields = fields;
+    }
+    if opts.event_time {
+        msg.event_time = timeutil::event_time(&msg.line, &msg.fields);
+    }
+    if opts.stderr {
+        msg.fields
+            .insert("stream".to_string(), "stderr".to_string());
+    }
+    msg
+}
 
-        // Wait for the child to exit; ignore status for now
-        let _ = child.wait();
+/// Remove a trailing `\n` or `\r\n`, like `BufRead::lines` does.
+fn strip_line_ending(buf: &mut Vec<u8>) {
+    if buf.last() == Some(&b'\n') {
+        buf.pop();
+        if buf.last() == Some(&b'\r') {
+            buf.pop();
+        }
+    }
+}
+
+/// Turn the raw bytes of one line into a `LogLine`.
+fn make_line(name: &str, mut bytes: Vec<u8>, opts: &LineOptions) -> io::Result<LogLine> {
+    if opts.strip_ansi {
+        ansi::strip(&mut bytes);
+    }
+    // E.g. a last line without `\n`, or `\r\n` inside journal messages
+    if opts.normalize_crlf {
+        bytes.retain(|&b| b != b'\r');
+    }
+    let (line, raw) = if opts.binary_match {
+        // Keep the original bytes for matching; the text is only for display
+        (String::from_utf8_lossy(&bytes).into_owned(), Some(bytes))
+    } else if opts.strict_utf8 {
+        let line = String::from_utf8(bytes).map_err(|_| {
+            io::Error::new(
+                io::ErrorKind::InvalidData,
+                "stream did not contain valid UTF-8",
+            )
+        })?;
+        (line, None)
+    } else {
+        // Invalid sequences become U+FFFD rather than ending the source
+        let line = match String::from_utf8(bytes) {
+            Ok(line) => line,
+            Err(e) => String::from_utf8_lossy(e.as_bytes()).into_owned(),
+        };
+        (line, None)
+    };
+
+    // `lineno` is assigned by the reader loop, `tag` and `seq` by the consumer
+    Ok(LogLine {
+        raw,
+        meta: opts.meta.clone(),
+        ..LogLine::new(name, line)
     })
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn options(normalize_crlf: bool) -> LineOptions {
+        LineOptions {
+            binary_match: false,
+            strict_utf8: false,
+            strip_ansi: false,
+            normalize_crlf,
+            trim_whitespace: false,
+            multiline: None,
+            parse: None,
+            regex_size_limit: crate::config::default_regex_size_limit(),
+            read_buffer_bytes: 8192,
+            max_line_bytes: 1024 * 1024,
+            event_time: false,
+            meta: None,
+            stderr: false,
+        }
+    }
+
+    /// The line text as the reader loop would produce it from `bytes`.
+    fn read(bytes: &[u8], normalize_crlf: bool) -> String {
+        let mut buf = bytes.to_vec();
+        strip_line_ending(&mut buf);
+        make_line("app", buf, &options(normalize_crlf))
+            .unwrap()
+            .line
+    }
+
+    #[test]
+    fn normalize_crlf_removes_what_the_line_ending_leaves() {
+        // A plain `\r\n` ending is stripped either way
+        assert_eq!(read(b"done\r\n", false), "done");
+        assert_eq!(read(b"done\r\n", true), "done");
+
+        // Only `normalize_crlf` handles a last line without `\n`, doubled
+        // endings and carriage returns inside the line
+        for (bytes, cleaned) in [
+            (&b"last\r"[..], "last"),
+            (b"twice\r\r\n", "twice"),
+            (b"a\r\nb\r\n", "a\nb"),
+        ] {
+            assert_ne!(read(bytes, false), cleaned);
+            assert_eq!(read(bytes, true), cleaned);
+        }
+    }
+
+    /// Read `input` as a source would, returning the lines it sent and how
+    /// the reader ended.
+    fn forward(input: &[u8], opts: &LineOptions) -> (Vec<String>, io::Result<()>) {
+        let (tx, rx) = mpsc::sync_channel(64);
+        let sender = LineSender {
+            tx,
+            stats: Arc::new(Stats::new()),
+            remaining: None,
+            _close_notice: None,
+            failures: None,
+            first_line: None,
+        };
+        let result = forward_lines("app", input, opts, &sender, &AtomicBool::new(false));
+        drop(sender);
+        (rx.iter().map(|m| m.line).collect(), result)
+    }
+
+    #[test]
+    fn invalid_utf8_is_replaced_and_reading_goes_on() {
+        let (lines, result) = forward(b"one\n\xFFtwo\nthree\n", &options(false));
+        assert!(result.is_ok());
+        assert_eq!(lines, ["one", "\u{FFFD}two", "three"]);
+    }
+
+    #[test]
+    fn strict_utf8_stops_at_the_first_invalid_line() {
+        let opts = LineOptions {
+            strict_utf8: true,
+            ..options(false)
+        };
+        let (lines, result) = forward(b"one\n\xFFtwo\nthree\n", &opts);
+        assert_eq!(result.unwrap_err().kind(), io::ErrorKind::InvalidData);
+        assert_eq!(lines, ["one"]);
+    }
+
+    #[test]
+    fn long_lines_are_cut_and_reading_goes_on() {
+        let opts = LineOptions {
+            max_line_bytes: 4,
+            ..options(false)
+        };
+        let (lines, result) = forward("abcd\nabcdef\r\nxyzé\nok".as_bytes(), &opts);
+        assert!(result.is_ok());
+        // `é` is two bytes: it is left out rather than split
+        assert_eq!(lines, ["abcd", "abcd", "xyz", "ok"]);
+    }
+}
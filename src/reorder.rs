@@ -0,0 +1,87 @@
+// src/reorder.rs
+use crate::logline::LogLine;
+use std::cmp::{Ordering, Reverse};
+use std::collections::BinaryHeap;
+use std::time::{Duration, Instant, SystemTime};
+
+/// Holds lines back for a short window and releases them sorted by time
+/// (`reorder_window_ms`), so lines from different sources come out in
+/// event order rather than read order.
+///
+/// Lines are keyed by their parsed `event_time`, else by when they were
+/// read. The earliest buffered line is released once it has waited for the
+/// window; a later line with an earlier time can still overtake it until
+/// then. At most `capacity` lines are held; beyond that the earliest is
+/// released early.
+#[derive(Debug)]
+pub struct Reorder {
+    window: Duration,
+    capacity: usize,
+    heap: BinaryHeap<Reverse<Entry>>,
+    /// Arrival counter, so lines with equal times keep their order.
+    arrivals: u64,
+}
+
+#[derive(Debug)]
+struct Entry {
+    key: SystemTime,
+    arrival: u64,
+    due: Instant,
+    line: LogLine,
+}
+
+impl PartialEq for Entry {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for Entry {}
+
+impl PartialOrd for Entry {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Entry {
+    fn cmp(&self, other: &Self) -> Ordering {
+        (self.key, self.arrival).cmp(&(other.key, other.arrival))
+    }
+}
+
+impl Reorder {
+    pub fn new(window: Duration, capacity: usize) -> Self {
+        Self {
+            window,
+            capacity,
+            heap: BinaryHeap::new(),
+            arrivals: 0,
+        }
+    }
+
+    pub fn push(&mut self, line: LogLine, now: Instant) {
+        self.arrivals += 1;
+        self.heap.push(Reverse(Entry {
+            key: line.event_time.unwrap_or(line.timestamp),
+            arrival: self.arrivals,
+            due: now + self.window,
+            line,
+        }));
+    }
+
+    /// The earliest line if it is due at `now` (or the buffer is over
+    /// capacity); with `None`, the earliest line whatever its due time.
+    pub fn pop(&mut self, now: Option<Instant>) -> Option<LogLine> {
+        let Reverse(first) = self.heap.peek()?;
+        let ready = match now {
+            Some(now) => first.due <= now || self.heap.len() > self.capacity,
+            None => true,
+        };
+        ready.then(|| self.heap.pop().expect("peeked entry").0.line)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.heap.is_empty()
+    }
+}
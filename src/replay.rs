@@ -0,0 +1,41 @@
+// src/replay.rs
+use crate::logline::LogLine;
+use std::time::{Duration, Instant, SystemTime};
+
+/// Spaces emitted lines out like their own timestamps (`replay`).
+///
+/// Each line is due `gap / speed` after the previous timed line, measured
+/// from when that line was due, so time spent writing does not add up.
+/// Lines without a timestamp, or older than the previous one, are due
+/// right away.
+#[derive(Debug)]
+pub struct Replayer {
+    speed: f64,
+    /// Event time of the last timed line and when it was due.
+    last: Option<(SystemTime, Instant)>,
+}
+
+impl Replayer {
+    pub fn new(speed: f64) -> Self {
+        Self { speed, last: None }
+    }
+
+    /// How long to wait before emitting `msg`.
+    pub fn delay(&mut self, msg: &LogLine) -> Duration {
+        let Some(event) = msg.event_time else {
+            return Duration::ZERO;
+        };
+        let now = Instant::now();
+
+        let due = match self.last {
+            Some((prev_event, prev_due)) => match event.duration_since(prev_event) {
+                Ok(gap) => prev_due + gap.div_f64(self.speed),
+                Err(_) => now, // out of order: start over from here
+            },
+            None => now,
+        };
+
+        self.last = Some((event, due.max(now)));
+        due.saturating_duration_since(now)
+    }
+}
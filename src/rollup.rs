@@ -0,0 +1,239 @@
+// src/rollup.rs
+use crate::config::ConfigError;
+use crate::filters;
+use crate::logline::LogLine;
+use crate::sink_policy::OnError;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs::{File, OpenOptions};
+use std::io::{self, Write};
+use std::path::PathBuf;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+#[derive(Debug, Deserialize, Serialize, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct RollupConfig {
+    /// File that receives one JSON summary per window.
+    pub path: PathBuf,
+
+    /// Length of each window in seconds.
+    #[serde(default = "default_interval_secs")]
+    pub interval_secs: u64,
+
+    /// Number of distinct messages listed per summary.
+    #[serde(default = "default_top")]
+    pub top: usize,
+
+    /// Lines matching this regex count as errors.
+    #[serde(default = "default_error_pattern")]
+    pub error_pattern: String,
+
+    /// What to do when writing a summary fails.
+    #[serde(default)]
+    pub on_error: OnError,
+}
+
+fn default_interval_secs() -> u64 {
+    60
+}
+
+fn default_top() -> usize {
+    10
+}
+
+fn default_error_pattern() -> String {
+    r"(?i)\b(?:error|fatal|critical|panic)\b".to_string()
+}
+
+/// One line of a rollup summary.
+#[derive(Debug, Serialize)]
+struct Entry<'a> {
+    source: &'a str,
+    message: &'a str,
+    count: u64,
+}
+
+/// A rollup summary record.
+#[derive(Debug, Serialize)]
+struct Record<'a> {
+    #[serde(rename = "type")]
+    kind: &'static str,
+    window_start_ms: u128,
+    window_end_ms: u128,
+    /// Error lines seen in the window, including those not listed.
+    errors: u64,
+    top: Vec<Entry<'a>>,
+}
+
+/// Counts normalized error lines per window and writes the most frequent
+/// ones as a JSON summary when the window closes.
+pub struct Rollup {
+    re: Regex,
+    top: usize,
+    interval: Duration,
+    file: File,
+    started: Instant,
+    started_at: SystemTime,
+    counts: HashMap<(String, String), u64>,
+}
+
+impl Rollup {
+    pub fn open(cfg: &RollupConfig, size_limit: usize) -> Result<Self, ConfigError> {
+        let re = filters::build_regex(&cfg.error_pattern, "rollup error", size_limit)?;
+        let file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&cfg.path)
+            .map_err(|e| ConfigError::OutputIo {
+                path: cfg.path.display().to_string(),
+                source: e,
+            })?;
+
+        Ok(Self {
+            re,
+            top: cfg.top,
+            interval: Duration::from_secs(cfg.interval_secs),
+            file,
+            started: Instant::now(),
+            started_at: SystemTime::now(),
+            counts: HashMap::new(),
+        })
+    }
+
+    /// Count `msg` if it is an error line.
+    pub fn observe(&mut self, msg: &LogLine) {
+        if self.re.is_match(&msg.line) {
+            let key = (msg.source.clone(), normalize(&msg.line));
+            *self.counts.entry(key).or_insert(0) += 1;
+        }
+    }
+
+    /// Write the summary and start a new window if the current one is over.
+    pub fn roll_if_due(&mut self) -> io::Result<()> {
+        if self.started.elapsed() < self.interval {
+            return Ok(());
+        }
+        self.roll()
+    }
+
+    /// Write the summary for the current window (if it saw any errors)
+    /// and start a new one. The window is kept if the write fails.
+    pub fn roll(&mut self) -> io::Result<()> {
+        let now = SystemTime::now();
+        if !self.counts.is_empty() {
+            let mut entries: Vec<_> = self.counts.iter().collect();
+            entries.sort_by(|(a_key, a), (b_key, b)| b.cmp(a).then_with(|| a_key.cmp(b_key)));
+
+            let record = Record {
+                kind: "rollup",
+                window_start_ms: epoch_ms(self.started_at),
+                window_end_ms: epoch_ms(now),
+                errors: self.counts.values().sum(),
+                top: entries
+                    .into_iter()
+                    .take(self.top)
+                    .map(|((source, message), &count)| Entry {
+                        source,
+                        message,
+                        count,
+                    })
+                    .collect(),
+            };
+            // Serializing plain strings and integers cannot fail.
+            let json = serde_json::to_string(&record).expect("rollup serialization failed");
+            writeln!(self.file, "{json}")?;
+        }
+
+        self.counts.clear();
+        self.started = Instant::now();
+        self.started_at = now;
+        Ok(())
+    }
+}
+
+impl Drop for Rollup {
+    fn drop(&mut self) {
+        // Summarize the partial window on shutdown; there is no later
+        // write left to retry it with
+        if let Err(e) = self.roll() {
+            eprintln!("[logscout] rollup: failed to write the summary of the last window: {e}");
+        }
+    }
+}
+
+/// Collapse the parts of a message that vary between occurrences
+/// (numbers, ids, durations) so repeats group together.
+fn normalize(line: &str) -> String {
+    let mut out = String::with_capacity(line.len());
+    let mut in_number = false;
+    for c in line.trim().chars() {
+        if c.is_ascii_digit() {
+            if !in_number {
+                out.push('#');
+                in_number = true;
+            }
+        } else {
+            in_number = false;
+            out.push(c);
+        }
+    }
+    out
+}
+
+fn epoch_ms(t: SystemTime) -> u128 {
+    t.duration_since(UNIX_EPOCH)
+        .map(|d| d.as_millis())
+        .unwrap_or(0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::{Value, json};
+    use std::fs;
+
+    #[test]
+    fn summaries_list_the_most_frequent_errors() {
+        let path =
+            std::env::temp_dir().join(format!("logscout-rollup-{}.jsonl", std::process::id()));
+        let _ = fs::remove_file(&path);
+        let cfg = RollupConfig {
+            path: path.clone(),
+            interval_secs: 0,
+            top: 1,
+            error_pattern: default_error_pattern(),
+            on_error: OnError::Stop,
+        };
+        let mut rollup = Rollup::open(&cfg, 1 << 20).unwrap();
+
+        for (source, line) in [
+            ("api", "ERROR timeout after 30s"),
+            ("api", "ERROR timeout after 45s"),
+            ("db", "FATAL disk full"),
+            ("api", "INFO request served"),
+        ] {
+            rollup.observe(&LogLine::new(source, line));
+        }
+        rollup.roll_if_due().unwrap();
+        // The last, partial window is written when the rollup is dropped
+        rollup.observe(&LogLine::new("db", "FATAL disk full"));
+        drop(rollup);
+
+        let records: Vec<Value> = fs::read_to_string(&path)
+            .unwrap()
+            .lines()
+            .map(|l| serde_json::from_str(l).unwrap())
+            .collect();
+        let _ = fs::remove_file(&path);
+        assert_eq!(records.len(), 2);
+        assert_eq!(records[0]["type"], "rollup");
+        assert_eq!(records[0]["errors"], 3);
+        assert_eq!(
+            records[0]["top"],
+            json!([{"source": "api", "message": "ERROR timeout after #s", "count": 2}])
+        );
+        assert_eq!(records[1]["errors"], 1);
+        assert_eq!(records[1]["top"][0]["source"], "db");
+    }
+}
@@ -0,0 +1,33 @@
+// src/sample.rs
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+#[derive(Debug, Deserialize, Serialize, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct SampleConfig {
+    /// Emit one in every `rate` lines that pass the filters (per source).
+    pub rate: u64,
+}
+
+/// Deterministic 1-in-N sampling with a separate counter per source.
+#[derive(Debug)]
+pub struct Sampler {
+    rate: u64,
+    counts: HashMap<String, u64>,
+}
+
+impl Sampler {
+    pub fn new(cfg: &SampleConfig) -> Self {
+        Self {
+            rate: cfg.rate,
+            counts: HashMap::new(),
+        }
+    }
+
+    /// Count a line from `source` and say whether it should be emitted.
+    pub fn keep(&mut self, source: &str) -> bool {
+        let count = self.counts.entry(source.to_string()).or_insert(0);
+        *count += 1;
+        count.is_multiple_of(self.rate)
+    }
+}
@@ -0,0 +1,125 @@
+// src/sink_policy.rs
+use serde::{Deserialize, Serialize};
+use std::io;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::thread;
+use std::time::Duration;
+
+/// What to do when writing to an output fails.
+#[derive(Debug, Deserialize, Serialize, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum OnError {
+    /// Report the error and stop the run.
+    #[default]
+    Stop,
+    /// Skip the line for this output and carry on.
+    Drop,
+    /// Retry a few times with backoff, then skip the line.
+    Retry,
+    /// Keep retrying until the write succeeds or shutdown is requested.
+    Block,
+    /// Stop using this output and carry on with the others.
+    Disable,
+}
+
+/// Retries made by `OnError::Retry` before a line is dropped.
+const RETRY_ATTEMPTS: u32 = 3;
+
+const INITIAL_BACKOFF: Duration = Duration::from_millis(50);
+const MAX_BACKOFF: Duration = Duration::from_secs(1);
+
+/// Applies an `OnError` policy to the writes of one output.
+#[derive(Debug)]
+pub struct SinkGuard {
+    name: &'static str,
+    policy: OnError,
+    disabled: bool,
+}
+
+impl SinkGuard {
+    pub fn new(name: &'static str, policy: OnError) -> Self {
+        Self {
+            name,
+            policy,
+            disabled: false,
+        }
+    }
+
+    /// Run `write` under the policy.
+    ///
+    /// Returns whether the write went through, or an error if the run
+    /// should stop.
+    pub fn run(
+        &mut self,
+        shutdown: &AtomicBool,
+        mut write: impl FnMut() -> io::Result<()>,
+    ) -> io::Result<bool> {
+        if self.disabled {
+            return Ok(false);
+        }
+
+        let err = match write() {
+            Ok(()) => return Ok(true),
+            Err(e) => e,
+        };
+
+        match self.policy {
+            OnError::Stop => Err(err),
+
+            OnError::Drop => {
+                eprintln!(
+                    "[logscout] output `{}`: write failed, line dropped: {err}",
+                    self.name
+                );
+                Ok(false)
+            }
+
+            OnError::Disable => {
+                eprintln!(
+                    "[logscout] output `{}`: write failed, disabling it: {err}",
+                    self.name
+                );
+                self.disabled = true;
+                Ok(false)
+            }
+
+            OnError::Retry => {
+                let mut backoff = INITIAL_BACKOFF;
+                let mut last = err;
+                for _ in 0..RETRY_ATTEMPTS {
+                    if shutdown.load(Ordering::SeqCst) {
+                        break;
+                    }
+                    thread::sleep(backoff);
+                    backoff *= 2;
+                    match write() {
+                        Ok(()) => return Ok(true),
+                        Err(e) => last = e,
+                    }
+                }
+                eprintln!(
+                    "[logscout] output `{}`: write failed after {RETRY_ATTEMPTS} retries, line dropped: {last}",
+                    self.name
+                );
+                Ok(false)
+            }
+
+            OnError::Block => {
+                eprintln!(
+                    "[logscout] output `{}`: write failed, retrying until it recovers: {err}",
+                    self.name
+                );
+                let mut backoff = INITIAL_BACKOFF;
+                while !shutdown.load(Ordering::SeqCst) {
+                    thread::sleep(backoff);
+                    backoff = (backoff * 2).min(MAX_BACKOFF);
+                    if write().is_ok() {
+                        eprintln!("[logscout] output `{}`: recovered", self.name);
+                        return Ok(true);
+                    }
+                }
+                Ok(false)
+            }
+        }
+    }
+}
@@ -1,23 +1,86 @@
 // src/stats.rs
+use crate::filters::FilterDecision;
+use crate::timeutil;
+use serde::Serialize;
+use std::collections::{BTreeMap, BTreeSet, VecDeque};
+use std::process::ExitStatus;
 use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::mpsc::{self, RecvTimeoutError};
+use std::sync::{Arc, Mutex};
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
 
 /// Statistics for processed log lines
 /// total: total lines processed
+/// total_bytes: combined length of all lines processed (without newlines)
 /// included: lines that passed the regex filters
 /// excluded: lines that were regex filtered out
+/// dropped: lines that matched no include pattern
+/// sampled_out: lines that passed the filters but were skipped by sampling
+/// duplicates: lines that passed the filters but repeated a recent one
+/// out_of_range: lines dropped for falling outside `since`/`until`
+/// skipped_empty: empty or whitespace-only lines dropped by `skip_empty_lines`
+/// below_severity: syslog lines dropped for a severity below `min_severity`
+/// queue_waits: times a reader had to wait for room in the queue (and for how long)
+/// per_source: the counts above, broken down by source
+/// exit_statuses: last exit status of each command source
+/// pattern_hits: lines each include/exclude pattern matched (set at the end of a run)
+/// tag_counts: lines given each tag (set at the end of a run)
+/// samples: the first and last few included lines of each source (`summary_samples`)
+/// first_lines: how long after the start each source sent its first line
 #[derive(Debug)]
 pub struct Stats {
     total: AtomicU64,
+    total_bytes: AtomicU64,
     included: AtomicU64,
     excluded: AtomicU64,
+    dropped: AtomicU64,
+    sampled_out: AtomicU64,
+    duplicates: AtomicU64,
+    out_of_range: AtomicU64,
+    skipped_empty: AtomicU64,
+    below_severity: AtomicU64,
+    queue_waits: AtomicU64,
+    queue_wait_micros: AtomicU64,
+    per_source: Mutex<BTreeMap<String, SourceCounts>>,
+    exit_statuses: Mutex<BTreeMap<String, ExitStatus>>,
+    started: Mutex<BTreeSet<String>>,
+    pattern_hits: Mutex<Vec<PatternHits>>,
+    tag_counts: Mutex<Vec<TagCount>>,
+    samples: Mutex<BTreeMap<String, LineSamples>>,
+    created: Instant,
+    first_lines: Mutex<BTreeMap<String, Duration>>,
+}
+
+impl Default for Stats {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl Stats {
     pub fn new() -> Self {
         Self {
             total: AtomicU64::new(0),
+            total_bytes: AtomicU64::new(0),
             included: AtomicU64::new(0),
             excluded: AtomicU64::new(0),
+            dropped: AtomicU64::new(0),
+            sampled_out: AtomicU64::new(0),
+            duplicates: AtomicU64::new(0),
+            out_of_range: AtomicU64::new(0),
+            skipped_empty: AtomicU64::new(0),
+            below_severity: AtomicU64::new(0),
+            queue_waits: AtomicU64::new(0),
+            queue_wait_micros: AtomicU64::new(0),
+            per_source: Mutex::new(BTreeMap::new()),
+            exit_statuses: Mutex::new(BTreeMap::new()),
+            started: Mutex::new(BTreeSet::new()),
+            pattern_hits: Mutex::new(Vec::new()),
+            tag_counts: Mutex::new(Vec::new()),
+            samples: Mutex::new(BTreeMap::new()),
+            created: Instant::now(),
+            first_lines: Mutex::new(BTreeMap::new()),
         }
     }
 
@@ -25,6 +88,14 @@ impl Stats {
         self.total.fetch_add(1, Ordering::Relaxed);
     }
 
+    pub fn add_bytes(&self, n: u64) {
+        self.total_bytes.fetch_add(n, Ordering::Relaxed);
+    }
+
+    pub fn total_bytes(&self) -> u64 {
+        self.total_bytes.load(Ordering::Relaxed)
+    }
+
     pub fn inc_included(&self) {
         self.included.fetch_add(1, Ordering::Relaxed);
     }
@@ -33,6 +104,79 @@ impl Stats {
         self.excluded.fetch_add(1, Ordering::Relaxed);
     }
 
+    pub fn inc_dropped(&self) {
+        self.dropped.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Count a classified line of `bytes` length, read at `at`, towards
+    /// its source.
+    pub fn count_source(&self, source: &str, bytes: u64, at: SystemTime, decision: FilterDecision) {
+        let mut map = self.per_source.lock().unwrap();
+        let counts = map.entry(source.to_string()).or_default();
+        counts.total += 1;
+        counts.bytes += bytes;
+        counts.observe_rate(at);
+        match decision {
+            FilterDecision::Excluded => counts.excluded += 1,
+            FilterDecision::Included | FilterDecision::Passed => counts.included += 1,
+            FilterDecision::DroppedNoIncludeMatch => counts.dropped += 1,
+        }
+    }
+
+    pub fn inc_duplicates(&self) {
+        self.duplicates.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn duplicates(&self) -> u64 {
+        self.duplicates.load(Ordering::Relaxed)
+    }
+
+    pub fn inc_sampled_out(&self) {
+        self.sampled_out.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn sampled_out(&self) -> u64 {
+        self.sampled_out.load(Ordering::Relaxed)
+    }
+
+    pub fn inc_out_of_range(&self) {
+        self.out_of_range.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn out_of_range(&self) -> u64 {
+        self.out_of_range.load(Ordering::Relaxed)
+    }
+
+    pub fn inc_skipped_empty(&self) {
+        self.skipped_empty.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn skipped_empty(&self) -> u64 {
+        self.skipped_empty.load(Ordering::Relaxed)
+    }
+
+    pub fn inc_below_severity(&self) {
+        self.below_severity.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn below_severity(&self) -> u64 {
+        self.below_severity.load(Ordering::Relaxed)
+    }
+
+    pub fn add_queue_wait(&self, waited: Duration) {
+        self.queue_waits.fetch_add(1, Ordering::Relaxed);
+        self.queue_wait_micros
+            .fetch_add(waited.as_micros() as u64, Ordering::Relaxed);
+    }
+
+    /// How many times readers waited for a full queue, and for how long in total.
+    pub fn queue_waits(&self) -> (u64, Duration) {
+        (
+            self.queue_waits.load(Ordering::Relaxed),
+            Duration::from_micros(self.queue_wait_micros.load(Ordering::Relaxed)),
+        )
+    }
+
     pub fn snapshot(&self) -> (u64, u64, u64) {
         (
             self.total.load(Ordering::Relaxed),
@@ -40,4 +184,305 @@ impl Stats {
             self.excluded.load(Ordering::Relaxed),
         )
     }
+
+    /// Final counters in a serializable form (for `--stats-json`).
+    pub fn summary(&self) -> Summary {
+        Summary {
+            total: self.total.load(Ordering::Relaxed),
+            total_bytes: self.total_bytes(),
+            included: self.included.load(Ordering::Relaxed),
+            excluded: self.excluded.load(Ordering::Relaxed),
+            dropped: self.dropped.load(Ordering::Relaxed),
+            per_source: self.per_source.lock().unwrap().clone(),
+            pattern_hits: self.pattern_hits(),
+            tags: self.tag_counts(),
+            first_line_ms: self
+                .first_lines()
+                .into_iter()
+                .map(|(source, after)| (source, after.as_millis() as u64))
+                .collect(),
+        }
+    }
+
+    /// The source that produced the most lines so far (first by name on a tie).
+    pub fn busiest_source(&self) -> Option<(String, SourceCounts)> {
+        let map = self.per_source.lock().unwrap();
+        map.iter()
+            .rev()
+            .max_by_key(|(_, c)| c.total)
+            .map(|(name, c)| (name.clone(), *c))
+    }
+
+    /// Record the exit status of a command source (replacing any earlier one).
+    pub fn set_exit_status(&self, source: &str, status: ExitStatus) {
+        let mut map = self.exit_statuses.lock().unwrap();
+        map.insert(source.to_string(), status);
+    }
+
+    /// Record the final per-pattern hit counts of the filters.
+    pub fn set_pattern_hits(&self, hits: Vec<PatternHits>) {
+        *self.pattern_hits.lock().unwrap() = hits;
+    }
+
+    /// Lines each include/exclude pattern was the first to match.
+    pub fn pattern_hits(&self) -> Vec<PatternHits> {
+        self.pattern_hits.lock().unwrap().clone()
+    }
+
+    /// Record the final per-tag line counts.
+    pub fn set_tag_counts(&self, counts: Vec<TagCount>) {
+        *self.tag_counts.lock().unwrap() = counts;
+    }
+
+    /// Lines given each tag, in config order.
+    pub fn tag_counts(&self) -> Vec<TagCount> {
+        self.tag_counts.lock().unwrap().clone()
+    }
+
+    /// Keep `line` if it is among the first `n` included lines of `source`,
+    /// or for as long as it is among the last `n`.
+    pub fn sample_line(&self, source: &str, line: &str, n: usize) {
+        let mut map = self.samples.lock().unwrap();
+        let samples = map.entry(source.to_string()).or_default();
+        samples.seen += 1;
+        if samples.first.len() < n {
+            samples.first.push(line.to_string());
+            return;
+        }
+        if samples.last.len() == n {
+            samples.last.pop_front();
+        }
+        samples.last.push_back(line.to_string());
+    }
+
+    /// The kept lines of every source, sorted by source name.
+    pub fn samples(&self) -> BTreeMap<String, LineSamples> {
+        self.samples.lock().unwrap().clone()
+    }
+
+    /// Record that a source's reader opened its input and is reading.
+    pub fn mark_started(&self, source: &str) {
+        let mut set = self.started.lock().unwrap();
+        set.insert(source.to_string());
+    }
+
+    /// Whether the reader of `source` has started (see `mark_started`).
+    pub fn has_started(&self, source: &str) -> bool {
+        self.started.lock().unwrap().contains(source)
+    }
+
+    /// Record that `source` sent its first line now, unless it did before.
+    pub fn mark_first_line(&self, source: &str) {
+        let after = self.created.elapsed();
+        let mut map = self.first_lines.lock().unwrap();
+        map.entry(source.to_string()).or_insert(after);
+    }
+
+    /// How long after the start each source sent its first line, sorted
+    /// by source name. Sources that sent none are missing.
+    pub fn first_lines(&self) -> BTreeMap<String, Duration> {
+        self.first_lines.lock().unwrap().clone()
+    }
+
+    /// Last exit status per command source, sorted by source name.
+    pub fn exit_statuses(&self) -> Vec<(String, ExitStatus)> {
+        let map = self.exit_statuses.lock().unwrap();
+        map.iter().map(|(k, v)| (k.clone(), *v)).collect()
+    }
+}
+
+/// The first and last few included lines of a source.
+#[derive(Debug, Default, Clone)]
+pub struct LineSamples {
+    pub first: Vec<String>,
+    /// Lines after `first`, oldest first; never overlaps it.
+    pub last: VecDeque<String>,
+    /// Every line offered, kept or not.
+    pub seen: u64,
+}
+
+impl LineSamples {
+    /// Lines between `first` and `last` that were not kept.
+    pub fn skipped(&self) -> u64 {
+        self.seen - (self.first.len() + self.last.len()) as u64
+    }
+}
+
+/// Line counts for a single source. `included` counts every line that
+/// passed the filters, including any later skipped by sampling.
+#[derive(Debug, Default, Clone, Copy, Serialize)]
+pub struct SourceCounts {
+    pub total: u64,
+    pub bytes: u64,
+    pub included: u64,
+    pub excluded: u64,
+    pub dropped: u64,
+    /// Most lines read within any one-second span (by the wall clock,
+    /// sliding in steps of 100 ms).
+    pub peak_per_sec: u64,
+    /// Start of that span, in milliseconds since the UNIX epoch.
+    pub peak_at_ms: u64,
+    /// Lines per 100 ms slot of the last second, indexed by slot number
+    /// modulo `RATE_SLOTS`.
+    #[serde(skip)]
+    slots: [u64; RATE_SLOTS],
+    /// The newest slot counted (100 ms units since the epoch).
+    #[serde(skip)]
+    slot: u64,
+    /// Sum of `slots`: the lines read in the last second.
+    #[serde(skip)]
+    window_lines: u64,
+}
+
+/// Slots of `SLOT_MS` in the one-second rate window.
+const RATE_SLOTS: usize = 10;
+const SLOT_MS: u64 = 100;
+
+impl SourceCounts {
+    /// When the peak span started, as an RFC 3339 UTC time.
+    pub fn peak_at(&self) -> String {
+        timeutil::rfc3339(UNIX_EPOCH + Duration::from_millis(self.peak_at_ms))
+    }
+
+    /// Count a line read at `at` in the sliding one-second window, raising
+    /// the peak as soon as the window exceeds it. A line stamped before the
+    /// newest slot (the clock went back) counts in the newest slot.
+    fn observe_rate(&mut self, at: SystemTime) {
+        let ms = at
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |d| d.as_millis() as u64);
+        let slot = (ms / SLOT_MS).max(self.slot);
+        // Empty the slots that fell out of the window
+        let expired = (slot - self.slot).min(RATE_SLOTS as u64);
+        for n in 1..=expired {
+            let i = ((self.slot + n) % RATE_SLOTS as u64) as usize;
+            self.window_lines -= self.slots[i];
+            self.slots[i] = 0;
+        }
+        self.slot = slot;
+
+        self.slots[(slot % RATE_SLOTS as u64) as usize] += 1;
+        self.window_lines += 1;
+        if self.window_lines > self.peak_per_sec {
+            self.peak_per_sec = self.window_lines;
+            self.peak_at_ms = ((slot + 1) * SLOT_MS).saturating_sub(1000);
+        }
+    }
+}
+
+/// Snapshot of the final statistics, as printed by `--stats-json`.
+#[derive(Debug, Clone, Serialize)]
+pub struct Summary {
+    pub total: u64,
+    pub total_bytes: u64,
+    pub included: u64,
+    pub excluded: u64,
+    pub dropped: u64,
+    pub per_source: BTreeMap<String, SourceCounts>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub pattern_hits: Vec<PatternHits>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub tags: Vec<TagCount>,
+    /// Milliseconds from the start to each source's first line.
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    pub first_line_ms: BTreeMap<String, u64>,
+}
+
+/// How many lines one include or exclude pattern was the first to match.
+#[derive(Debug, Clone, Serialize)]
+pub struct PatternHits {
+    /// `include`, `include_all` or `exclude`.
+    pub list: &'static str,
+    pub pattern: String,
+    pub hits: u64,
+}
+
+/// How many lines were given one tag.
+#[derive(Debug, Clone, Serialize)]
+pub struct TagCount {
+    pub tag: String,
+    pub lines: u64,
+}
+
+/// Background thread printing a rolling stats snapshot to stderr.
+/// Stops (and is joined) when dropped.
+pub struct Reporter {
+    stop: Option<mpsc::Sender<()>>,
+    handle: Option<JoinHandle<()>>,
+}
+
+impl Reporter {
+    pub fn spawn(stats: Arc<Stats>, interval: Duration) -> Self {
+        let (stop, stopped) = mpsc::channel::<()>();
+
+        let handle = thread::spawn(move || {
+            let mut last_total = stats.snapshot().0;
+            let mut last_bytes = stats.total_bytes();
+            let mut last_at = Instant::now();
+
+            // Any message or a dropped sender means "stop"
+            while let Err(RecvTimeoutError::Timeout) = stopped.recv_timeout(interval) {
+                let (total, included, excluded) = stats.snapshot();
+                let bytes = stats.total_bytes();
+                let elapsed = last_at.elapsed().as_secs_f64();
+                let delta = total - last_total;
+                let (rate, byte_rate) = if elapsed > 0.0 {
+                    (
+                        delta as f64 / elapsed,
+                        (bytes - last_bytes) as f64 / elapsed,
+                    )
+                } else {
+                    (0.0, 0.0)
+                };
+
+                eprintln!(
+                    "[logscout] stats: {:.1} lines/s, {:.0} bytes/s (+{} in {:.1}s), total {}, included {}, excluded {}",
+                    rate, byte_rate, delta, elapsed, total, included, excluded
+                );
+
+                last_total = total;
+                last_bytes = bytes;
+                last_at = Instant::now();
+            }
+        });
+
+        Self {
+            stop: Some(stop),
+            handle: Some(handle),
+        }
+    }
+}
+
+impl Drop for Reporter {
+    fn drop(&mut self) {
+        drop(self.stop.take());
+        if let Some(h) = self.handle.take() {
+            let _ = h.join();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn at(ms: u64) -> SystemTime {
+        UNIX_EPOCH + Duration::from_millis(ms)
+    }
+
+    #[test]
+    fn peak_rate_slides_across_second_boundaries() {
+        let mut counts = SourceCounts::default();
+        // 10 lines between 10.6 s and 11.5 s: 5 in each clock second
+        for ms in (10_600..11_600).step_by(100) {
+            counts.observe_rate(at(ms));
+        }
+        assert_eq!(counts.peak_per_sec, 10);
+        assert_eq!(counts.peak_at_ms, 10_600);
+
+        // A second later the window has emptied again
+        counts.observe_rate(at(13_000));
+        assert_eq!(counts.window_lines, 1);
+        assert_eq!(counts.peak_per_sec, 10);
+    }
 }
@@ -0,0 +1,296 @@
+// src/syslog.rs
+//! Writing emitted lines to the local syslog daemon (`destination: syslog`),
+//! and reading the severity of syslog lines (`min_severity`).
+
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+
+/// Syslog facility of the messages (`output.facility`).
+#[derive(Debug, Deserialize, Serialize, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum Facility {
+    Kern,
+    #[default]
+    User,
+    Mail,
+    Daemon,
+    Auth,
+    Syslog,
+    Lpr,
+    News,
+    Uucp,
+    Cron,
+    Authpriv,
+    Ftp,
+    Local0,
+    Local1,
+    Local2,
+    Local3,
+    Local4,
+    Local5,
+    Local6,
+    Local7,
+}
+
+impl Facility {
+    /// The numeric facility, as used in `<PRI>`.
+    pub fn code(self) -> u8 {
+        match self {
+            Facility::Kern => 0,
+            Facility::User => 1,
+            Facility::Mail => 2,
+            Facility::Daemon => 3,
+            Facility::Auth => 4,
+            Facility::Syslog => 5,
+            Facility::Lpr => 6,
+            Facility::News => 7,
+            Facility::Uucp => 8,
+            Facility::Cron => 9,
+            Facility::Authpriv => 10,
+            Facility::Ftp => 11,
+            Facility::Local0 => 16,
+            Facility::Local1 => 17,
+            Facility::Local2 => 18,
+            Facility::Local3 => 19,
+            Facility::Local4 => 20,
+            Facility::Local5 => 21,
+            Facility::Local6 => 22,
+            Facility::Local7 => 23,
+        }
+    }
+}
+
+/// Syslog severity given to every message (`output.severity`), or the
+/// lowest one kept (`min_severity`). Ordered from most to least severe.
+#[derive(Debug, Deserialize, Serialize, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord)]
+#[serde(rename_all = "snake_case")]
+pub enum Severity {
+    Emerg,
+    Alert,
+    Crit,
+    Err,
+    Warning,
+    Notice,
+    #[default]
+    Info,
+    Debug,
+}
+
+impl Severity {
+    /// The severity of a `<PRI>` value (`facility * 8 + severity`).
+    pub fn from_pri(pri: u8) -> Self {
+        match pri % 8 {
+            0 => Severity::Emerg,
+            1 => Severity::Alert,
+            2 => Severity::Crit,
+            3 => Severity::Err,
+            4 => Severity::Warning,
+            5 => Severity::Notice,
+            6 => Severity::Info,
+            _ => Severity::Debug,
+        }
+    }
+}
+
+/// The severity of a syslog line, decoded from the `<PRI>` it starts with
+/// (as in `<34>Oct 11 22:14:15 host su: ...`). `None` without a valid one.
+pub fn severity(line: &str) -> Option<Severity> {
+    let rest = line.strip_prefix('<')?;
+    let end = rest.find('>')?;
+    let digits = &rest[..end];
+    if digits.is_empty() || digits.len() > 3 || !digits.bytes().all(|b| b.is_ascii_digit()) {
+        return None;
+    }
+    // The highest facility is 23 (local7)
+    let pri: u8 = digits.parse().ok().filter(|&pri| pri <= 191)?;
+    Some(Severity::from_pri(pri))
+}
+
+/// Message layout (`output.syslog_format`).
+#[derive(Debug, Deserialize, Serialize, Clone, Copy, Default, PartialEq, Eq)]
+pub enum SyslogFormat {
+    /// `<PRI>tag[pid]: line`; the daemon adds the time and host.
+    #[default]
+    #[serde(rename = "rfc3164")]
+    Rfc3164,
+
+    /// `<PRI>1 time host tag pid - - line`.
+    #[serde(rename = "rfc5424")]
+    Rfc5424,
+}
+
+/// The default socket: `/dev/log`, or `/var/run/syslog` (macOS) if only
+/// that exists.
+pub fn default_socket() -> PathBuf {
+    let dev_log = PathBuf::from("/dev/log");
+    let darwin = PathBuf::from("/var/run/syslog");
+    if !dev_log.exists() && darwin.exists() {
+        darwin
+    } else {
+        dev_log
+    }
+}
+
+#[cfg(unix)]
+pub use writer::SyslogWriter;
+
+#[cfg(unix)]
+mod writer {
+    use super::SyslogFormat;
+    use crate::output::OutputConfig;
+    use crate::timeutil;
+    use std::fs;
+    use std::io::{self, Write};
+    use std::os::unix::net::UnixDatagram;
+    use std::path::PathBuf;
+    use std::time::SystemTime;
+
+    /// Sends each `write` as one message to the local syslog daemon,
+    /// without its trailing newline. Used as the main output for
+    /// `destination: syslog`, behind a `BufferedWriter`, which writes one
+    /// record per call, so a multiline record stays one message.
+    pub struct SyslogWriter {
+        socket: UnixDatagram,
+        path: PathBuf,
+        format: SyslogFormat,
+        /// `<PRI>` with the tag and PID (RFC 3164), or just `<PRI>1`
+        /// (RFC 5424, where the time comes next).
+        header: String,
+        /// Host, tag and PID, after the time (RFC 5424 only).
+        origin: String,
+    }
+
+    impl SyslogWriter {
+        /// Connect to the socket and facility given by `cfg`.
+        pub fn connect(cfg: &OutputConfig) -> io::Result<Self> {
+            let path = cfg
+                .syslog_socket
+                .clone()
+                .unwrap_or_else(super::default_socket);
+            let socket = UnixDatagram::unbound()?;
+            socket.connect(&path)?;
+
+            let facility = cfg.facility.unwrap_or_default();
+            let severity = cfg.severity.unwrap_or_default();
+            let pri = facility.code() * 8 + severity as u8;
+            let tag = "logscout";
+            let pid = std::process::id();
+            let (header, origin) = match cfg.syslog_format {
+                SyslogFormat::Rfc3164 => (format!("<{pri}>{tag}[{pid}]: "), String::new()),
+                SyslogFormat::Rfc5424 => {
+                    let host = fs::read_to_string("/proc/sys/kernel/hostname")
+                        .map(|h| h.trim().to_string())
+                        .ok()
+                        .filter(|h| !h.is_empty())
+                        .unwrap_or_else(|| "-".into());
+                    (format!("<{pri}>1 "), format!(" {host} {tag} {pid} - - "))
+                }
+            };
+
+            Ok(Self {
+                socket,
+                path,
+                format: cfg.syslog_format,
+                header,
+                origin,
+            })
+        }
+
+        fn send(&mut self, line: &[u8]) -> io::Result<()> {
+            let mut message = self.header.clone().into_bytes();
+            if self.format == SyslogFormat::Rfc5424 {
+                message.extend_from_slice(timeutil::rfc3339(SystemTime::now()).as_bytes());
+                message.extend_from_slice(self.origin.as_bytes());
+            }
+            message.extend_from_slice(line);
+
+            if self.socket.send(&message).is_ok() {
+                return Ok(());
+            }
+            // The daemon may have restarted and recreated its socket
+            self.socket = UnixDatagram::unbound()?;
+            self.socket.connect(&self.path)?;
+            self.socket.send(&message).map(drop)
+        }
+    }
+
+    impl Write for SyslogWriter {
+        /// Send `buf` as one message. Nothing is taken if sending fails.
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            self.send(buf.strip_suffix(b"\n").unwrap_or(buf))?;
+            Ok(buf.len())
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+    use crate::output::{BufferedWriter, Destination, OutputConfig};
+    use crate::sink_policy::{OnError, SinkGuard};
+    use std::fs;
+    use std::os::unix::net::UnixDatagram;
+    use std::sync::atomic::AtomicBool;
+    use std::thread;
+    use std::time::Duration;
+
+    /// Every message waiting on `socket`.
+    fn received(socket: &UnixDatagram) -> Vec<String> {
+        socket.set_nonblocking(true).unwrap();
+        let mut buf = [0; 1024];
+        let mut messages = Vec::new();
+        while let Ok(n) = socket.recv(&mut buf) {
+            messages.push(String::from_utf8_lossy(&buf[..n]).into_owned());
+        }
+        messages
+    }
+
+    #[test]
+    fn a_failed_send_is_retried_once_per_record() {
+        let dir = std::env::temp_dir().join(format!("logscout-syslog-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("log.sock");
+        let daemon = UnixDatagram::bind(&path).unwrap();
+
+        let cfg = OutputConfig {
+            destination: Destination::Syslog,
+            syslog_socket: Some(path.clone()),
+            flush_lines: 1,
+            ..OutputConfig::default()
+        };
+        let mut out = BufferedWriter::new(SyslogWriter::connect(&cfg).unwrap(), &cfg);
+        let header = format!("<14>logscout[{}]: ", std::process::id());
+        out.write_line("first").unwrap();
+        assert_eq!(received(&daemon), [format!("{header}first")]);
+
+        // The daemon goes away, and is back before the first retry
+        drop(daemon);
+        fs::remove_file(&path).unwrap();
+        out.push_line("second");
+        out.push_line("panic: boom\n  at main.rs:1");
+        let restarted = thread::spawn({
+            let path = path.clone();
+            move || {
+                thread::sleep(Duration::from_millis(10));
+                UnixDatagram::bind(&path).unwrap()
+            }
+        });
+        let mut guard = SinkGuard::new("output", OnError::Retry);
+        assert!(guard.run(&AtomicBool::new(false), || out.flush()).unwrap());
+
+        // Each record once, the multiline one as a single message
+        assert_eq!(
+            received(&restarted.join().unwrap()),
+            [
+                format!("{header}second"),
+                format!("{header}panic: boom\n  at main.rs:1")
+            ]
+        );
+        let _ = fs::remove_dir_all(&dir);
+    }
+}
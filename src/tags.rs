@@ -0,0 +1,78 @@
+// src/tags.rs
+use crate::config::ConfigError;
+use crate::filters;
+use crate::stats::TagCount;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicU64, Ordering};
+
+/// One tagging rule: lines matching `pattern` get the tag `name`.
+#[derive(Debug, Deserialize, Serialize, Clone, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct TagRule {
+    pub name: String,
+    pub pattern: String,
+}
+
+/// Tags each line with the first rule that matches it.
+#[derive(Debug)]
+pub struct Tagger {
+    rules: Vec<(Arc<str>, Regex)>,
+    /// Lines each tag was given.
+    counts: Vec<AtomicU64>,
+}
+
+impl Tagger {
+    /// Compile the tag rules. Returns `None` if there are none.
+    pub fn from_config(rules: &[TagRule], size_limit: usize) -> Result<Option<Self>, ConfigError> {
+        let mut compiled: Vec<(Arc<str>, Regex)> = Vec::new();
+        for rule in rules {
+            if rule.name.is_empty() {
+                return Err(ConfigError::Invalid("tags: name must not be empty.".into()));
+            }
+            if compiled.iter().any(|(name, _)| **name == rule.name) {
+                return Err(ConfigError::Invalid(format!(
+                    "tags: duplicate tag `{}`.",
+                    rule.name
+                )));
+            }
+            let re = filters::build_regex(&rule.pattern, "tag", size_limit)?;
+            compiled.push((Arc::from(rule.name.as_str()), re));
+        }
+
+        Ok((!compiled.is_empty()).then(|| Self {
+            counts: compiled.iter().map(|_| AtomicU64::new(0)).collect(),
+            rules: compiled,
+        }))
+    }
+
+    /// The tag of the first rule matching `line`, counting it.
+    pub fn tag(&self, line: &str) -> Option<Arc<str>> {
+        let i = self.rules.iter().position(|(_, re)| re.is_match(line))?;
+        self.counts[i].fetch_add(1, Ordering::Relaxed);
+        Some(self.rules[i].0.clone())
+    }
+
+    /// Add the counts of `old` to the same tags here, so the counts of
+    /// tags kept across a reload carry over.
+    pub fn carry_counts_from(&self, old: &Tagger) {
+        for ((name, _), count) in self.rules.iter().zip(&self.counts) {
+            if let Some(i) = old.rules.iter().position(|(old_name, _)| old_name == name) {
+                count.fetch_add(old.counts[i].load(Ordering::Relaxed), Ordering::Relaxed);
+            }
+        }
+    }
+
+    /// How many lines got each tag, in config order.
+    pub fn counts(&self) -> Vec<TagCount> {
+        self.rules
+            .iter()
+            .zip(&self.counts)
+            .map(|((name, _), count)| TagCount {
+                tag: name.to_string(),
+                lines: count.load(Ordering::Relaxed),
+            })
+            .collect()
+    }
+}
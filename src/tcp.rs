@@ -0,0 +1,161 @@
+// src/tcp.rs
+use serde::{Deserialize, Serialize};
+use std::io::{self, ErrorKind, Read};
+use std::net::{SocketAddr, TcpListener, TcpStream};
+use std::path::PathBuf;
+use std::sync::{
+    Arc,
+    atomic::{AtomicBool, Ordering},
+};
+use std::thread;
+use std::time::Duration;
+
+#[derive(Debug, Deserialize, Serialize, Clone, PartialEq, Eq)]
+#[serde(deny_unknown_fields)]
+pub struct TlsConfig {
+    /// PEM file with the server certificate, followed by any intermediates.
+    pub cert: PathBuf,
+
+    /// PEM file with the certificate's private key.
+    pub key: PathBuf,
+}
+
+#[cfg(feature = "tls")]
+impl TlsConfig {
+    /// Load the certificate and key into a rustls server config.
+    pub fn load(&self) -> io::Result<Arc<rustls::ServerConfig>> {
+        use rustls::pki_types::pem::PemObject;
+        use rustls::pki_types::{CertificateDer, PrivateKeyDer};
+
+        let pem_err =
+            |path: &PathBuf, e: String| io::Error::other(format!("`{}`: {e}", path.display()));
+        let certs = CertificateDer::pem_file_iter(&self.cert)
+            .and_then(|certs| certs.collect::<Result<Vec<_>, _>>())
+            .map_err(|e| pem_err(&self.cert, e.to_string()))?;
+        if certs.is_empty() {
+            return Err(pem_err(&self.cert, "no certificate found".into()));
+        }
+        let key = PrivateKeyDer::from_pem_file(&self.key)
+            .map_err(|e| pem_err(&self.key, e.to_string()))?;
+
+        let provider = Arc::new(rustls::crypto::ring::default_provider());
+        let config = rustls::ServerConfig::builder_with_provider(provider)
+            .with_safe_default_protocol_versions()
+            .and_then(|b| b.with_no_client_auth().with_single_cert(certs, key))
+            .map_err(io::Error::other)?;
+        Ok(Arc::new(config))
+    }
+}
+
+/// How often the accept loop checks whether to stop.
+const ACCEPT_POLL: Duration = Duration::from_millis(100);
+
+/// How often a connection waiting for data checks whether to stop.
+const READ_POLL: Duration = Duration::from_millis(200);
+
+/// A TCP listener for a `tcp` source, serving TLS if configured.
+pub(crate) struct Listener {
+    inner: TcpListener,
+    #[cfg(feature = "tls")]
+    tls: Option<Arc<rustls::ServerConfig>>,
+}
+
+impl Listener {
+    pub fn bind(addr: &str, tls: Option<&TlsConfig>) -> io::Result<Self> {
+        #[cfg(not(feature = "tls"))]
+        if tls.is_some() {
+            return Err(io::Error::other("built without TLS support"));
+        }
+        #[cfg(feature = "tls")]
+        let tls = tls.map(TlsConfig::load).transpose()?;
+
+        let inner = TcpListener::bind(addr)?;
+        inner.set_nonblocking(true)?;
+        Ok(Self {
+            inner,
+            #[cfg(feature = "tls")]
+            tls,
+        })
+    }
+
+    /// Wait for the next connection, or `None` once `done()`. Reads from
+    /// the connection end (as at EOF) once `stop` is set.
+    pub fn accept(
+        &self,
+        stop: &Arc<AtomicBool>,
+        done: impl Fn() -> bool,
+    ) -> Option<(Connection, SocketAddr)> {
+        while !done() {
+            match self.inner.accept() {
+                Ok((stream, peer)) => match self.connection(stream, stop) {
+                    Ok(conn) => return Some((conn, peer)),
+                    Err(e) => {
+                        eprintln!("[logscout] tcp: failed to set up connection from {peer}: {e}")
+                    }
+                },
+                Err(e) if e.kind() == ErrorKind::WouldBlock => thread::sleep(ACCEPT_POLL),
+                Err(e) => {
+                    eprintln!("[logscout] tcp: accept failed: {e}");
+                    thread::sleep(ACCEPT_POLL);
+                }
+            }
+        }
+        None
+    }
+
+    fn connection(&self, stream: TcpStream, stop: &Arc<AtomicBool>) -> io::Result<Connection> {
+        stream.set_nonblocking(false)?;
+        stream.set_read_timeout(Some(READ_POLL))?;
+
+        #[cfg(feature = "tls")]
+        if let Some(tls) = &self.tls {
+            let session = rustls::ServerConnection::new(tls.clone()).map_err(io::Error::other)?;
+            return Ok(Connection {
+                stream: Stream::Tls(Box::new(rustls::StreamOwned::new(session, stream))),
+                stop: stop.clone(),
+            });
+        }
+
+        Ok(Connection {
+            stream: Stream::Plain(stream),
+            stop: stop.clone(),
+        })
+    }
+}
+
+enum Stream {
+    Plain(TcpStream),
+    #[cfg(feature = "tls")]
+    Tls(Box<rustls::StreamOwned<rustls::ServerConnection, TcpStream>>),
+}
+
+/// One accepted connection, decrypted if it uses TLS.
+pub(crate) struct Connection {
+    stream: Stream,
+    stop: Arc<AtomicBool>,
+}
+
+impl Read for Connection {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        loop {
+            let result = match &mut self.stream {
+                Stream::Plain(s) => s.read(buf),
+                // Forwarders often hang up without a TLS close_notify; treat
+                // that as the end of the stream
+                #[cfg(feature = "tls")]
+                Stream::Tls(s) => match s.read(buf) {
+                    Err(e) if e.kind() == ErrorKind::UnexpectedEof => Ok(0),
+                    other => other,
+                },
+            };
+            match result {
+                Err(e) if matches!(e.kind(), ErrorKind::WouldBlock | ErrorKind::TimedOut) => {
+                    if self.stop.load(Ordering::Relaxed) {
+                        return Ok(0);
+                    }
+                }
+                other => return other,
+            }
+        }
+    }
+}
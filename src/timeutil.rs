@@ -0,0 +1,228 @@
+// src/timeutil.rs
+use std::collections::BTreeMap;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// A calendar date in UTC.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Date {
+    pub year: i64,
+    pub month: u32,
+    pub day: u32,
+}
+
+/// The UTC calendar date of `t`.
+pub fn utc_date(t: SystemTime) -> Date {
+    let secs = match t.duration_since(UNIX_EPOCH) {
+        Ok(d) => d.as_secs() as i64,
+        Err(e) => -(e.duration().as_secs() as i64),
+    };
+    civil_from_days(secs.div_euclid(86_400))
+}
+
+/// `t` as an RFC 3339 UTC timestamp with milliseconds,
+/// e.g. `2024-05-01T12:34:56.789Z`.
+pub fn rfc3339(t: SystemTime) -> String {
+    let millis = match t.duration_since(UNIX_EPOCH) {
+        Ok(d) => d.as_millis() as i64,
+        Err(e) => -(e.duration().as_millis() as i64),
+    };
+    let secs = millis.div_euclid(1000);
+    let date = civil_from_days(secs.div_euclid(86_400));
+    let tod = secs.rem_euclid(86_400);
+
+    format!(
+        "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}.{:03}Z",
+        date.year,
+        date.month,
+        date.day,
+        tod / 3600,
+        tod % 3600 / 60,
+        tod % 60,
+        millis.rem_euclid(1000)
+    )
+}
+
+/// Convert days since 1970-01-01 to a proleptic Gregorian date.
+/// (Howard Hinnant's `civil_from_days` algorithm.)
+fn civil_from_days(days: i64) -> Date {
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z.rem_euclid(146_097); // [0, 146096]
+    let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365; // [0, 399]
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100); // [0, 365]
+    let mp = (5 * doy + 2) / 153; // [0, 11]
+    let day = (doy - (153 * mp + 2) / 5 + 1) as u32; // [1, 31]
+    let month = if mp < 10 { mp + 3 } else { mp - 9 } as u32; // [1, 12]
+    let year = yoe + era * 400 + i64::from(month <= 2);
+
+    Date { year, month, day }
+}
+
+/// Days since 1970-01-01 of a proleptic Gregorian date
+/// (Howard Hinnant's `days_from_civil` algorithm).
+fn days_from_civil(year: i64, month: u32, day: u32) -> i64 {
+    let y = if month <= 2 { year - 1 } else { year };
+    let era = y.div_euclid(400);
+    let yoe = y.rem_euclid(400); // [0, 399]
+    let mp = i64::from((month + 9) % 12); // March = 0
+    let doy = (153 * mp + 2) / 5 + i64::from(day) - 1; // [0, 365]
+    let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy; // [0, 146096]
+    era * 146_097 + doe - 719_468
+}
+
+/// A time given as calendar fields plus a UTC offset in seconds.
+fn to_system_time(date: Date, hms: (u32, u32, u32), nanos: u32, offset: i64) -> Option<SystemTime> {
+    let (h, m, s) = hms;
+    if !(1..=12).contains(&date.month)
+        || !(1..=31).contains(&date.day)
+        || h > 23
+        || m > 59
+        || s > 60
+    {
+        return None;
+    }
+    let secs = days_from_civil(date.year, date.month, date.day) * 86_400
+        + i64::from(h * 3600 + m * 60 + s)
+        - offset;
+    let secs = u64::try_from(secs).ok()?;
+    Some(UNIX_EPOCH + Duration::new(secs, nanos))
+}
+
+/// Parse exactly `n` ASCII digits at the start of `s`.
+fn digits(s: &str, n: usize) -> Option<u32> {
+    let d = s.get(..n)?;
+    if !d.bytes().all(|b| b.is_ascii_digit()) {
+        return None;
+    }
+    d.parse().ok()
+}
+
+/// A `+hh:mm`, `+hhmm`, `-...` or `Z` offset in seconds; missing means UTC.
+fn utc_offset(s: &str) -> Option<i64> {
+    let sign = match s.as_bytes().first() {
+        None => return Some(0),
+        Some(b'Z' | b'z') => return Some(0),
+        Some(b'+') => 1,
+        Some(b'-') => -1,
+        Some(_) => return Some(0), // no zone, followed by other text
+    };
+    let h = digits(&s[1..], 2)?;
+    let rest = s[3..].strip_prefix(':').unwrap_or(&s[3..]);
+    let m = digits(rest, 2)?;
+    Some(sign * i64::from(h * 3600 + m * 60))
+}
+
+/// Parse an RFC 3339 / ISO 8601 timestamp at the start of `s`, such as
+/// `2024-05-01T12:34:56.789Z` or `2024-05-01 12:34:56+02:00`. Text after
+/// the timestamp is ignored; a missing zone means UTC.
+pub fn parse_iso8601(s: &str) -> Option<SystemTime> {
+    let year = digits(s, 4)?;
+    let month = digits(s.get(5..)?, 2)?;
+    let day = digits(s.get(8..)?, 2)?;
+    if s.as_bytes().get(4) != Some(&b'-') || s.as_bytes().get(7) != Some(&b'-') {
+        return None;
+    }
+    if !matches!(s.as_bytes().get(10), Some(b'T' | b't' | b' ')) {
+        return None;
+    }
+    let time = s.get(11..)?;
+    let h = digits(time, 2)?;
+    let m = digits(time.get(3..)?, 2)?;
+    let sec = digits(time.get(6..)?, 2)?;
+    if time.as_bytes().get(2) != Some(&b':') || time.as_bytes().get(5) != Some(&b':') {
+        return None;
+    }
+
+    // Optional fraction, up to nanoseconds
+    let mut rest = &time[8..];
+    let mut nanos = 0;
+    if let Some(frac) = rest.strip_prefix(['.', ',']) {
+        let len = frac.bytes().take_while(u8::is_ascii_digit).count();
+        if len == 0 {
+            return None;
+        }
+        let kept = &frac[..len.min(9)];
+        nanos = kept.parse::<u32>().ok()? * 10u32.pow(9 - kept.len() as u32);
+        rest = &frac[len..];
+    }
+
+    let date = Date {
+        year: i64::from(year),
+        month,
+        day,
+    };
+    to_system_time(date, (h, m, sec), nanos, utc_offset(rest)?)
+}
+
+/// Parse a Common Log Format time such as `10/Oct/2000:13:55:36 -0700`.
+pub fn parse_clf(s: &str) -> Option<SystemTime> {
+    const MONTHS: [&str; 12] = [
+        "Jan", "Feb", "Mar", "Apr", "May", "Jun", "Jul", "Aug", "Sep", "Oct", "Nov", "Dec",
+    ];
+    let day = digits(s, 2)?;
+    let month = MONTHS.iter().position(|m| s.get(3..6) == Some(*m))? as u32 + 1;
+    let year = digits(s.get(7..)?, 4)?;
+    let h = digits(s.get(12..)?, 2)?;
+    let m = digits(s.get(15..)?, 2)?;
+    let sec = digits(s.get(18..)?, 2)?;
+    let offset = utc_offset(s.get(21..).unwrap_or(""))?;
+
+    let date = Date {
+        year: i64::from(year),
+        month,
+        day,
+    };
+    to_system_time(date, (h, m, sec), 0, offset)
+}
+
+/// Seconds (or milliseconds, for values past the year 33658) since the
+/// UNIX epoch, as found in JSON logs.
+pub fn parse_epoch(s: &str) -> Option<SystemTime> {
+    let value: f64 = s.parse().ok()?;
+    if !value.is_finite() || value < 0.0 {
+        return None;
+    }
+    let secs = if value >= 1e12 { value / 1000.0 } else { value };
+    Some(UNIX_EPOCH + Duration::from_secs_f64(secs))
+}
+
+/// Fields that may hold the time of a parsed line, in order of preference.
+const TIME_FIELDS: &[&str] = &["time", "timestamp", "@timestamp", "ts"];
+
+/// When the event behind a line happened: from a `time`, `timestamp`,
+/// `@timestamp` or `ts` field (CLF, RFC 3339 or epoch seconds/millis), or
+/// else an RFC 3339 timestamp at the start of the line (optionally in `[]`).
+pub fn event_time(line: &str, fields: &BTreeMap<String, String>) -> Option<SystemTime> {
+    for name in TIME_FIELDS {
+        if let Some(value) = fields.get(*name) {
+            let parsed = parse_clf(value)
+                .or_else(|| parse_iso8601(value))
+                .or_else(|| parse_epoch(value));
+            if parsed.is_some() {
+                return parsed;
+            }
+        }
+    }
+    parse_iso8601(line.strip_prefix('[').unwrap_or(line))
+}
+
+/// The `since`/`until` range lines' event times must fall in.
+#[derive(Debug, Clone, Copy)]
+pub struct TimeRange {
+    /// Earliest time kept (inclusive).
+    pub since: Option<SystemTime>,
+    /// Latest time kept (exclusive).
+    pub until: Option<SystemTime>,
+    /// Whether lines without a parseable time are kept.
+    pub keep_untimed: bool,
+}
+
+impl TimeRange {
+    /// Whether a line with event time `t` is kept.
+    pub fn contains(&self, t: Option<SystemTime>) -> bool {
+        match t {
+            Some(t) => self.since.is_none_or(|s| t >= s) && self.until.is_none_or(|u| t < u),
+            None => self.keep_untimed,
+        }
+    }
+}
@@ -0,0 +1,286 @@
+// src/webhook.rs
+use crate::config::ConfigError;
+use serde::{Deserialize, Serialize};
+use std::io::{self, BufRead, BufReader, Write};
+use std::net::{TcpStream, ToSocketAddrs};
+use std::sync::{
+    Arc,
+    atomic::{AtomicBool, Ordering},
+    mpsc::{self, RecvTimeoutError, SyncSender, TrySendError},
+};
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant};
+
+#[derive(Debug, Deserialize, Serialize, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct WebhookConfig {
+    /// Endpoint to POST batches to, e.g. `http://alerts.local:8080/hook`.
+    pub url: String,
+
+    /// Send a batch once this many lines are waiting.
+    #[serde(default = "default_batch_size")]
+    pub batch_size: usize,
+
+    /// Send waiting lines at least this often (milliseconds).
+    #[serde(default = "default_flush_interval_ms")]
+    pub flush_interval_ms: u64,
+
+    /// Extra attempts for a batch after a network error, 429 or 5xx.
+    #[serde(default = "default_retries")]
+    pub retries: u32,
+
+    /// Lines waiting to be sent; further lines are dropped while it is full.
+    #[serde(default = "default_queue_capacity")]
+    pub queue_capacity: usize,
+}
+
+fn default_batch_size() -> usize {
+    50
+}
+
+fn default_flush_interval_ms() -> u64 {
+    1000
+}
+
+fn default_retries() -> u32 {
+    3
+}
+
+fn default_queue_capacity() -> usize {
+    10_000
+}
+
+impl WebhookConfig {
+    pub fn validate(&self) -> Result<(), ConfigError> {
+        Endpoint::parse(&self.url)?;
+        if self.batch_size == 0 || self.flush_interval_ms == 0 || self.queue_capacity == 0 {
+            return Err(ConfigError::Invalid(
+                "output.webhook: batch_size, flush_interval_ms and queue_capacity must be greater than zero."
+                    .into(),
+            ));
+        }
+        Ok(())
+    }
+}
+
+/// Timeout for connecting, writing a batch, and reading the response.
+const HTTP_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Delay before the first retry; doubled for every further one.
+const RETRY_BACKOFF: Duration = Duration::from_millis(200);
+
+/// Where batches are sent, split out of an `http://` URL.
+#[derive(Debug, Clone)]
+struct Endpoint {
+    /// `host:port` to connect to and to send as `Host`.
+    authority: String,
+    path: String,
+}
+
+impl Endpoint {
+    fn parse(url: &str) -> Result<Self, ConfigError> {
+        let invalid =
+            |why: &str| ConfigError::Invalid(format!("output.webhook.url `{url}`: {why}"));
+
+        let Some(rest) = url.strip_prefix("http://") else {
+            return Err(invalid("only http:// URLs are supported"));
+        };
+        let (authority, path) = match rest.find(['/', '?']) {
+            Some(i) if rest[i..].starts_with('?') => (&rest[..i], format!("/{}", &rest[i..])),
+            Some(i) => (&rest[..i], rest[i..].to_string()),
+            None => (rest, "/".to_string()),
+        };
+        if authority.is_empty() {
+            return Err(invalid("missing host"));
+        }
+
+        // Default the port so the address can be resolved
+        let has_port = authority
+            .rsplit_once(':')
+            .is_some_and(|(_, port)| !port.is_empty() && !port.contains(']'));
+        let authority = if has_port {
+            authority.to_string()
+        } else {
+            format!("{authority}:80")
+        };
+
+        Ok(Self { authority, path })
+    }
+}
+
+/// Posts emitted lines to a webhook in JSON array batches.
+///
+/// Lines are handed to a sender thread over a bounded queue, so a slow or
+/// unreachable endpoint never blocks the consumer; when the queue is full,
+/// new lines are dropped (with a warning).
+pub struct Webhook {
+    tx: Option<SyncSender<String>>,
+    handle: Option<JoinHandle<()>>,
+    /// Lines dropped since the queue last had room.
+    dropped: u64,
+}
+
+impl Webhook {
+    pub fn start(cfg: &WebhookConfig, shutdown: Arc<AtomicBool>) -> Result<Self, ConfigError> {
+        let endpoint = Endpoint::parse(&cfg.url)?;
+        let (tx, rx) = mpsc::sync_channel::<String>(cfg.queue_capacity);
+        let cfg = cfg.clone();
+
+        let handle = thread::spawn(move || {
+            let interval = Duration::from_millis(cfg.flush_interval_ms);
+            let mut batch = Vec::new();
+            let mut deadline = Instant::now();
+
+            loop {
+                let next = if batch.is_empty() {
+                    rx.recv().map_err(|_| RecvTimeoutError::Disconnected)
+                } else {
+                    rx.recv_timeout(deadline.saturating_duration_since(Instant::now()))
+                };
+
+                let done = match next {
+                    Ok(record) => {
+                        if batch.is_empty() {
+                            deadline = Instant::now() + interval;
+                        }
+                        batch.push(record);
+                        if batch.len() < cfg.batch_size {
+                            continue;
+                        }
+                        false
+                    }
+                    Err(RecvTimeoutError::Timeout) => false,
+                    Err(RecvTimeoutError::Disconnected) => true,
+                };
+
+                if !batch.is_empty() {
+                    deliver(&endpoint, &batch, cfg.retries, &shutdown);
+                    batch.clear();
+                }
+                if done {
+                    break;
+                }
+            }
+        });
+
+        Ok(Self {
+            tx: Some(tx),
+            handle: Some(handle),
+            dropped: 0,
+        })
+    }
+
+    /// Queue one rendered JSON record without blocking.
+    pub fn send(&mut self, record: String) {
+        let Some(tx) = &self.tx else {
+            return;
+        };
+        match tx.try_send(record) {
+            Ok(()) => {
+                if self.dropped > 0 {
+                    eprintln!(
+                        "[logscout] webhook: queue has room again, {} line(s) were dropped",
+                        self.dropped
+                    );
+                    self.dropped = 0;
+                }
+            }
+            Err(TrySendError::Full(_)) => {
+                if self.dropped == 0 {
+                    eprintln!("[logscout] webhook: endpoint is falling behind, dropping lines");
+                }
+                self.dropped += 1;
+            }
+            // The sender thread is gone (it only exits once we hang up)
+            Err(TrySendError::Disconnected(_)) => self.tx = None,
+        }
+    }
+}
+
+impl Drop for Webhook {
+    /// Send what is still queued, then stop the sender thread.
+    fn drop(&mut self) {
+        drop(self.tx.take());
+        if let Some(h) = self.handle.take() {
+            let _ = h.join();
+        }
+    }
+}
+
+/// POST one batch, retrying transient failures. Gives up (and drops the
+/// batch) after `retries` extra attempts, on a client error, or when
+/// shutting down.
+fn deliver(endpoint: &Endpoint, batch: &[String], retries: u32, shutdown: &AtomicBool) {
+    let body = format!("[{}]", batch.join(","));
+    let mut backoff = RETRY_BACKOFF;
+
+    for attempt in 0..=retries {
+        let error = match post(endpoint, &body) {
+            Ok(status) if (200..300).contains(&status) => return,
+            Ok(status) if (400..500).contains(&status) && status != 429 => {
+                eprintln!(
+                    "[logscout] webhook: endpoint rejected a batch of {} line(s) with status {}, dropping it",
+                    batch.len(),
+                    status
+                );
+                return;
+            }
+            Ok(status) => format!("status {status}"),
+            Err(e) => e.to_string(),
+        };
+
+        if attempt == retries || shutdown.load(Ordering::Relaxed) {
+            eprintln!(
+                "[logscout] webhook: failed to send a batch of {} line(s) ({}), dropping it",
+                batch.len(),
+                error
+            );
+            return;
+        }
+        thread::sleep(backoff);
+        backoff *= 2;
+    }
+}
+
+/// Send `body` as a JSON POST and return the response status code.
+fn post(endpoint: &Endpoint, body: &str) -> io::Result<u16> {
+    let mut last_err = io::Error::new(io::ErrorKind::NotFound, "host did not resolve");
+    let mut stream = None;
+    for addr in endpoint.authority.to_socket_addrs()? {
+        match TcpStream::connect_timeout(&addr, HTTP_TIMEOUT) {
+            Ok(s) => {
+                stream = Some(s);
+                break;
+            }
+            Err(e) => last_err = e,
+        }
+    }
+    let mut stream = stream.ok_or(last_err)?;
+    stream.set_read_timeout(Some(HTTP_TIMEOUT))?;
+    stream.set_write_timeout(Some(HTTP_TIMEOUT))?;
+
+    write!(
+        stream,
+        "POST {} HTTP/1.1\r\nHost: {}\r\nUser-Agent: logscout/{}\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n",
+        endpoint.path,
+        endpoint.authority,
+        env!("CARGO_PKG_VERSION"),
+        body.len()
+    )?;
+    stream.write_all(body.as_bytes())?;
+    stream.flush()?;
+
+    // Only the status line matters, e.g. `HTTP/1.1 204 No Content`
+    let mut status_line = String::new();
+    BufReader::new(stream).read_line(&mut status_line)?;
+    status_line
+        .split_whitespace()
+        .nth(1)
+        .and_then(|code| code.parse().ok())
+        .ok_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("malformed response `{}`", status_line.trim_end()),
+            )
+        })
+}
@@ -0,0 +1,180 @@
+// src/websocket.rs
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Deserialize, Serialize, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct WebSocketConfig {
+    /// Address to listen on, e.g. `127.0.0.1:9001`.
+    pub bind: String,
+
+    /// Number of recent lines sent to a client when it connects.
+    #[serde(default = "default_backlog")]
+    pub backlog: usize,
+}
+
+fn default_backlog() -> usize {
+    100
+}
+
+#[cfg(feature = "websocket")]
+pub use server::WebSocketServer;
+
+#[cfg(feature = "websocket")]
+mod server {
+    use super::WebSocketConfig;
+    use std::collections::VecDeque;
+    use std::io::{self, ErrorKind};
+    use std::net::{TcpListener, TcpStream};
+    use std::sync::{
+        Arc, Mutex,
+        atomic::{AtomicBool, Ordering},
+    };
+    use std::thread;
+    use std::time::Duration;
+    use tungstenite::{Message, WebSocket};
+
+    /// How often the accept loop checks the shutdown flag.
+    const ACCEPT_POLL: Duration = Duration::from_millis(100);
+
+    /// A slow client must not stall the consumer for longer than this.
+    const WRITE_TIMEOUT: Duration = Duration::from_secs(1);
+
+    #[derive(Default)]
+    struct Shared {
+        clients: Vec<WebSocket<TcpStream>>,
+        backlog: VecDeque<String>,
+    }
+
+    /// Broadcasts emitted lines (as JSON) to every connected WebSocket client.
+    pub struct WebSocketServer {
+        shared: Arc<Mutex<Shared>>,
+        backlog: usize,
+    }
+
+    impl WebSocketServer {
+        /// Bind the listener and start accepting clients in the background.
+        pub fn start(cfg: &WebSocketConfig, shutdown: Arc<AtomicBool>) -> io::Result<Self> {
+            let listener = TcpListener::bind(&cfg.bind)?;
+            listener.set_nonblocking(true)?;
+
+            let shared = Arc::new(Mutex::new(Shared::default()));
+            let accept_shared = shared.clone();
+            thread::spawn(move || accept_loop(listener, accept_shared, shutdown));
+
+            Ok(Self {
+                shared,
+                backlog: cfg.backlog,
+            })
+        }
+
+        /// Send a line to every client, dropping the ones that fail.
+        pub fn broadcast(&self, line: &str) {
+            let mut shared = self.shared.lock().unwrap();
+
+            if self.backlog > 0 {
+                if shared.backlog.len() == self.backlog {
+                    shared.backlog.pop_front();
+                }
+                shared.backlog.push_back(line.to_string());
+            }
+
+            shared
+                .clients
+                .retain_mut(|ws| ws.send(Message::text(line)).is_ok());
+        }
+    }
+
+    impl Drop for WebSocketServer {
+        fn drop(&mut self) {
+            let mut shared = self.shared.lock().unwrap();
+            for ws in shared.clients.iter_mut() {
+                let _ = ws.close(None);
+                let _ = ws.flush();
+            }
+            shared.clients.clear();
+        }
+    }
+
+    fn accept_loop(listener: TcpListener, shared: Arc<Mutex<Shared>>, shutdown: Arc<AtomicBool>) {
+        while !shutdown.load(Ordering::Relaxed) {
+            let stream = match listener.accept() {
+                Ok((stream, _)) => stream,
+                Err(e) if e.kind() == ErrorKind::WouldBlock => {
+                    thread::sleep(ACCEPT_POLL);
+                    continue;
+                }
+                Err(e) => {
+                    eprintln!("[logscout] websocket: accept failed: {e}");
+                    thread::sleep(ACCEPT_POLL);
+                    continue;
+                }
+            };
+
+            if let Some(ws) = handshake(stream) {
+                let mut shared = shared.lock().unwrap();
+                let Shared { clients, backlog } = &mut *shared;
+                let mut ws = ws;
+
+                // Catch the new client up before it joins the broadcast
+                let caught_up = backlog
+                    .iter()
+                    .all(|line| ws.send(Message::text(line.as_str())).is_ok());
+                if caught_up {
+                    clients.push(ws);
+                }
+            }
+        }
+    }
+
+    fn handshake(stream: TcpStream) -> Option<WebSocket<TcpStream>> {
+        stream.set_nonblocking(false).ok()?;
+        stream.set_write_timeout(Some(WRITE_TIMEOUT)).ok()?;
+        match tungstenite::accept(stream) {
+            Ok(ws) => Some(ws),
+            Err(e) => {
+                eprintln!("[logscout] websocket: handshake failed: {e}");
+                None
+            }
+        }
+    }
+}
+
+#[cfg(all(test, feature = "websocket"))]
+mod tests {
+    use super::*;
+    use std::net::{TcpListener, TcpStream};
+    use std::sync::Arc;
+    use std::sync::atomic::{AtomicBool, Ordering};
+    use tungstenite::Message;
+
+    #[test]
+    fn clients_receive_broadcast_lines() {
+        // A port that was free a moment ago
+        let port = TcpListener::bind("127.0.0.1:0")
+            .unwrap()
+            .local_addr()
+            .unwrap()
+            .port();
+        let cfg = WebSocketConfig {
+            bind: format!("127.0.0.1:{port}"),
+            backlog: 10,
+        };
+        let shutdown = Arc::new(AtomicBool::new(false));
+        let server = WebSocketServer::start(&cfg, shutdown.clone()).unwrap();
+        server.broadcast(r#"{"line":"before"}"#);
+
+        let stream = TcpStream::connect(("127.0.0.1", port)).unwrap();
+        let (mut client, _) =
+            tungstenite::client(format!("ws://127.0.0.1:{port}/"), stream).unwrap();
+
+        // The backlog arrives first; once it has, the client is registered
+        assert_eq!(
+            client.read().unwrap(),
+            Message::text(r#"{"line":"before"}"#)
+        );
+        server.broadcast(r#"{"line":"after"}"#);
+        assert_eq!(client.read().unwrap(), Message::text(r#"{"line":"after"}"#));
+
+        shutdown.store(true, Ordering::Relaxed);
+    }
+}
@@ -0,0 +1,441 @@
+// src/windows_event.rs
+//! Reading a Windows Event Log channel through the `wevtapi` Evt* API.
+//! Only built with the `windows_event` feature on Windows.
+
+use crate::config::StartAt;
+use std::collections::HashMap;
+use std::ffi::c_void;
+use std::io;
+use std::ptr;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::Duration;
+
+/// Longest a followed channel sleeps before checking for shutdown again.
+const WAIT_INTERVAL: Duration = Duration::from_millis(250);
+
+/// Events fetched per `EvtNext` call.
+const BATCH: usize = 64;
+
+type Handle = isize;
+
+const EVT_SUBSCRIBE_TO_FUTURE_EVENTS: u32 = 1;
+const EVT_SUBSCRIBE_START_AT_OLDEST_RECORD: u32 = 2;
+const EVT_RENDER_CONTEXT_SYSTEM: u32 = 1;
+const EVT_RENDER_EVENT_VALUES: u32 = 0;
+const EVT_RENDER_EVENT_XML: u32 = 1;
+const EVT_FORMAT_MESSAGE_EVENT: u32 = 1;
+
+/// Indexes into the values of an `EVT_RENDER_CONTEXT_SYSTEM` rendering.
+const SYSTEM_PROVIDER_NAME: usize = 0;
+const SYSTEM_EVENT_ID: usize = 2;
+const SYSTEM_LEVEL: usize = 4;
+
+const EVT_VAR_TYPE_STRING: u32 = 1;
+const EVT_VAR_TYPE_BYTE: u32 = 4;
+const EVT_VAR_TYPE_UINT16: u32 = 6;
+
+const ERROR_INSUFFICIENT_BUFFER: i32 = 122;
+const ERROR_NO_MORE_ITEMS: i32 = 259;
+const WAIT_FAILED: u32 = 0xFFFF_FFFF;
+
+/// One rendered value (`EVT_VARIANT`): a value or pointer, a count and a type.
+#[repr(C)]
+#[derive(Clone, Copy)]
+struct EvtVariant {
+    value: u64,
+    count: u32,
+    kind: u32,
+}
+
+#[link(name = "wevtapi")]
+unsafe extern "system" {
+    fn EvtSubscribe(
+        session: Handle,
+        signal_event: Handle,
+        channel_path: *const u16,
+        query: *const u16,
+        bookmark: Handle,
+        context: *const c_void,
+        callback: *const c_void,
+        flags: u32,
+    ) -> Handle;
+    fn EvtNext(
+        result_set: Handle,
+        events_size: u32,
+        events: *mut Handle,
+        timeout: u32,
+        flags: u32,
+        returned: *mut u32,
+    ) -> i32;
+    fn EvtCreateRenderContext(count: u32, value_paths: *const *const u16, flags: u32) -> Handle;
+    fn EvtRender(
+        context: Handle,
+        fragment: Handle,
+        flags: u32,
+        buffer_size: u32,
+        buffer: *mut c_void,
+        buffer_used: *mut u32,
+        property_count: *mut u32,
+    ) -> i32;
+    fn EvtOpenPublisherMetadata(
+        session: Handle,
+        publisher_id: *const u16,
+        log_file_path: *const u16,
+        locale: u32,
+        flags: u32,
+    ) -> Handle;
+    fn EvtFormatMessage(
+        publisher_metadata: Handle,
+        event: Handle,
+        message_id: u32,
+        value_count: u32,
+        values: *const EvtVariant,
+        flags: u32,
+        buffer_size: u32,
+        buffer: *mut u16,
+        buffer_used: *mut u32,
+    ) -> i32;
+    fn EvtClose(object: Handle) -> i32;
+}
+
+#[link(name = "kernel32")]
+unsafe extern "system" {
+    fn CreateEventW(
+        attributes: *const c_void,
+        manual_reset: i32,
+        initial_state: i32,
+        name: *const u16,
+    ) -> Handle;
+    fn ResetEvent(event: Handle) -> i32;
+    fn WaitForSingleObject(handle: Handle, millis: u32) -> u32;
+    fn CloseHandle(handle: Handle) -> i32;
+}
+
+/// `s` as a NUL-terminated UTF-16 string.
+fn wide(s: &str) -> Vec<u16> {
+    s.encode_utf16().chain([0]).collect()
+}
+
+/// The string a NUL-terminated UTF-16 pointer points to.
+///
+/// # Safety
+/// `p` must be null or point to a NUL-terminated UTF-16 string.
+unsafe fn from_wide_ptr(p: *const u16) -> String {
+    if p.is_null() {
+        return String::new();
+    }
+    let mut len = 0;
+    // SAFETY: the string is NUL-terminated, so every unit up to it is valid
+    while unsafe { *p.add(len) } != 0 {
+        len += 1;
+    }
+    // SAFETY: `len` units were just read
+    String::from_utf16_lossy(unsafe { std::slice::from_raw_parts(p, len) })
+}
+
+/// Turn a `BOOL` result into an error carrying `GetLastError`.
+fn check(ok: i32) -> io::Result<()> {
+    if ok == 0 {
+        Err(io::Error::last_os_error())
+    } else {
+        Ok(())
+    }
+}
+
+/// Turn a null handle into an error carrying `GetLastError`.
+fn check_handle(h: Handle) -> io::Result<Handle> {
+    if h == 0 {
+        Err(io::Error::last_os_error())
+    } else {
+        Ok(h)
+    }
+}
+
+/// An Evt* handle, closed on drop.
+struct EvtHandle(Handle);
+
+impl Drop for EvtHandle {
+    fn drop(&mut self) {
+        // SAFETY: the handle is open and not used after this
+        unsafe { EvtClose(self.0) };
+    }
+}
+
+/// A manual-reset event the subscription signals when events arrive.
+struct Signal(Handle);
+
+impl Signal {
+    fn new() -> io::Result<Self> {
+        // SAFETY: null attributes and name are allowed; starts signaled so
+        // the first `EvtNext` runs right away
+        check_handle(unsafe { CreateEventW(ptr::null(), 1, 1, ptr::null()) }).map(Self)
+    }
+
+    /// Sleep until the subscription signals or `timeout` passes.
+    fn wait(&self, timeout: Duration) -> io::Result<()> {
+        // SAFETY: `self.0` is an open event handle
+        unsafe {
+            if WaitForSingleObject(self.0, timeout.as_millis() as u32) == WAIT_FAILED {
+                return Err(io::Error::last_os_error());
+            }
+            check(ResetEvent(self.0))
+        }
+    }
+}
+
+impl Drop for Signal {
+    fn drop(&mut self) {
+        // SAFETY: the handle is open and not used after this
+        unsafe { CloseHandle(self.0) };
+    }
+}
+
+/// Turns events into lines like `Error Application Error[1000]: message`.
+struct Formatter {
+    context: EvtHandle,
+    /// Message tables of the providers seen so far (`None` if unavailable).
+    publishers: HashMap<String, Option<EvtHandle>>,
+}
+
+impl Formatter {
+    fn new() -> io::Result<Self> {
+        // SAFETY: no value paths are passed for a system context
+        let context = check_handle(unsafe {
+            EvtCreateRenderContext(0, ptr::null(), EVT_RENDER_CONTEXT_SYSTEM)
+        })?;
+        Ok(Self {
+            context: EvtHandle(context),
+            publishers: HashMap::new(),
+        })
+    }
+
+    fn format(&mut self, event: Handle) -> io::Result<String> {
+        let values = self.system_values(event)?;
+        let value = |i: usize| values.get(i).copied();
+
+        // SAFETY: string values point into `values`, which is still alive
+        let provider = match value(SYSTEM_PROVIDER_NAME) {
+            Some(v) if v.kind == EVT_VAR_TYPE_STRING => unsafe {
+                from_wide_ptr(v.value as usize as *const u16)
+            },
+            _ => String::new(),
+        };
+        let event_id = match value(SYSTEM_EVENT_ID) {
+            Some(v) if v.kind == EVT_VAR_TYPE_UINT16 => v.value as u16,
+            _ => 0,
+        };
+        let level = match value(SYSTEM_LEVEL) {
+            Some(v) if v.kind == EVT_VAR_TYPE_BYTE => v.value as u8,
+            _ => 0,
+        };
+
+        let message = match self.message(&provider, event) {
+            Some(m) => m,
+            None => render_xml(event)?,
+        };
+        // Messages often span lines; keep each event on one
+        let message = message.split_whitespace().collect::<Vec<_>>().join(" ");
+
+        Ok(format!(
+            "{} {provider}[{event_id}]: {message}",
+            level_name(level)
+        ))
+    }
+
+    /// The event's system properties, as `EvtVariant`s.
+    fn system_values(&self, event: Handle) -> io::Result<Vec<EvtVariant>> {
+        let mut buf: Vec<EvtVariant> = Vec::new();
+        loop {
+            let size = (buf.len() * size_of::<EvtVariant>()) as u32;
+            let (mut used, mut count) = (0u32, 0u32);
+            // SAFETY: `buf` holds `size` writable, suitably aligned bytes
+            let ok = unsafe {
+                EvtRender(
+                    self.context.0,
+                    event,
+                    EVT_RENDER_EVENT_VALUES,
+                    size,
+                    buf.as_mut_ptr().cast(),
+                    &mut used,
+                    &mut count,
+                )
+            };
+            if ok != 0 {
+                buf.truncate(count as usize);
+                return Ok(buf);
+            }
+            let err = io::Error::last_os_error();
+            if err.raw_os_error() != Some(ERROR_INSUFFICIENT_BUFFER) {
+                return Err(err);
+            }
+            let needed = (used as usize).div_ceil(size_of::<EvtVariant>());
+            buf.resize(
+                needed,
+                EvtVariant {
+                    value: 0,
+                    count: 0,
+                    kind: 0,
+                },
+            );
+        }
+    }
+
+    /// The event's message from its provider's message table, if it has one.
+    fn message(&mut self, provider: &str, event: Handle) -> Option<String> {
+        let publisher = self
+            .publishers
+            .entry(provider.to_string())
+            .or_insert_with(|| {
+                let name = wide(provider);
+                // SAFETY: `name` is NUL-terminated; local session and locale
+                let h = unsafe { EvtOpenPublisherMetadata(0, name.as_ptr(), ptr::null(), 0, 0) };
+                (h != 0).then_some(EvtHandle(h))
+            })
+            .as_ref()?;
+
+        let mut buf: Vec<u16> = Vec::new();
+        loop {
+            let mut used = 0u32;
+            // SAFETY: `buf` holds `buf.len()` writable UTF-16 units
+            let ok = unsafe {
+                EvtFormatMessage(
+                    publisher.0,
+                    event,
+                    0,
+                    0,
+                    ptr::null(),
+                    EVT_FORMAT_MESSAGE_EVENT,
+                    buf.len() as u32,
+                    buf.as_mut_ptr(),
+                    &mut used,
+                )
+            };
+            if ok != 0 {
+                let len = buf.iter().position(|&c| c == 0).unwrap_or(buf.len());
+                return Some(String::from_utf16_lossy(&buf[..len]));
+            }
+            if io::Error::last_os_error().raw_os_error() != Some(ERROR_INSUFFICIENT_BUFFER) {
+                return None;
+            }
+            buf.resize(used as usize, 0);
+        }
+    }
+}
+
+/// The event as XML, for events whose provider has no message table.
+fn render_xml(event: Handle) -> io::Result<String> {
+    let mut buf: Vec<u16> = Vec::new();
+    loop {
+        let size = (buf.len() * 2) as u32;
+        let (mut used, mut count) = (0u32, 0u32);
+        // SAFETY: `buf` holds `size` writable bytes
+        let ok = unsafe {
+            EvtRender(
+                0,
+                event,
+                EVT_RENDER_EVENT_XML,
+                size,
+                buf.as_mut_ptr().cast(),
+                &mut used,
+                &mut count,
+            )
+        };
+        if ok != 0 {
+            let len = buf.iter().position(|&c| c == 0).unwrap_or(buf.len());
+            return Ok(String::from_utf16_lossy(&buf[..len]));
+        }
+        let err = io::Error::last_os_error();
+        if err.raw_os_error() != Some(ERROR_INSUFFICIENT_BUFFER) {
+            return Err(err);
+        }
+        buf.resize((used as usize).div_ceil(2), 0);
+    }
+}
+
+/// The Event Viewer name of a level (0, "LogAlways", is shown as information).
+fn level_name(level: u8) -> &'static str {
+    match level {
+        1 => "Critical",
+        2 => "Error",
+        3 => "Warning",
+        5 => "Verbose",
+        _ => "Information",
+    }
+}
+
+/// Read events of `channel` (e.g. `Application`) and pass each one, as a
+/// line, to `emit`, until the existing events are read (or, when
+/// following, until shutdown) or `emit` returns `false`. `started` is
+/// called once the channel is open.
+pub fn read(
+    channel: &str,
+    start_at: StartAt,
+    follow: bool,
+    shutdown: &AtomicBool,
+    started: impl FnOnce(),
+    mut emit: impl FnMut(Vec<u8>) -> bool,
+) -> io::Result<()> {
+    let signal = Signal::new()?;
+    let flags = match start_at {
+        StartAt::Beginning => EVT_SUBSCRIBE_START_AT_OLDEST_RECORD,
+        StartAt::End => EVT_SUBSCRIBE_TO_FUTURE_EVENTS,
+    };
+    let channel_w = wide(channel);
+    let query = wide("*");
+    // SAFETY: the strings are NUL-terminated; with a signal event and no
+    // callback the subscription is pulled with `EvtNext`
+    let subscription = EvtHandle(check_handle(unsafe {
+        EvtSubscribe(
+            0,
+            signal.0,
+            channel_w.as_ptr(),
+            query.as_ptr(),
+            0,
+            ptr::null(),
+            ptr::null(),
+            flags,
+        )
+    })?);
+    let mut formatter = Formatter::new()?;
+    started();
+
+    let mut events = [0 as Handle; BATCH];
+    while !shutdown.load(Ordering::Relaxed) {
+        let mut returned = 0u32;
+        // SAFETY: `events` has room for `BATCH` handles
+        let ok = unsafe {
+            EvtNext(
+                subscription.0,
+                BATCH as u32,
+                events.as_mut_ptr(),
+                0,
+                0,
+                &mut returned,
+            )
+        };
+        if ok == 0 {
+            let err = io::Error::last_os_error();
+            if err.raw_os_error() != Some(ERROR_NO_MORE_ITEMS) {
+                return Err(err);
+            }
+            if !follow {
+                break;
+            }
+            signal.wait(WAIT_INTERVAL)?;
+            continue;
+        }
+
+        // Every returned handle is closed, even after `emit` declines
+        let batch: Vec<EvtHandle> = events[..returned as usize]
+            .iter()
+            .map(|&h| EvtHandle(h))
+            .collect();
+        for event in &batch {
+            let line = formatter.format(event.0)?;
+            if !emit(line.into_bytes()) {
+                return Ok(());
+            }
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,472 @@
+// tests/pipeline.rs
+//! Whole runs over temporary files, with the output captured in memory.
+
+use logscout::{Config, Controls, run_with_config, run_with_controls};
+use std::fs;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex, mpsc};
+use std::thread;
+use std::time::Duration;
+
+/// A writer whose output can still be read after the run has dropped it.
+#[derive(Clone, Default)]
+struct SharedBuf(Arc<Mutex<Vec<u8>>>);
+
+impl SharedBuf {
+    fn lines(&self) -> Vec<String> {
+        let buf = self.0.lock().unwrap();
+        String::from_utf8_lossy(&buf)
+            .lines()
+            .map(String::from)
+            .collect()
+    }
+}
+
+impl Write for SharedBuf {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.0.lock().unwrap().extend_from_slice(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+/// A fresh directory for one test.
+fn scratch_dir(test: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("logscout-{}-{}", test, std::process::id()));
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+/// Write `lines` to `name` in `dir` and return its path.
+fn log_file(dir: &Path, name: &str, lines: impl IntoIterator<Item = String>) -> PathBuf {
+    let path = dir.join(name);
+    let mut text = String::new();
+    for line in lines {
+        text.push_str(&line);
+        text.push('\n');
+    }
+    fs::write(&path, text).unwrap();
+    path
+}
+
+/// Load `yaml` the way the binary does, validation included.
+fn config(dir: &Path, yaml: &str) -> Config {
+    let path = dir.join("config.yaml");
+    fs::write(&path, yaml).unwrap();
+    Config::from_files(&[path]).unwrap()
+}
+
+#[test]
+fn max_output_bytes_stops_the_run() {
+    let dir = scratch_dir("max-output-bytes");
+    let log = log_file(&dir, "app.log", (0..100).map(|i| format!("line {i:03}")));
+    // Each line is written as `[app] line NNN\n`: 15 bytes
+    let cfg = config(
+        &dir,
+        &format!(
+            "follow: false\nmax_output_bytes: 50\nsources:\n  - name: app\n    type: file\n    path: {}\n",
+            log.display()
+        ),
+    );
+
+    let out = SharedBuf::default();
+    let stats = run_with_config(&cfg, out.clone(), Arc::new(AtomicBool::new(false))).unwrap();
+
+    // 45 bytes after three lines, 60 after the fourth
+    assert_eq!(
+        out.lines(),
+        [
+            "[app] line 000",
+            "[app] line 001",
+            "[app] line 002",
+            "[app] line 003"
+        ]
+    );
+    let (_, included, _) = stats.snapshot();
+    assert_eq!(included, 4);
+}
+
+/// A writer whose first `failures` writes fail.
+struct FlakyWriter {
+    failures: u32,
+    out: SharedBuf,
+}
+
+impl Write for FlakyWriter {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        if self.failures > 0 {
+            self.failures -= 1;
+            return Err(io::Error::other("disk on fire"));
+        }
+        self.out.write(buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+/// Run over two lines, `one` and `two`, with an output whose first
+/// `failures` writes fail under `on_error: policy`.
+fn run_flaky(test: &str, policy: &str, failures: u32) -> (Vec<String>, u64) {
+    let dir = scratch_dir(test);
+    let log = log_file(&dir, "app.log", ["one".to_string(), "two".to_string()]);
+    let cfg = config(
+        &dir,
+        &format!(
+            "follow: false\noutput:\n  flush_lines: 1\n  on_error: {policy}\nsources:\n  - name: app\n    type: file\n    path: {}\n",
+            log.display()
+        ),
+    );
+
+    let out = SharedBuf::default();
+    let writer = FlakyWriter {
+        failures,
+        out: out.clone(),
+    };
+    let stats = run_with_config(&cfg, writer, Arc::new(AtomicBool::new(false))).unwrap();
+    let (total, _, _) = stats.snapshot();
+    (out.lines(), total)
+}
+
+#[test]
+fn on_error_stop_ends_the_run() {
+    let (lines, total) = run_flaky("on-error-stop", "stop", u32::MAX);
+    assert!(lines.is_empty());
+    assert_eq!(total, 1);
+}
+
+#[test]
+fn on_error_drop_skips_the_failed_line() {
+    let (lines, _) = run_flaky("on-error-drop", "drop", 1);
+    assert_eq!(lines, ["[app] two"]);
+}
+
+#[test]
+fn on_error_retry_writes_the_line_once() {
+    let (lines, _) = run_flaky("on-error-retry", "retry", 2);
+    assert_eq!(lines, ["[app] one", "[app] two"]);
+}
+
+#[test]
+fn on_error_block_writes_the_line_once() {
+    let (lines, _) = run_flaky("on-error-block", "block", 5);
+    assert_eq!(lines, ["[app] one", "[app] two"]);
+}
+
+#[test]
+fn the_final_flush_follows_on_error() {
+    let dir = scratch_dir("final-flush");
+    let log = log_file(&dir, "app.log", ["one".to_string(), "two".to_string()]);
+    // Nothing is flushed before the end of the run
+    let cfg = config(
+        &dir,
+        &format!(
+            "follow: false\noutput:\n  flush_lines: 100\n  flush_interval_ms: 60000\n  on_error: retry\nsources:\n  - name: app\n    type: file\n    path: {}\n",
+            log.display()
+        ),
+    );
+
+    let out = SharedBuf::default();
+    let writer = FlakyWriter {
+        failures: 1,
+        out: out.clone(),
+    };
+    run_with_config(&cfg, writer, Arc::new(AtomicBool::new(false))).unwrap();
+    assert_eq!(out.lines(), ["[app] one", "[app] two"]);
+}
+
+#[test]
+fn on_error_disable_stops_writing() {
+    let (lines, total) = run_flaky("on-error-disable", "disable", 1);
+    assert!(lines.is_empty());
+    assert_eq!(total, 2);
+}
+
+#[cfg(unix)]
+#[test]
+fn stderr_lines_keep_the_source_name() {
+    let dir = scratch_dir("stderr-source");
+    let cfg = config(
+        &dir,
+        "follow: false\nsources:\n  - name: app\n    type: command\n    command: sh\n    args: ['-c', 'echo out; echo err >&2']\n    capture_stderr: true\n    label: APP\n",
+    );
+
+    let out = SharedBuf::default();
+    let stats = run_with_config(&cfg, out.clone(), Arc::new(AtomicBool::new(false))).unwrap();
+
+    // The label applies to both streams; stderr is marked as such
+    let mut lines = out.lines();
+    lines.sort();
+    assert_eq!(lines, ["[APP:stderr] err", "[APP] out"]);
+    let per_source = stats.summary().per_source;
+    assert_eq!(per_source.keys().collect::<Vec<_>>(), ["app"]);
+    assert_eq!(per_source["app"].total, 2);
+}
+
+#[test]
+fn finite_sources_end_the_run() {
+    let dir = scratch_dir("finite-sources");
+    let a = log_file(&dir, "a.log", ["a1".to_string(), "a2".to_string()]);
+    let b = log_file(&dir, "b.log", ["b1".to_string()]);
+    let cfg = config(
+        &dir,
+        &format!(
+            "follow: false\nsources:\n  - name: a\n    type: file\n    path: {}\n  - name: b\n    type: file\n    path: {}\n",
+            a.display(),
+            b.display()
+        ),
+    );
+
+    // Returns on its own once both readers are done, without a shutdown
+    let out = SharedBuf::default();
+    let (done, finished) = mpsc::channel();
+    let writer = out.clone();
+    thread::spawn(move || {
+        let _ = done.send(run_with_config(
+            &cfg,
+            writer,
+            Arc::new(AtomicBool::new(false)),
+        ));
+    });
+    let stats = finished
+        .recv_timeout(Duration::from_secs(10))
+        .expect("run did not end after its sources")
+        .unwrap();
+
+    let mut lines = out.lines();
+    lines.sort();
+    assert_eq!(lines, ["[a] a1", "[a] a2", "[b] b1"]);
+    assert_eq!(stats.snapshot().0, 3);
+}
+
+#[cfg(unix)]
+#[test]
+fn count_through_counts_without_printing() {
+    let dir = scratch_dir("count-through");
+    // Prints its second line only once the test says so
+    let go = dir.join("go");
+    let script = format!(
+        "echo hidden; while [ ! -e '{}' ]; do sleep 0.05; done; echo shown",
+        go.display()
+    );
+    let cfg = config(
+        &dir,
+        &format!(
+            "follow: false\nsources:\n  - name: app\n    type: command\n    command: sh\n    args: ['-c', \"{script}\"]\n"
+        ),
+    );
+
+    let controls = Controls::default();
+    controls.count_through.store(true, Ordering::Relaxed);
+    let count_through = controls.count_through.clone();
+    let out = SharedBuf::default();
+    let writer = out.clone();
+    let run = thread::spawn(move || run_with_controls(&cfg, writer, controls));
+
+    // Give the consumer time to take the first line while counting through
+    thread::sleep(Duration::from_secs(1));
+    assert!(out.lines().is_empty());
+    count_through.store(false, Ordering::Relaxed);
+    fs::write(&go, "").unwrap();
+
+    let stats = run.join().unwrap().unwrap();
+    assert_eq!(out.lines(), ["[app] shown"]);
+    // Both lines were counted, whether shown or not
+    let (total, included, _) = stats.snapshot();
+    assert_eq!((total, included), (2, 2));
+}
+
+#[test]
+fn skip_empty_lines_drops_blank_lines() {
+    let dir = scratch_dir("skip-empty");
+    let log = log_file(
+        &dir,
+        "app.log",
+        ["one", "", "  ", "two", "\t", "three", ""].map(String::from),
+    );
+    let run = |skip: bool| {
+        let cfg = config(
+            &dir,
+            &format!(
+                "follow: false\nskip_empty_lines: {skip}\nsources:\n  - name: app\n    type: file\n    path: {}\n",
+                log.display()
+            ),
+        );
+        let out = SharedBuf::default();
+        let stats = run_with_config(&cfg, out.clone(), Arc::new(AtomicBool::new(false))).unwrap();
+        (out.lines(), stats)
+    };
+
+    let (lines, stats) = run(true);
+    assert_eq!(lines, ["[app] one", "[app] two", "[app] three"]);
+    assert_eq!(stats.skipped_empty(), 4);
+    // Still read, but neither included nor excluded
+    assert_eq!(stats.snapshot(), (7, 3, 0));
+
+    // Without it, blank lines are written like any other
+    let (lines, stats) = run(false);
+    assert_eq!(lines.len(), 7);
+    assert_eq!(stats.skipped_empty(), 0);
+}
+
+#[test]
+fn quiet_counts_like_a_normal_run() {
+    let dir = scratch_dir("quiet-dedup");
+    let log = log_file(&dir, "app.log", ["a", "a", "b"].map(String::from));
+    let raw = dir.join("raw.log");
+    let run = |quiet: bool| {
+        let cfg = config(
+            &dir,
+            &format!(
+                "follow: false\nquiet: {quiet}\ndedup_window_secs: 60\nraw_output:\n  path: {}\nsources:\n  - name: app\n    type: file\n    path: {}\n",
+                raw.display(),
+                log.display()
+            ),
+        );
+        let out = SharedBuf::default();
+        let stats = run_with_config(&cfg, out.clone(), Arc::new(AtomicBool::new(false))).unwrap();
+        (out.lines(), stats.snapshot(), stats.duplicates())
+    };
+
+    let (shown, normal, duplicates) = run(false);
+    assert_eq!(shown, ["[app] a", "[app] b"]);
+    assert_eq!((normal, duplicates), ((3, 2, 0), 1));
+
+    // The duplicate is not counted as included, and only the main output is silent
+    fs::remove_file(&raw).unwrap();
+    let (shown, quiet, duplicates) = run(true);
+    assert!(shown.is_empty());
+    assert_eq!((quiet, duplicates), (normal, 1));
+    assert_eq!(fs::read_to_string(&raw).unwrap().lines().count(), 3);
+}
+
+/// Run over one line, `login alice@example.com`, with email redaction and
+/// these extra top-level settings.
+fn run_redacted(test: &str, settings: &str) -> Vec<String> {
+    let dir = scratch_dir(test);
+    let log = log_file(&dir, "app.log", ["login alice@example.com".to_string()]);
+    let cfg = config(
+        &dir,
+        &format!(
+            "follow: false\nredact:\n  builtin: [email]\n{settings}sources:\n  - name: app\n    type: file\n    path: {}\n",
+            log.display()
+        ),
+    );
+    let out = SharedBuf::default();
+    run_with_config(&cfg, out.clone(), Arc::new(AtomicBool::new(false))).unwrap();
+    out.lines()
+}
+
+#[test]
+fn redaction_applies_to_text_output() {
+    assert_eq!(
+        run_redacted("redact-text", ""),
+        ["[app] login [REDACTED:email]"]
+    );
+}
+
+#[test]
+fn redaction_applies_to_json_output() {
+    let lines = run_redacted("redact-json", "output:\n  format: json\n");
+    assert_eq!(lines.len(), 1);
+    assert!(!lines[0].contains("alice"), "{}", lines[0]);
+    let record: serde_json::Value = serde_json::from_str(&lines[0]).unwrap();
+    assert_eq!(record["line"], "login [REDACTED:email]");
+}
+
+#[test]
+fn config_fragments_merge_into_one_run() {
+    let dir = scratch_dir("config-dir");
+    let lines = || ["ERROR a", "WARN b", "INFO c"].map(String::from);
+    let app = log_file(&dir, "app.log", lines());
+    let db = log_file(&dir, "db.log", lines());
+    let conf = dir.join("conf.d");
+    fs::create_dir_all(&conf).unwrap();
+    let fragment = |name: &str, pattern: &str, source: &str, path: &Path| {
+        let yaml = format!(
+            "include: [{pattern}]\nsources:\n  - name: {source}\n    type: file\n    path: {}\n",
+            path.display()
+        );
+        fs::write(conf.join(name), yaml).unwrap();
+    };
+    fragment("10-app.yaml", "ERROR", "app", &app);
+    fs::write(conf.join("00-base.yaml"), "follow: false\n").unwrap();
+    fragment("20-db.yaml", "WARN", "db", &db);
+
+    // Both sources are read, and both include lists apply to each
+    let cfg = Config::from_files(std::slice::from_ref(&conf)).unwrap();
+    assert_eq!(cfg.include, ["ERROR", "WARN"]);
+    let out = SharedBuf::default();
+    run_with_config(&cfg, out.clone(), Arc::new(AtomicBool::new(false))).unwrap();
+    let mut lines = out.lines();
+    lines.sort();
+    assert_eq!(
+        lines,
+        [
+            "[app] ERROR a",
+            "[app] WARN b",
+            "[db] ERROR a",
+            "[db] WARN b"
+        ]
+    );
+
+    // A source name may only be defined by one fragment
+    fragment("30-again.yaml", "INFO", "app", &app);
+    let err = Config::from_files(&[conf]).unwrap_err().to_string();
+    assert!(err.contains("Source `app` is defined in both"), "{err}");
+}
+
+/// A writer that keeps every `write` call separately.
+#[derive(Clone, Default)]
+struct WriteCalls(Arc<Mutex<Vec<Vec<u8>>>>);
+
+impl Write for WriteCalls {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.0.lock().unwrap().push(buf.to_vec());
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+#[test]
+fn busy_sources_never_split_a_record() {
+    let dir = scratch_dir("no-split");
+    let records = 2000;
+    let mut yaml = "follow: false\nsources:\n".to_string();
+    for s in 0..4 {
+        let name = format!("s{s}");
+        let lines =
+            (0..records).flat_map(|i| [format!("{name} record {i}"), format!("  detail {i}")]);
+        let path = log_file(&dir, &format!("{name}.log"), lines);
+        yaml += &format!(
+            "  - name: {name}\n    type: file\n    path: {}\n    multiline:\n      continuation: indented\n",
+            path.display()
+        );
+    }
+    let cfg = config(&dir, &yaml);
+
+    let out = WriteCalls::default();
+    run_with_config(&cfg, out.clone(), Arc::new(AtomicBool::new(false))).unwrap();
+
+    // Every write is one whole two-line record, in order within its source
+    let calls = out.0.lock().unwrap();
+    assert_eq!(calls.len(), 4 * records);
+    let mut next = [0; 4];
+    for call in calls.iter() {
+        let text = String::from_utf8_lossy(call);
+        let s: usize = text[2..3].parse().unwrap();
+        let i = next[s];
+        assert_eq!(text, format!("[s{s}] s{s} record {i}\n  detail {i}\n"));
+        next[s] += 1;
+    }
+}